@@ -36,6 +36,9 @@ CREATE TABLE game_ratings (
     deviation_b REAL NOT NULL,
     winner INTEGER NOT NULL,
     valid BOOLEAN NOT NULL, 
+    -- Character ranks at the time, for the matchup tiers when a game is re-rated
+    rank_a INTEGER,
+    rank_b INTEGER,
     PRIMARY KEY (timestamp, id_a, id_b, winner)
 );
 
@@ -54,9 +57,16 @@ CREATE TABLE players  (
 CREATE TABLE player_names (
     id INTEGER NOT NULL,
     name TEXT NOT NULL,
+    first_seen INTEGER NOT NULL,
+    last_seen INTEGER NOT NULL,
     PRIMARY KEY(id, name)
 );
 
+-- Every name in player_names folded for loose searching, see search.rs
+CREATE VIRTUAL TABLE player_search USING fts5(folded, id UNINDEXED, name UNINDEXED, tokenize = 'trigram');
+-- The same names split into folded words for search suggestions
+CREATE VIRTUAL TABLE player_autocomplete USING fts5(words, id UNINDEXED, name UNINDEXED, prefix = '2 3');
+
 CREATE TABLE player_ratings (
     id INTEGER NOT NULL,
     char_id INTEGER NOT NULL,
@@ -105,44 +115,71 @@ CREATE TABLE player_matchups (
     PRIMARY KEY(id, char_id, opp_char_id)
 );
 
-CREATE TABLE global_matchups(
+-- Each row defines one of the tables on the matchups page. A game counts towards a
+-- tier when both players pass every condition that is set:
+--   max_rank      character rank at most this, or popularity * max_rank if proportional
+--   min_rating    rating (with a settled deviation) in [min_rating, max_rating)
+--   min_floor     game played on this floor or higher, 99 is celestial
+-- New tiers only start filling up with games rated after they are added.
+CREATE TABLE matchup_tiers(
+    tier_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL,
+    max_rank INTEGER,
+    proportional INTEGER NOT NULL DEFAULT 0,
+    min_rating REAL,
+    max_rating REAL,
+    min_floor INTEGER,
+    PRIMARY KEY(tier_id)
+);
+
+INSERT INTO matchup_tiers VALUES(1, 'Global matchups', '', NULL, 0, NULL, NULL, NULL);
+INSERT INTO matchup_tiers VALUES(2, 'Top 1000', 'This table is based on games between top 1000 (character) players', 1000, 0, NULL, NULL, NULL);
+INSERT INTO matchup_tiers VALUES(3, 'Proportional', 'This is the top 1000 but multiplied out by popularity. For example, if Sol has 15% popularity the top 150 players are counted.', 1000, 1, NULL, NULL, NULL);
+INSERT INTO matchup_tiers VALUES(4, 'Top 100', 'This table is based on games between top 100 (character) players', 100, 0, NULL, NULL, NULL);
+
+CREATE TABLE matchups(
+    tier_id INTEGER NOT NULL,
     char_id INTEGER NOT NULL,
     opp_char_id INTEGER NOT NULL,
     rating_value REAL NOT NULL,
     rating_deviation REAL NOT NULL,
     wins INTEGER NOT NULL,
     losses INTEGER NOT NULL,
-    PRIMARY KEY(char_id, opp_char_id)
+    PRIMARY KEY(tier_id, char_id, opp_char_id)
 );
 
-CREATE TABLE top_1000_matchups(
+-- Rating adjusted matchup estimates, refreshed every statistics period. advantage is how
+-- many rating points the character is worth in the matchup, std_error its standard error.
+CREATE TABLE matchup_estimates(
     char_id INTEGER NOT NULL,
     opp_char_id INTEGER NOT NULL,
-    rating_value REAL NOT NULL,
-    rating_deviation REAL NOT NULL,
+    game_count INTEGER NOT NULL,
     wins INTEGER NOT NULL,
-    losses INTEGER NOT NULL,
+    advantage REAL NOT NULL,
+    std_error REAL NOT NULL,
     PRIMARY KEY(char_id, opp_char_id)
 );
 
-CREATE TABLE top_100_matchups(
+-- The same for each rating bracket of character_popularity_rating, from the games where the
+-- character's player was rated within the bracket.
+CREATE TABLE matchup_estimates_rating(
+    rating_bracket INTEGER NOT NULL,
     char_id INTEGER NOT NULL,
     opp_char_id INTEGER NOT NULL,
-    rating_value REAL NOT NULL,
-    rating_deviation REAL NOT NULL,
+    game_count INTEGER NOT NULL,
     wins INTEGER NOT NULL,
-    losses INTEGER NOT NULL,
-    PRIMARY KEY(char_id, opp_char_id)
+    advantage REAL NOT NULL,
+    std_error REAL NOT NULL,
+    PRIMARY KEY(rating_bracket, char_id, opp_char_id)
 );
 
-CREATE TABLE proportional_matchups(
+CREATE TABLE tier_list(
+    rating_bracket INTEGER NOT NULL,
     char_id INTEGER NOT NULL,
-    opp_char_id INTEGER NOT NULL,
-    rating_value REAL NOT NULL,
-    rating_deviation REAL NOT NULL,
-    wins INTEGER NOT NULL,
-    losses INTEGER NOT NULL,
-    PRIMARY KEY(char_id, opp_char_id)
+    win_rate REAL NOT NULL,
+    fraud_offset REAL,
+    PRIMARY KEY(rating_bracket, char_id)
 );
 
 CREATE TABLE player_floor_distribution(
@@ -208,13 +245,28 @@ CREATE TABLE fraud_index_highest_rated (
     PRIMARY KEY(char_id)
 );
 
+-- Who currently gets a badge, rebuilt from supporters by rater::update_vip_status.
+-- vip_status is the badge text.
 CREATE TABLE vip_status (
     id INTEGER NOT NULL,
     vip_status TEXT NOT NULL,
     notes TEXT NOT NULL,
+    badge_colour TEXT,
     PRIMARY KEY(id)
 );
 
+-- Every supporter period, expiry_timestamp is NULL for ones that last until ended by hand.
+CREATE TABLE supporters (
+    id INTEGER NOT NULL,
+    tier TEXT NOT NULL,
+    start_timestamp INTEGER NOT NULL,
+    expiry_timestamp INTEGER,
+    badge_text TEXT,
+    badge_colour TEXT,
+    notes TEXT NOT NULL,
+    PRIMARY KEY(id, start_timestamp)
+);
+
 CREATE TABLE cheater_status (
     id INTEGER NOT NULL,
     cheater_status TEXT NOT NULL,
@@ -222,15 +274,157 @@ CREATE TABLE cheater_status (
     PRIMARY KEY(id)
 );
 
+CREATE TABLE cheater_log (
+    log_id INTEGER PRIMARY KEY,
+    timestamp INTEGER NOT NULL,
+    id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    cheater_status TEXT,
+    moderator TEXT NOT NULL,
+    notes TEXT NOT NULL
+);
+
+CREATE INDEX cheater_log_id ON cheater_log(id);
+
+-- How much each character's rating moved when the re-rate after a marking or unmarking ran,
+-- log_id is the cheater_log row that caused it.
+CREATE TABLE cheater_offsets (
+    log_id INTEGER NOT NULL,
+    id INTEGER NOT NULL,
+    char_id INTEGER NOT NULL,
+    offset REAL NOT NULL,
+    PRIMARY KEY(log_id, id, char_id)
+);
+
+-- Players whose cheater status changed on the admin pages, the rater re-rates their opponents.
+CREATE TABLE pending_rerates (
+    id INTEGER NOT NULL,
+    requested INTEGER NOT NULL,
+    PRIMARY KEY(id)
+);
+
+-- Review queue filled by rater::detect_suspicious_accounts. status is 'open' or 'dismissed',
+-- nothing in here is acted on automatically.
+CREATE TABLE suspicious_accounts (
+    id INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    score REAL NOT NULL,
+    details TEXT NOT NULL,
+    flagged_at INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    PRIMARY KEY(id, reason)
+);
+
+-- Suspected win trading filled by rater::detect_trading_pairs. status is 'open', 'dismissed' or
+-- 'excluded'; games between an excluded pair are not rated.
+CREATE TABLE trading_pairs (
+    winner_id INTEGER NOT NULL,
+    loser_id INTEGER NOT NULL,
+    games INTEGER NOT NULL,
+    sets INTEGER NOT NULL,
+    wins INTEGER NOT NULL,
+    expected_games REAL NOT NULL,
+    rating_transferred REAL NOT NULL,
+    first_timestamp INTEGER NOT NULL,
+    last_timestamp INTEGER NOT NULL,
+    flagged_at INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    PRIMARY KEY(winner_id, loser_id)
+);
+
+-- One row per hidden player, hidden_status is always 'hidden'.
 CREATE TABLE hidden_status (
     id INTEGER NOT NULL,
-    hidden_status TEXT,
+    hidden_status TEXT NOT NULL,
     notes TEXT NOT NULL,
+    PRIMARY KEY(id)
+);
+
+-- Self-service hiding, see api::start_hide_player. state is 'unhidden', 'pending' or 'hidden'.
+-- While pending the player has until code_expiry to put the code in their R-Code comment,
+-- after which they go back to previous_state.
+CREATE TABLE hide_requests (
+    id INTEGER NOT NULL,
+    state TEXT NOT NULL,
+    previous_state TEXT NOT NULL,
     code TEXT,
+    code_expiry INTEGER,
+    last_request INTEGER NOT NULL,
+    last_poll INTEGER,
+    PRIMARY KEY(id)
+);
+
+-- Every change of hide state. source is 'player', 'expired', 'admin' or 'cli'.
+CREATE TABLE hide_log (
+    timestamp INTEGER NOT NULL,
+    id INTEGER NOT NULL,
+    from_state TEXT NOT NULL,
+    to_state TEXT NOT NULL,
+    source TEXT NOT NULL
+);
+
+CREATE INDEX hide_log_id ON hide_log(id);
+
+-- Who changed a player's VIP or hidden status by hand, cheater markings go to cheater_log.
+-- action is 'vip', 'end_vip', 'hide' or 'unhide', moderator is 'cli' for the commands.
+CREATE TABLE moderation_log (
+    timestamp INTEGER NOT NULL,
+    id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    moderator TEXT NOT NULL,
+    notes TEXT NOT NULL
+);
+
+CREATE INDEX moderation_log_id ON moderation_log(id);
+
+-- Codes for api::download_player_export, deleted once the export is handed out.
+CREATE TABLE export_requests (
+    id INTEGER NOT NULL,
+    code TEXT NOT NULL,
+    code_expiry INTEGER NOT NULL,
+    last_request INTEGER NOT NULL,
+    last_poll INTEGER,
     PRIMARY KEY(id)
 );
 
 
+-- Seasons split the rating history at balance patches. end_timestamp is NULL for
+-- the ongoing season. soft_reset is the fraction of a player's distance from 1500
+-- that is carried into the season, NULL means everyone starts from scratch.
+CREATE TABLE seasons (
+    season_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    start_timestamp INTEGER NOT NULL,
+    end_timestamp INTEGER,
+    soft_reset REAL,
+    PRIMARY KEY(season_id)
+);
+
+CREATE TABLE season_player_ratings (
+    season_id INTEGER NOT NULL,
+    id INTEGER NOT NULL,
+    char_id INTEGER NOT NULL,
+    wins INTEGER NOT NULL,
+    losses INTEGER NOT NULL,
+    value REAL NOT NULL,
+    deviation REAL NOT NULL,
+    last_decay INTEGER NOT NULL,
+    PRIMARY KEY(season_id, id, char_id)
+);
+
+CREATE INDEX season_player_value ON season_player_ratings(season_id, value);
+
+CREATE TABLE season_matchups(
+    season_id INTEGER NOT NULL,
+    char_id INTEGER NOT NULL,
+    opp_char_id INTEGER NOT NULL,
+    rating_value REAL NOT NULL,
+    rating_deviation REAL NOT NULL,
+    wins INTEGER NOT NULL,
+    losses INTEGER NOT NULL,
+    PRIMARY KEY(season_id, char_id, opp_char_id)
+);
+
 CREATE TABLE config (
     last_update INTEGER NOT NULL
 );
@@ -0,0 +1,17 @@
+-- Adds the log of cheater markings and unmarkings, run migrate_cheater_offsets.sql after it.
+-- sqlite3 ratings.sqlite < migrate_cheater_log.sql
+BEGIN;
+
+CREATE TABLE cheater_log (
+    log_id INTEGER PRIMARY KEY,
+    timestamp INTEGER NOT NULL,
+    id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    cheater_status TEXT,
+    moderator TEXT NOT NULL,
+    notes TEXT NOT NULL
+);
+
+CREATE INDEX cheater_log_id ON cheater_log(id);
+
+COMMIT;
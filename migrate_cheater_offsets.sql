@@ -0,0 +1,29 @@
+-- Numbers the cheater_log rows and adds the table of rating moves each marking or unmarking caused.
+-- sqlite3 ratings.sqlite < migrate_cheater_offsets.sql
+BEGIN;
+
+CREATE TABLE cheater_log_new (
+    log_id INTEGER PRIMARY KEY,
+    timestamp INTEGER NOT NULL,
+    id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    cheater_status TEXT,
+    moderator TEXT NOT NULL,
+    notes TEXT NOT NULL
+);
+INSERT INTO cheater_log_new(timestamp, id, action, cheater_status, moderator, notes)
+    SELECT timestamp, id, action, cheater_status, moderator, notes FROM cheater_log ORDER BY rowid;
+DROP TABLE cheater_log;
+ALTER TABLE cheater_log_new RENAME TO cheater_log;
+CREATE INDEX cheater_log_id ON cheater_log(id);
+
+-- Re-rates from before this weren't recorded, those log rows stay without offsets
+CREATE TABLE cheater_offsets (
+    log_id INTEGER NOT NULL,
+    id INTEGER NOT NULL,
+    char_id INTEGER NOT NULL,
+    offset REAL NOT NULL,
+    PRIMARY KEY(log_id, id, char_id)
+);
+
+COMMIT;
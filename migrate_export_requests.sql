@@ -0,0 +1,10 @@
+-- Adds the codes players use to download their own data export.
+-- sqlite3 ratings.sqlite < migrate_export_requests.sql
+CREATE TABLE export_requests (
+    id INTEGER NOT NULL,
+    code TEXT NOT NULL,
+    code_expiry INTEGER NOT NULL,
+    last_request INTEGER NOT NULL,
+    last_poll INTEGER,
+    PRIMARY KEY(id)
+);
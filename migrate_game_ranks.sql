@@ -0,0 +1,5 @@
+-- Keeps the character ranks each game was rated with, so re-rating a game can add it to or
+-- remove it from the same matchup tiers. Games rated before this fall back to current ranks.
+-- sqlite3 ratings.sqlite < migrate_game_ranks.sql
+ALTER TABLE game_ratings ADD COLUMN rank_a INTEGER;
+ALTER TABLE game_ratings ADD COLUMN rank_b INTEGER;
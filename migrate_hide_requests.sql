@@ -0,0 +1,40 @@
+-- Moves pending hide codes out of hidden_status into hide_requests, leaving only hidden players.
+-- sqlite3 ratings.sqlite < migrate_hide_requests.sql
+BEGIN;
+
+CREATE TABLE hide_requests (
+    id INTEGER NOT NULL,
+    state TEXT NOT NULL,
+    previous_state TEXT NOT NULL,
+    code TEXT,
+    code_expiry INTEGER,
+    last_request INTEGER NOT NULL,
+    last_poll INTEGER,
+    PRIMARY KEY(id)
+);
+
+CREATE TABLE hide_log (
+    timestamp INTEGER NOT NULL,
+    id INTEGER NOT NULL,
+    from_state TEXT NOT NULL,
+    to_state TEXT NOT NULL,
+    source TEXT NOT NULL
+);
+
+CREATE INDEX hide_log_id ON hide_log(id);
+
+-- Outstanding codes are dropped, players can ask for a new one
+DELETE FROM hidden_status WHERE hidden_status IS NULL;
+UPDATE hidden_status SET hidden_status = 'hidden';
+
+CREATE TABLE hidden_status_new (
+    id INTEGER NOT NULL,
+    hidden_status TEXT NOT NULL,
+    notes TEXT NOT NULL,
+    PRIMARY KEY(id)
+);
+INSERT INTO hidden_status_new SELECT id, hidden_status, notes FROM hidden_status;
+DROP TABLE hidden_status;
+ALTER TABLE hidden_status_new RENAME TO hidden_status;
+
+COMMIT;
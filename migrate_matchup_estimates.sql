@@ -0,0 +1,11 @@
+-- Adds the rating adjusted matchup estimates, they are filled in on the next statistics update.
+-- sqlite3 ratings.sqlite < migrate_matchup_estimates.sql
+CREATE TABLE matchup_estimates(
+    char_id INTEGER NOT NULL,
+    opp_char_id INTEGER NOT NULL,
+    game_count INTEGER NOT NULL,
+    wins INTEGER NOT NULL,
+    advantage REAL NOT NULL,
+    std_error REAL NOT NULL,
+    PRIMARY KEY(char_id, opp_char_id)
+);
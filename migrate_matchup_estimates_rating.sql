@@ -0,0 +1,13 @@
+-- Adds the matchup estimates of each rating bracket used by the tier list, they are filled in
+-- on the next statistics update.
+-- sqlite3 ratings.sqlite < migrate_matchup_estimates_rating.sql
+CREATE TABLE matchup_estimates_rating(
+    rating_bracket INTEGER NOT NULL,
+    char_id INTEGER NOT NULL,
+    opp_char_id INTEGER NOT NULL,
+    game_count INTEGER NOT NULL,
+    wins INTEGER NOT NULL,
+    advantage REAL NOT NULL,
+    std_error REAL NOT NULL,
+    PRIMARY KEY(rating_bracket, char_id, opp_char_id)
+);
@@ -0,0 +1,43 @@
+-- Moves an existing database from the four per-tier matchup tables to matchup_tiers/matchups.
+-- sqlite3 ratings.sqlite < migrate_matchup_tiers.sql
+BEGIN;
+
+CREATE TABLE matchup_tiers(
+    tier_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL,
+    max_rank INTEGER,
+    proportional INTEGER NOT NULL DEFAULT 0,
+    min_rating REAL,
+    max_rating REAL,
+    min_floor INTEGER,
+    PRIMARY KEY(tier_id)
+);
+
+INSERT INTO matchup_tiers VALUES(1, 'Global matchups', '', NULL, 0, NULL, NULL, NULL);
+INSERT INTO matchup_tiers VALUES(2, 'Top 1000', 'This table is based on games between top 1000 (character) players', 1000, 0, NULL, NULL, NULL);
+INSERT INTO matchup_tiers VALUES(3, 'Proportional', 'This is the top 1000 but multiplied out by popularity. For example, if Sol has 15% popularity the top 150 players are counted.', 1000, 1, NULL, NULL, NULL);
+INSERT INTO matchup_tiers VALUES(4, 'Top 100', 'This table is based on games between top 100 (character) players', 100, 0, NULL, NULL, NULL);
+
+CREATE TABLE matchups(
+    tier_id INTEGER NOT NULL,
+    char_id INTEGER NOT NULL,
+    opp_char_id INTEGER NOT NULL,
+    rating_value REAL NOT NULL,
+    rating_deviation REAL NOT NULL,
+    wins INTEGER NOT NULL,
+    losses INTEGER NOT NULL,
+    PRIMARY KEY(tier_id, char_id, opp_char_id)
+);
+
+INSERT INTO matchups SELECT 1, * FROM global_matchups;
+INSERT INTO matchups SELECT 2, * FROM top_1000_matchups;
+INSERT INTO matchups SELECT 3, * FROM proportional_matchups;
+INSERT INTO matchups SELECT 4, * FROM top_100_matchups;
+
+DROP TABLE global_matchups;
+DROP TABLE top_1000_matchups;
+DROP TABLE proportional_matchups;
+DROP TABLE top_100_matchups;
+
+COMMIT;
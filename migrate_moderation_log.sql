@@ -0,0 +1,15 @@
+-- Adds the log of VIP and hide changes made by moderators.
+-- sqlite3 ratings.sqlite < migrate_moderation_log.sql
+BEGIN;
+
+CREATE TABLE moderation_log (
+    timestamp INTEGER NOT NULL,
+    id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    moderator TEXT NOT NULL,
+    notes TEXT NOT NULL
+);
+
+CREATE INDEX moderation_log_id ON moderation_log(id);
+
+COMMIT;
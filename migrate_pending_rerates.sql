@@ -0,0 +1,7 @@
+-- Adds the queue of re-rates requested from the admin pages.
+-- sqlite3 ratings.sqlite < migrate_pending_rerates.sql
+CREATE TABLE pending_rerates (
+    id INTEGER NOT NULL,
+    requested INTEGER NOT NULL,
+    PRIMARY KEY(id)
+);
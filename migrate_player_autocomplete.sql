@@ -0,0 +1,3 @@
+-- Adds the search suggestion index, fill it afterwards with `cargo run search_index`.
+-- sqlite3 ratings.sqlite < migrate_player_autocomplete.sql
+CREATE VIRTUAL TABLE player_autocomplete USING fts5(words, id UNINDEXED, name UNINDEXED, prefix = '2 3');
@@ -0,0 +1,26 @@
+-- Adds first/last seen timestamps to the names of an existing database.
+-- sqlite3 ratings.sqlite < migrate_player_names.sql
+BEGIN;
+
+ALTER TABLE player_names ADD COLUMN first_seen INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE player_names ADD COLUMN last_seen INTEGER NOT NULL DEFAULT 0;
+
+-- Names without any games left to date them by stay at 0, they are shown as unknown until
+-- the name is seen again.
+UPDATE player_names SET
+    first_seen = COALESCE((
+        SELECT MIN(timestamp) FROM (
+            SELECT timestamp FROM games WHERE id_a = player_names.id AND name_a = player_names.name
+            UNION ALL
+            SELECT timestamp FROM games WHERE id_b = player_names.id AND name_b = player_names.name
+        )
+    ), 0),
+    last_seen = COALESCE((
+        SELECT MAX(timestamp) FROM (
+            SELECT timestamp FROM games WHERE id_a = player_names.id AND name_a = player_names.name
+            UNION ALL
+            SELECT timestamp FROM games WHERE id_b = player_names.id AND name_b = player_names.name
+        )
+    ), 0);
+
+COMMIT;
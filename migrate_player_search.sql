@@ -0,0 +1,3 @@
+-- Adds the player search index, fill it afterwards with `cargo run search_index`.
+-- sqlite3 ratings.sqlite < migrate_player_search.sql
+CREATE VIRTUAL TABLE player_search USING fts5(folded, id UNINDEXED, name UNINDEXED, tokenize = 'trigram');
@@ -0,0 +1,39 @@
+-- Adds seasons with their own player ratings and matchups, the rater needs these tables to update.
+-- sqlite3 ratings.sqlite < migrate_seasons.sql
+BEGIN;
+
+CREATE TABLE seasons (
+    season_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    start_timestamp INTEGER NOT NULL,
+    end_timestamp INTEGER,
+    soft_reset REAL,
+    PRIMARY KEY(season_id)
+);
+
+CREATE TABLE season_player_ratings (
+    season_id INTEGER NOT NULL,
+    id INTEGER NOT NULL,
+    char_id INTEGER NOT NULL,
+    wins INTEGER NOT NULL,
+    losses INTEGER NOT NULL,
+    value REAL NOT NULL,
+    deviation REAL NOT NULL,
+    last_decay INTEGER NOT NULL,
+    PRIMARY KEY(season_id, id, char_id)
+);
+
+CREATE INDEX season_player_value ON season_player_ratings(season_id, value);
+
+CREATE TABLE season_matchups(
+    season_id INTEGER NOT NULL,
+    char_id INTEGER NOT NULL,
+    opp_char_id INTEGER NOT NULL,
+    rating_value REAL NOT NULL,
+    rating_deviation REAL NOT NULL,
+    wins INTEGER NOT NULL,
+    losses INTEGER NOT NULL,
+    PRIMARY KEY(season_id, char_id, opp_char_id)
+);
+
+COMMIT;
@@ -0,0 +1,21 @@
+-- Turns the existing VIPs of a database into supporter periods that don't expire.
+-- sqlite3 ratings.sqlite < migrate_supporters.sql
+BEGIN;
+
+ALTER TABLE vip_status ADD COLUMN badge_colour TEXT;
+
+CREATE TABLE supporters (
+    id INTEGER NOT NULL,
+    tier TEXT NOT NULL,
+    start_timestamp INTEGER NOT NULL,
+    expiry_timestamp INTEGER,
+    badge_text TEXT,
+    badge_colour TEXT,
+    notes TEXT NOT NULL,
+    PRIMARY KEY(id, start_timestamp)
+);
+
+INSERT INTO supporters
+    SELECT id, vip_status, 0, NULL, vip_status, NULL, notes FROM vip_status;
+
+COMMIT;
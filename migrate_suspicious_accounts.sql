@@ -0,0 +1,11 @@
+-- Adds the review queue of suspicious accounts, it is filled in on the next statistics update.
+-- sqlite3 ratings.sqlite < migrate_suspicious_accounts.sql
+CREATE TABLE suspicious_accounts (
+    id INTEGER NOT NULL,
+    reason TEXT NOT NULL,
+    score REAL NOT NULL,
+    details TEXT NOT NULL,
+    flagged_at INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    PRIMARY KEY(id, reason)
+);
@@ -0,0 +1,9 @@
+-- Adds the tier list per rating bracket, it is filled in on the next statistics update.
+-- sqlite3 ratings.sqlite < migrate_tier_list.sql
+CREATE TABLE tier_list(
+    rating_bracket INTEGER NOT NULL,
+    char_id INTEGER NOT NULL,
+    win_rate REAL NOT NULL,
+    fraud_offset REAL,
+    PRIMARY KEY(rating_bracket, char_id)
+);
@@ -0,0 +1,16 @@
+-- Adds the suspected win trading pairs, they are filled in on the next statistics update.
+-- sqlite3 ratings.sqlite < migrate_trading_pairs.sql
+CREATE TABLE trading_pairs (
+    winner_id INTEGER NOT NULL,
+    loser_id INTEGER NOT NULL,
+    games INTEGER NOT NULL,
+    sets INTEGER NOT NULL,
+    wins INTEGER NOT NULL,
+    expected_games REAL NOT NULL,
+    rating_transferred REAL NOT NULL,
+    first_timestamp INTEGER NOT NULL,
+    last_timestamp INTEGER NOT NULL,
+    flagged_at INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    PRIMARY KEY(winner_id, loser_id)
+);
@@ -1,11 +1,18 @@
 use crate::ggst_api;
-use chrono::{Duration, NaiveDateTime, Utc};
-use fxhash::FxHashMap;
+use chrono::{DateTime, Duration, Utc};
+use fxhash::{FxHashMap, FxHashSet};
 use rand::distributions::{Alphanumeric, DistString};
-use rocket::serde::{json::Json, Serialize};
-use rusqlite::{named_params, params, Connection, OptionalExtension};
+use rocket::{
+    http::{Header, Status},
+    serde::{
+        json::{Json, Value},
+        Serialize,
+    },
+};
+use rusqlite::{named_params, params, types::ValueRef, Connection, OptionalExtension, Row};
 
 use crate::{
+    api_v1::{self, ApiError},
     glicko,
     glicko::Rating,
     rater::{self, RatedPlayer},
@@ -14,6 +21,18 @@ use crate::{
 
 type Result<T> = std::result::Result<T, anyhow::Error>;
 
+/// What hidden players are called wherever other players' pages or the API would show them.
+pub const HIDDEN_NAME: &str = "Hidden player";
+
+pub fn is_hidden(conn: &Connection, id: i64) -> bool {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM hidden_status WHERE id = ?)",
+        params![id],
+        |r| r.get(0),
+    )
+    .unwrap()
+}
+
 #[derive(Serialize)]
 pub struct Stats {
     game_count: i64,
@@ -35,7 +54,7 @@ pub struct Activity {
     sub_900: i64,
 }
 
-fn to_platform_string(i: i64) -> &'static str {
+pub fn to_platform_string(i: i64) -> &'static str {
     match i {
         1 => "PS",
         2 => "XB",
@@ -168,8 +187,7 @@ pub async fn daily_games(
     Json(
         conn.run(move |conn| {
             let tx = conn.transaction().unwrap();
-            let now = NaiveDateTime::from_timestamp_opt(Utc::now().timestamp(), 0).unwrap();
-            let now_date = now.date();
+            let now_date = Utc::now().date_naive();
             let length = length.unwrap_or(60);
             let then = now_date - Duration::days(length);
 
@@ -181,7 +199,7 @@ pub async fn daily_games(
                 then.iter_days()
                     .take(length as usize)
                     .map(|date| {
-                        let from = date.and_hms_opt(0, 0, 0).unwrap().timestamp();
+                        let from = date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
                         let to = from + 24 * 60 * 60;
                         tx.query_row(
                             "select count(*) from games where timestamp > ? and timestamp < ?",
@@ -194,7 +212,7 @@ pub async fn daily_games(
                 then.iter_days()
                     .take(length as usize)
                     .map(|date| {
-                        let from = date.and_hms_opt(0, 0, 0).unwrap().timestamp();
+                        let from = date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
                         let to = from + 24 * 60 * 60;
                         tx.query_row(
                             "select count(distinct id) from (
@@ -223,8 +241,7 @@ pub async fn weekly_games(
     Json(
         conn.run(move |conn| {
             let tx = conn.transaction().unwrap();
-            let now = NaiveDateTime::from_timestamp_opt(Utc::now().timestamp(), 0).unwrap();
-            let now_date = now.date();
+            let now_date = Utc::now().date_naive();
             let length = length.unwrap_or(8);
             let then = now_date - Duration::weeks(length);
 
@@ -236,7 +253,7 @@ pub async fn weekly_games(
                 then.iter_days()
                     .take(length as usize)
                     .map(|date| {
-                        let from = date.and_hms_opt(0, 0, 0).unwrap().timestamp();
+                        let from = date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
                         let to = from + 7 * 24 * 60 * 60;
                         tx.query_row(
                             "select count(*) from games where timestamp > ? and timestamp < ?",
@@ -249,7 +266,7 @@ pub async fn weekly_games(
                 then.iter_days()
                     .take(length as usize)
                     .map(|date| {
-                        let from = date.and_hms_opt(0, 0, 0).unwrap().timestamp();
+                        let from = date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
                         let to = from + 7 * 24 * 60 * 60;
                         tx.query_row(
                             "select count(distinct id) from (
@@ -278,8 +295,7 @@ pub async fn daily_character_games(
     Json(
         conn.run(move |conn| {
             let tx = conn.transaction().unwrap();
-            let now = NaiveDateTime::from_timestamp_opt(Utc::now().timestamp(), 0).unwrap();
-            let now_date = now.date();
+            let now_date = Utc::now().date_naive();
             let length = length.unwrap_or(60);
             let then = now_date - Duration::days(length);
 
@@ -294,7 +310,7 @@ pub async fn daily_character_games(
                         then.iter_days()
                             .take(length as usize)
                             .map(|date| {
-                                let from = date.and_hms_opt(0, 0, 0).unwrap().timestamp();
+                                let from = date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
                                 let to = from + 24 * 60 * 60;
                                 tx.query_row(
                                     "SELECT COUNT(*) 
@@ -341,25 +357,37 @@ pub struct RankingPlayer {
     rating_value: i64,
     rating_deviation: i64,
     vip_status: Option<String>,
+    vip_colour: Option<String>,
     cheater_status: Option<String>,
     hidden_status: Option<String>,
 }
 
 impl RankingPlayer {
+    #[allow(clippy::too_many_arguments)]
     fn from_db(
         pos: i32,
         name: String,
         platform: i64,
         vip_status: Option<String>,
+        vip_colour: Option<String>,
         cheater_status: Option<String>,
         hidden_status: Option<String>,
         rated_player: RatedPlayer,
     ) -> Self {
+        let hidden = hidden_status.is_some();
         Self {
             pos,
-            name,
-            platform: to_platform_string(platform),
-            id: format!("{:X}", rated_player.id),
+            name: if hidden { HIDDEN_NAME.to_owned() } else { name },
+            platform: if hidden {
+                ""
+            } else {
+                to_platform_string(platform)
+            },
+            id: if hidden {
+                String::new()
+            } else {
+                format!("{:X}", rated_player.id)
+            },
             character: website::CHAR_NAMES[rated_player.char_id as usize]
                 .1
                 .to_owned(),
@@ -370,21 +398,30 @@ impl RankingPlayer {
             rating_value: rated_player.rating.value.round() as i64,
             rating_deviation: (rated_player.rating.deviation * 2.0).round() as i64,
             vip_status,
+            vip_colour,
             cheater_status,
             hidden_status,
         }
     }
 }
-#[get("/api/top/all")]
-pub async fn top_all(conn: RatingsDbConn) -> Json<Vec<RankingPlayer>> {
-    Json(top_all_inner(&conn).await)
+#[get("/api/top/all?<season>")]
+pub async fn top_all(conn: RatingsDbConn, season: Option<i64>) -> Json<Vec<RankingPlayer>> {
+    Json(match season {
+        Some(season) => top_season_inner(&conn, season, None).await,
+        None => top_all_inner(&conn).await,
+    })
 }
 
+/// The legacy shape of `/api/v1/players/<id>/ratings`, one entry per character with the
+/// default rating for unplayed ones.
 #[get("/api/player_rating/<player>")]
-pub async fn player_rating_all(conn: RatingsDbConn, player: &str) -> Json<Vec<Rating>> {
-    let id = i64::from_str_radix(&player, 16).unwrap();
+pub async fn player_rating_all(
+    conn: RatingsDbConn,
+    player: &str,
+) -> std::result::Result<Json<Vec<Rating>>, ApiError> {
+    let id = api_v1::require_player(&conn, player, false).await?;
     let mut res = vec![Rating::default(); website::CHAR_NAMES.len()];
-    Json(
+    Ok(Json(
         conn.run(move |conn| {
             let mut stmt = conn
                 .prepare(
@@ -403,7 +440,7 @@ pub async fn player_rating_all(conn: RatingsDbConn, player: &str) -> Json<Vec<Ra
             res
         })
         .await,
-    )
+    ))
 
     //for char_id in 0..website::CHAR_NAMES.len() {
     //    let conn.run(move |conn| {
@@ -424,33 +461,29 @@ pub async fn player_rating(
     conn: RatingsDbConn,
     player: &str,
     character_short: &str,
-) -> Option<Json<Rating>> {
-    let id = i64::from_str_radix(&player, 16).unwrap();
-    if let Some(char_id) = website::CHAR_NAMES
-        .iter()
-        .position(|(c, _)| *c == character_short)
-    {
-        conn.run(move |conn| {
-            if let Some((value, deviation)) = conn
-                .query_row(
-                    "SELECT value, deviation
-                                FROM player_ratings
-                                WHERE id=? AND char_id=?",
-                    params![id, char_id],
-                    |r| Ok((r.get::<_, f64>(0)?, r.get::<_, f64>(1)?)),
-                )
-                .optional()
-                .unwrap()
-            {
-                Some(Json(Rating { value, deviation }))
-            } else {
-                None
-            }
-        })
-        .await
-    } else {
-        None
-    }
+) -> std::result::Result<Json<Rating>, ApiError> {
+    let char_id = api_v1::parse_character(character_short)?;
+    let id = api_v1::require_player(&conn, player, false).await?;
+    conn.run(move |conn| {
+        conn.query_row(
+            "SELECT value, deviation
+                        FROM player_ratings
+                        WHERE id=? AND char_id=?",
+            params![id, char_id],
+            |r| Ok((r.get::<_, f64>(0)?, r.get::<_, f64>(1)?)),
+        )
+        .optional()
+        .unwrap()
+    })
+    .await
+    .map(|(value, deviation)| Json(Rating { value, deviation }))
+    .ok_or_else(|| {
+        ApiError::new(
+            Status::NotFound,
+            "no_rating",
+            format!("{} has no rating with {}", player, character_short),
+        )
+    })
 }
 
 #[get("/api/accuracy/<player>/<character_short>")]
@@ -458,82 +491,79 @@ pub async fn player_rating_accuracy(
     conn: RatingsDbConn,
     player: &str,
     character_short: &str,
-) -> Option<Json<Vec<f64>>> {
-    let id = i64::from_str_radix(&player, 16).unwrap();
-    if let Some(char_id) = website::CHAR_NAMES
-        .iter()
-        .position(|(c, _)| *c == character_short)
-    {
-        conn.run(move |conn| {
-            let mut buckets = vec![(0.0, 0.0); 11];
-
-            let mut stmt = conn
-                .prepare(
-                    "
-                SELECT
-                    value_a as own_value,
-                    deviation_a as own_deviation,
-                    value_b as opp_value,
-                    deviation_b as opponent_deviation,
-                    winner
-                FROM games NATURAL JOIN game_ratings
-                WHERE 
-                    games.id_a = :id 
-                    AND games.char_a = :char_id 
-                    AND game_ratings.deviation_a< 75.0
-                    AND game_ratings.deviation_b < 75.0
-
-                UNION
+) -> std::result::Result<Json<Vec<f64>>, ApiError> {
+    let char_id = api_v1::parse_character(character_short)?;
+    let id = api_v1::require_player(&conn, player, false).await?;
+    Ok(Json(
+        conn.run(move |conn| rating_accuracy(conn, id, char_id))
+            .await,
+    ))
+}
 
-                SELECT
-                    value_b as own_value,
-                    deviation_b as own_deviation,
-                    value_a as opp_value,
-                    deviation_a as opponent_deviation,
-                    winner + 2 as winner
-                FROM games NATURAL JOIN game_ratings
-                WHERE 
-                    games.id_b = :id 
-                    AND games.char_b = :char_id 
-                    AND game_ratings.deviation_a < 0.5 
-                    AND game_ratings.deviation_b < 0.5",
-                )
-                .unwrap();
+/// The share of games won in each tenth of expected win probability, NaN where there are none.
+pub fn rating_accuracy(conn: &Connection, id: i64, char_id: usize) -> Vec<f64> {
+    let mut buckets = [(0.0, 0.0); 11];
 
-            let mut rows = stmt
-                .query(named_params! {
-                    ":id" : id,
-                    ":char_id": char_id,
-                })
-                .unwrap();
+    let mut stmt = conn
+        .prepare(
+            "
+        SELECT
+            value_a as own_value,
+            deviation_a as own_deviation,
+            value_b as opp_value,
+            deviation_b as opponent_deviation,
+            winner
+        FROM games NATURAL JOIN game_ratings
+        WHERE 
+            games.id_a = :id 
+            AND games.char_a = :char_id 
+            AND game_ratings.deviation_a< 75.0
+            AND game_ratings.deviation_b < 75.0
+
+        UNION
+
+        SELECT
+            value_b as own_value,
+            deviation_b as own_deviation,
+            value_a as opp_value,
+            deviation_a as opponent_deviation,
+            winner + 2 as winner
+        FROM games NATURAL JOIN game_ratings
+        WHERE 
+            games.id_b = :id 
+            AND games.char_b = :char_id 
+            AND game_ratings.deviation_a < 0.5 
+            AND game_ratings.deviation_b < 0.5",
+        )
+        .unwrap();
 
-            while let Some(row) = rows.next().unwrap() {
-                let own_rating = Rating::new(row.get(0).unwrap(), row.get(1).unwrap());
-                let opp_rating = Rating::new(row.get(2).unwrap(), row.get(3).unwrap());
-                let winner: i64 = row.get(4).unwrap();
+    let mut rows = stmt
+        .query(named_params! {
+            ":id" : id,
+            ":char_id": char_id,
+        })
+        .unwrap();
 
-                let expected = Rating::expected(own_rating, opp_rating);
+    while let Some(row) = rows.next().unwrap() {
+        let own_rating = Rating::new(row.get(0).unwrap(), row.get(1).unwrap());
+        let opp_rating = Rating::new(row.get(2).unwrap(), row.get(3).unwrap());
+        let winner: i64 = row.get(4).unwrap();
 
-                let bucket = (expected.min(1.0).max(0.0) * 10.0).round() as usize;
+        let expected = Rating::expected(own_rating, opp_rating);
 
-                match winner {
-                    1 | 4 => buckets[bucket].0 += 1.0,
-                    2 | 3 => buckets[bucket].1 += 1.0,
-                    _ => panic!("Bad winner"),
-                }
-            }
+        let bucket = (expected.clamp(0.0, 1.0) * 10.0).round() as usize;
 
-            Some(Json(
-                buckets
-                    .iter()
-                    .map(|(wins, losses)| wins / (wins + losses))
-                    .collect(),
-            ))
-        })
-        .await
-    } else {
-        None
+        match winner {
+            1 | 4 => buckets[bucket].0 += 1.0,
+            2 | 3 => buckets[bucket].1 += 1.0,
+            _ => panic!("Bad winner"),
+        }
     }
+
+    buckets
+        .iter()
+        .map(|(wins, losses)| wins / (wins + losses))
+        .collect()
 }
 
 pub async fn top_all_inner(conn: &RatingsDbConn) -> Vec<RankingPlayer> {
@@ -544,7 +574,7 @@ pub async fn top_all_inner(conn: &RatingsDbConn) -> Vec<RankingPlayer> {
                     player_ratings.id as id, char_id, 
                     wins, losses, 
                     value, deviation, last_decay, 
-                    name, platform, vip_status, cheater_status, hidden_status
+                    name, platform, vip_status, badge_colour, cheater_status, hidden_status
                  FROM ranking_global
                  NATURAL JOIN player_ratings
                  NATURAL JOIN players
@@ -563,6 +593,7 @@ pub async fn top_all_inner(conn: &RatingsDbConn) -> Vec<RankingPlayer> {
             let name = row.get("name").unwrap();
             let platform = row.get("platform").unwrap();
             let vip_status = row.get("vip_status").unwrap();
+            let vip_colour = row.get("badge_colour").unwrap();
             let cheater_status = row.get("cheater_status").unwrap();
             let hidden_status = row.get("hidden_status").unwrap();
             res.push(RankingPlayer::from_db(
@@ -570,6 +601,7 @@ pub async fn top_all_inner(conn: &RatingsDbConn) -> Vec<RankingPlayer> {
                 name,
                 platform,
                 vip_status,
+                vip_colour,
                 cheater_status,
                 hidden_status,
                 RatedPlayer::from_row(row),
@@ -637,6 +669,7 @@ pub async fn player_lookup(conn: RatingsDbConn, name: String) -> Json<Vec<Player
                         "SELECT id, name 
                     FROM player_names
                     WHERE name LIKE ?
+                        AND id NOT IN (SELECT id FROM hidden_status)
                     ",
                     )
                     .unwrap();
@@ -690,6 +723,7 @@ pub async fn player_lookup(conn: RatingsDbConn, name: String) -> Json<Vec<Player
 #[derive(Serialize)]
 pub struct SearchResultPlayer {
     name: String,
+    former_name: Option<String>,
     platform: &'static str,
     vip_status: Option<String>,
     cheater_status: Option<String>,
@@ -704,81 +738,133 @@ pub struct SearchResultPlayer {
 
 #[get("/api/search?<name>")]
 pub async fn search(conn: RatingsDbConn, name: String) -> Json<Vec<SearchResultPlayer>> {
-    Json(search_inner(&conn, name, false).await)
+    Json(search_inner(&conn, name, false, false).await)
 }
 
 #[get("/api/search_exact?<name>")]
 pub async fn search_exact(conn: RatingsDbConn, name: String) -> Json<Vec<SearchResultPlayer>> {
-    Json(search_inner(&conn, name, true).await)
+    Json(search_inner(&conn, name, true, false).await)
 }
 
+/// Hidden players are left out unless `include_hidden` is set.
 pub async fn search_inner(
     conn: &RatingsDbConn,
     search: String,
     exact: bool,
+    include_hidden: bool,
 ) -> Vec<SearchResultPlayer> {
-    if let Ok(res) = conn.run(move |c| {
-        info!("Searching for {}", search);
+    conn.run(move |c| search_players(c, search, exact, include_hidden))
+        .await
+        .unwrap_or_default()
+}
 
-        let mut stmt = c
-            .prepare(
-                "SELECT * FROM
-                    player_names
-                    NATURAL JOIN players
-                    NATURAL JOIN player_ratings
-                    LEFT JOIN vip_status ON vip_status.id = player_names.id
-                    LEFT JOIN cheater_status ON cheater_status.id = player_names.id
-                    LEFT JOIN hidden_status ON hidden_status.id = player_names.id
-                    WHERE name LIKE ?
-                    ORDER BY wins DESC
-                    LIMIT 1000
-                    ",
-            )?;
+fn search_players(
+    c: &Connection,
+    search: String,
+    exact: bool,
+    include_hidden: bool,
+) -> Result<Vec<SearchResultPlayer>> {
+    info!("Searching for {}", search);
 
-        let mut rows = if exact {
-            stmt.query(params![search])
-        } else {
-            stmt.query(params![format!("%{}%", search)])
+    let mut res = Vec::new();
+
+    if !exact {
+        let mut stmt = c.prepare_cached(
+            "SELECT
+                players.id AS id,
+                players.name AS name,
+                ?2 AS matched_name,
+                platform, char_id, value, deviation, wins, losses,
+                vip_status, cheater_status, hidden_status
+                FROM players
+                JOIN player_ratings ON player_ratings.id = players.id
+                LEFT JOIN vip_status ON vip_status.id = players.id
+                LEFT JOIN cheater_status ON cheater_status.id = players.id
+                LEFT JOIN hidden_status ON hidden_status.id = players.id
+                WHERE players.id = ?1
+                ORDER BY wins DESC
+                ",
+        )?;
+        for (id, matched_name) in crate::search::find_players(c, &search, include_hidden) {
+            let mut rows = stmt.query(params![id, matched_name])?;
+            while let Some(row) = rows.next()? {
+                res.push(search_result(row));
+            }
         }
-        .unwrap();
+        return Ok(res);
+    }
 
-        let mut res = Vec::new();
+    let mut stmt = c.prepare(
+        "SELECT
+                players.id AS id,
+                players.name AS name,
+                player_names.name AS matched_name,
+                platform, char_id, value, deviation, wins, losses,
+                vip_status, cheater_status, hidden_status
+                FROM player_names
+                JOIN players ON players.id = player_names.id
+                JOIN player_ratings ON player_ratings.id = player_names.id
+                LEFT JOIN vip_status ON vip_status.id = player_names.id
+                LEFT JOIN cheater_status ON cheater_status.id = player_names.id
+                LEFT JOIN hidden_status ON hidden_status.id = player_names.id
+                WHERE player_names.name LIKE ? AND (? OR hidden_status.id IS NULL)
+                ORDER BY wins DESC, player_names.name = players.name DESC
+                LIMIT 1000
+                ",
+    )?;
 
-        while let Some(row) = rows.next().unwrap() {
-            let rating: Rating =
-                Rating::new(row.get("value").unwrap(), row.get("deviation").unwrap());
-            let platform: i64 = row.get("platform").unwrap();
-            res.push(SearchResultPlayer {
-                name: row.get("name").unwrap(),
-                platform: to_platform_string(platform),
-                id: format!("{:X}", row.get::<_, i64>("id").unwrap()),
-                character: website::CHAR_NAMES[row.get::<_, usize>("char_id").unwrap()]
-                    .1
-                    .to_owned(),
-                character_short: website::CHAR_NAMES[row.get::<_, usize>("char_id").unwrap()]
-                    .0
-                    .to_owned(),
-                rating_value: rating.value.round() as i64,
-                rating_deviation: (rating.deviation * 2.0).round() as i64,
-                game_count: row.get::<_, i32>("wins").unwrap()
-                    + row.get::<_, i32>("losses").unwrap(),
-                vip_status: row.get::<_, Option<String>>("vip_status").unwrap(),
-                cheater_status: row.get::<_, Option<String>>("cheater_status").unwrap(),
-                hidden_status: row.get::<_, Option<String>>("hidden_status").unwrap(),
-            });
+    let mut rows = stmt.query(params![search, include_hidden])?;
+
+    //A player matching through several of their names only shows up once per character
+    let mut seen = FxHashSet::default();
+
+    while let Some(row) = rows.next()? {
+        let id: i64 = row.get("id").unwrap();
+        let char_id: usize = row.get("char_id").unwrap();
+        if seen.insert((id, char_id)) {
+            res.push(search_result(row));
         }
-        Result::Ok(res.into_iter().collect())
-    })
-    .await {
-        res
-    } else {
-        Vec::new()
+    }
+    Result::Ok(res)
+}
+
+fn search_result(row: &Row) -> SearchResultPlayer {
+    let id: i64 = row.get("id").unwrap();
+    let char_id: usize = row.get("char_id").unwrap();
+    let name: String = row.get("name").unwrap();
+    let matched_name: String = row.get("matched_name").unwrap();
+    let rating: Rating = Rating::new(row.get("value").unwrap(), row.get("deviation").unwrap());
+    let platform: i64 = row.get("platform").unwrap();
+    SearchResultPlayer {
+        former_name: if matched_name != name {
+            Some(matched_name)
+        } else {
+            None
+        },
+        name,
+        platform: to_platform_string(platform),
+        id: format!("{:X}", id),
+        character: website::CHAR_NAMES[char_id].1.to_owned(),
+        character_short: website::CHAR_NAMES[char_id].0.to_owned(),
+        rating_value: rating.value.round() as i64,
+        rating_deviation: (rating.deviation * 2.0).round() as i64,
+        game_count: row.get::<_, i32>("wins").unwrap() + row.get::<_, i32>("losses").unwrap(),
+        vip_status: row.get::<_, Option<String>>("vip_status").unwrap(),
+        cheater_status: row.get::<_, Option<String>>("cheater_status").unwrap(),
+        hidden_status: row.get::<_, Option<String>>("hidden_status").unwrap(),
     }
 }
 
-#[get("/api/top/<char_id>")]
-pub async fn top_char(conn: RatingsDbConn, char_id: i64) -> Json<Vec<RankingPlayer>> {
-    Json(top_char_inner(&conn, char_id).await)
+#[get("/api/top/<char_id>?<season>")]
+pub async fn top_char(
+    conn: RatingsDbConn,
+    char_id: i64,
+    season: Option<i64>,
+) -> Json<Vec<RankingPlayer>> {
+    Json(match season {
+        Some(season) => top_season_inner(&conn, season, Some(char_id)).await,
+        None => top_char_inner(&conn, char_id).await,
+    })
 }
 
 pub async fn top_char_inner(conn: &RatingsDbConn, char_id: i64) -> Vec<RankingPlayer> {
@@ -789,7 +875,7 @@ pub async fn top_char_inner(conn: &RatingsDbConn, char_id: i64) -> Vec<RankingPl
                     player_ratings.id as id, char_id, 
                     wins, losses, 
                     value, deviation, last_decay, 
-                    name, platform, vip_status, cheater_status, hidden_status
+                    name, platform, vip_status, badge_colour, cheater_status, hidden_status
                  FROM ranking_character
                  NATURAL JOIN player_ratings
                  NATURAL JOIN players
@@ -809,6 +895,65 @@ pub async fn top_char_inner(conn: &RatingsDbConn, char_id: i64) -> Vec<RankingPl
             let name = row.get("name").unwrap();
             let platform = row.get("platform").unwrap();
             let vip_status = row.get("vip_status").unwrap();
+            let vip_colour = row.get("badge_colour").unwrap();
+            let cheater_status = row.get("cheater_status").unwrap();
+            let hidden_status = row.get("hidden_status").unwrap();
+            res.push(RankingPlayer::from_db(
+                i,
+                name,
+                platform,
+                vip_status,
+                vip_colour,
+                cheater_status,
+                hidden_status,
+                RatedPlayer::from_row(row),
+            ));
+            i += 1;
+        }
+
+        res
+    })
+    .await
+}
+
+pub async fn top_season_inner(
+    conn: &RatingsDbConn,
+    season_id: i64,
+    char_id: Option<i64>,
+) -> Vec<RankingPlayer> {
+    conn.run(move |c| {
+        let mut stmt = c
+            .prepare(
+                "SELECT
+                    season_player_ratings.id as id, char_id,
+                    wins, losses,
+                    value, deviation, last_decay,
+                    name, platform, vip_status, badge_colour, cheater_status, hidden_status
+                 FROM season_player_ratings
+                 NATURAL JOIN players
+                 LEFT JOIN vip_status ON vip_status.id = season_player_ratings.id
+                 LEFT JOIN cheater_status ON cheater_status.id = season_player_ratings.id
+                 LEFT JOIN hidden_status ON hidden_status.id = season_player_ratings.id
+                 WHERE season_id = ?
+                    AND (? IS NULL OR char_id = ?)
+                    AND deviation < ?
+                    AND cheater_status IS NULL
+                    AND hidden_status IS NULL
+                 ORDER BY value DESC
+                 LIMIT 100",
+            )
+            .unwrap();
+        let mut rows = stmt
+            .query(params![season_id, char_id, char_id, rater::LOW_DEVIATION])
+            .unwrap();
+
+        let mut res = Vec::with_capacity(100);
+        let mut i = 1;
+        while let Some(row) = rows.next().unwrap() {
+            let name = row.get("name").unwrap();
+            let platform = row.get("platform").unwrap();
+            let vip_status = row.get("vip_status").unwrap();
+            let vip_colour = row.get("badge_colour").unwrap();
             let cheater_status = row.get("cheater_status").unwrap();
             let hidden_status = row.get("hidden_status").unwrap();
             res.push(RankingPlayer::from_db(
@@ -816,6 +961,7 @@ pub async fn top_char_inner(conn: &RatingsDbConn, char_id: i64) -> Vec<RankingPl
                 name,
                 platform,
                 vip_status,
+                vip_colour,
                 cheater_status,
                 hidden_status,
                 RatedPlayer::from_row(row),
@@ -829,11 +975,54 @@ pub async fn top_char_inner(conn: &RatingsDbConn, char_id: i64) -> Vec<RankingPl
 }
 
 #[derive(Serialize)]
-pub struct PlayerData {
-    name: String,
-    platform: String,
-    other_names: Option<Vec<String>>,
-    characters: Vec<PlayerCharacterData>,
+pub struct SeasonInfo {
+    pub season_id: i64,
+    pub name: String,
+    start: String,
+    end: Option<String>,
+    current: bool,
+}
+
+#[get("/api/seasons")]
+pub async fn seasons(conn: RatingsDbConn) -> Json<Vec<SeasonInfo>> {
+    Json(get_seasons(&conn).await)
+}
+
+pub async fn get_seasons(conn: &RatingsDbConn) -> Vec<SeasonInfo> {
+    conn.run(move |conn| {
+        let now = Utc::now().timestamp();
+        let format_date = |t: i64| {
+            DateTime::from_timestamp(t, 0)
+                .unwrap()
+                .format("%Y-%m-%d")
+                .to_string()
+        };
+
+        let mut seasons: Vec<SeasonInfo> = rater::get_seasons(conn)
+            .into_iter()
+            .map(|s| SeasonInfo {
+                current: s.contains(now),
+                season_id: s.season_id,
+                name: s.name,
+                start: format_date(s.start_timestamp),
+                end: s.end_timestamp.map(format_date),
+            })
+            .collect();
+
+        seasons.reverse();
+        seasons
+    })
+    .await
+}
+
+/// The ongoing season, or the latest one if they've all ended. `seasons` are newest first like
+/// `get_seasons` returns them.
+pub fn default_season(seasons: &[SeasonInfo]) -> Option<i64> {
+    seasons
+        .iter()
+        .find(|s| s.current)
+        .or_else(|| seasons.first())
+        .map(|s| s.season_id)
 }
 
 #[derive(Serialize)]
@@ -843,7 +1032,7 @@ pub struct PlayerDataChar {
     platform: &'static str,
     vip_status: Option<String>,
     cheater_status: Option<String>,
-    other_names: Option<Vec<String>>,
+    other_names: Option<Vec<OtherName>>,
     other_characters: Vec<OtherPlayerCharacter>,
     data: PlayerCharacterData,
     pub hidden_status: Option<String>,
@@ -877,18 +1066,32 @@ struct PlayerCharacterData {
     top_defeated_deviation: Option<i64>,
     top_defeated_floor: Option<String>,
     top_defeated_timestamp: Option<String>,
+    top_defeated_hidden: bool,
 
     win_rate: f64,
     game_count: i32,
     matchups: Vec<PlayerMatchup>,
+    season_ratings: Vec<PlayerSeasonRating>,
+}
+
+#[derive(Serialize)]
+struct PlayerSeasonRating {
+    season_id: i64,
+    season_name: String,
+    rating_value: i64,
+    rating_deviation: i64,
+    game_count: i64,
+    win_rate: f64,
 }
 
 #[derive(Serialize)]
 pub struct PlayerCharacterHistory {
+    player_id: String,
     history: Vec<PlayerSet>,
 }
 
 const MATCHUP_MIN_GAMES: i64 = 250;
+const MATCHUP_MAX_INTERVAL: f64 = 10.0;
 
 #[derive(Serialize)]
 struct PlayerSet {
@@ -1000,7 +1203,7 @@ pub async fn get_player_char_history(
     offset: i64,
     group_games: bool,
 ) -> Option<PlayerCharacterHistory> {
-    if let Ok(res) = conn.run(move |conn| {
+    conn.run(move |conn| {
         let history = {
             let mut stmt = conn
                 .prepare_cached(
@@ -1131,87 +1334,1189 @@ pub async fn get_player_char_history(
 
             history
                 .into_iter()
-                .map(RawPlayerSet::to_formatted_set)
+                .map(RawPlayerSet::into_formatted_set)
                 .collect()
         };
 
-        Result::Ok(Some(PlayerCharacterHistory { history }))
+        Result::Ok(Some(PlayerCharacterHistory {
+            player_id: format!("{:X}", id),
+            history,
+        }))
     })
-    .await {
-        res
-    } else {
-        None
-    }
+    .await
+    .unwrap_or_default()
 }
 
-pub async fn get_player_data_char(
-    conn: &RatingsDbConn,
-    id: i64,
-    char_id: i64,
-) -> Result<Option<PlayerDataChar>> {
-    conn.run(move |conn| {
-        if conn.query_row(
-            "SELECT EXISTS(SELECT 1 FROM players WHERE id=?)",
-            params![id],
-            |r| r.get(0),
-        )? {
-            let (name, platform, vip_status, cheater_status, hidden_status): (
-                String,
-                i64,
-                Option<String>,
-                Option<String>,
-                Option<String>,
-            ) = conn.query_row(
-                "SELECT name, platform, vip_status, cheater_status, hidden_status FROM players
-                        LEFT JOIN vip_status ON vip_status.id = players.id
-                        LEFT JOIN cheater_status ON cheater_status.id = players.id
-                        LEFT JOIN hidden_status ON hidden_status.id = players.id
-                           WHERE players.id=?
-                           ",
-                params![id],
-                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?)),
-            )?;
-            info!(
-                "Loading data for {} ({})",
-                name,
-                website::CHAR_NAMES[char_id as usize].0
-            );
+#[derive(Serialize)]
+pub struct GameSet {
+    timestamp: i64,
+    floor: i64,
+    own_rating_value: f64,
+    own_rating_deviation: f64,
+    opponent_id: Option<String>,
+    opponent_name: String,
+    opponent_platform: &'static str,
+    opponent_character: &'static str,
+    opponent_character_short: &'static str,
+    opponent_rating_value: f64,
+    opponent_rating_deviation: f64,
+    opponent_vip: bool,
+    opponent_cheater: bool,
+    opponent_hidden: bool,
+    win_probability: f64,
+    wins: i32,
+    losses: i32,
+    valid: bool,
+    rating_changes: Vec<f64>,
+}
 
-            let other_names = get_player_other_names(conn, id, &name);
+#[derive(Serialize)]
+pub struct PlayerGames {
+    games: Vec<GameSet>,
+    next_cursor: Option<String>,
+}
 
-            let other_characters = get_player_other_characters(conn, id);
+#[derive(Default)]
+pub struct GameFilter {
+    pub opponent_char: Option<i64>,
+    pub opponent_id: Option<i64>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub floor: Option<i64>,
+    pub won: Option<bool>,
+}
 
-            let character_data = get_player_character_data(conn, id, char_id)?.unwrap();
+/// Where a page of games starts, written as `<timestamp>_<opponent id>`. Everything strictly
+/// older than the cursor's game comes next.
+pub struct GameCursor {
+    pub timestamp: i64,
+    pub opponent_id: Option<i64>,
+}
 
-            Ok(Some(PlayerDataChar {
-                id: format!("{:X}", id),
-                name,
-                platform: to_platform_string(platform),
-                vip_status,
-                cheater_status,
-                other_characters,
-                other_names,
-                data: character_data,
-                hidden_status,
-            }))
-        } else {
-            Ok(None)
-        }
-    })
-    .await
+impl GameCursor {
+    pub fn parse(cursor: &str) -> Option<Self> {
+        let mut parts = cursor.splitn(2, '_');
+        let timestamp = parts.next()?.parse().ok()?;
+        let opponent_id = match parts.next() {
+            Some(id) => Some(i64::from_str_radix(id, 16).ok()?),
+            None => None,
+        };
+        Some(Self {
+            timestamp,
+            opponent_id,
+        })
+    }
 }
 
-fn get_player_other_names(conn: &Connection, id: i64, name: &str) -> Option<Vec<String>> {
-    let mut stmt = conn
-        .prepare_cached("SELECT name FROM player_names WHERE id=?")
+impl std::fmt::Display for GameCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.opponent_id {
+            Some(id) => write!(f, "{}_{:X}", self.timestamp, id),
+            None => write!(f, "{}", self.timestamp),
+        }
+    }
+}
+
+/// A page of at most `limit` sets (or games when they aren't grouped), newest first. Sets are
+/// never split between pages.
+pub fn get_player_char_games(
+    conn: &Connection,
+    id: i64,
+    char_id: i64,
+    filter: &GameFilter,
+    cursor: Option<GameCursor>,
+    limit: usize,
+    group_games: bool,
+) -> PlayerGames {
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT * FROM (
+                SELECT
+                    timestamp,
+                    value_a AS own_value,
+                    deviation_a AS own_deviation,
+                    game_floor,
+                    name_b AS opponent_name,
+                    id_b AS opponent_id,
+                    char_b AS opponent_character,
+                    platform_b AS opponent_platform,
+                    value_b AS opponent_value,
+                    deviation_b AS opponent_deviation,
+                    winner = 1 AS won,
+                    valid,
+                    vip_status,
+                    cheater_status,
+                    hidden_status
+                FROM games NATURAL JOIN game_ratings
+                LEFT JOIN vip_status ON vip_status.id = games.id_b
+                LEFT JOIN cheater_status ON cheater_status.id = games.id_b
+                LEFT JOIN hidden_status ON hidden_status.id = games.id_b
+                WHERE games.id_a = :id AND games.char_a = :char_id
+
+                UNION ALL
+
+                SELECT
+                    timestamp,
+                    value_b AS own_value,
+                    deviation_b AS own_deviation,
+                    game_floor,
+                    name_a AS opponent_name,
+                    id_a AS opponent_id,
+                    char_a AS opponent_character,
+                    platform_a AS opponent_platform,
+                    value_a AS opponent_value,
+                    deviation_a AS opponent_deviation,
+                    winner = 2 AS won,
+                    valid,
+                    vip_status,
+                    cheater_status,
+                    hidden_status
+                FROM games NATURAL JOIN game_ratings
+                LEFT JOIN vip_status ON vip_status.id = games.id_a
+                LEFT JOIN cheater_status ON cheater_status.id = games.id_a
+                LEFT JOIN hidden_status ON hidden_status.id = games.id_a
+                WHERE games.id_b = :id AND games.char_b = :char_id
+            )
+            WHERE (:opponent_char IS NULL OR opponent_character = :opponent_char)
+                AND (:opponent_id IS NULL OR (opponent_id = :opponent_id AND hidden_status IS NULL))
+                AND (:from IS NULL OR timestamp >= :from)
+                AND (:to IS NULL OR timestamp < :to)
+                AND (:floor IS NULL OR game_floor = :floor)
+                AND (:won IS NULL OR won = :won)
+                AND (:cursor_timestamp IS NULL
+                    OR timestamp < :cursor_timestamp
+                    OR (timestamp = :cursor_timestamp AND opponent_id < :cursor_opponent))
+            ORDER BY timestamp DESC, opponent_id DESC",
+        )
+        .unwrap();
+
+    let mut rows = stmt
+        .query(named_params! {
+            ":id": id,
+            ":char_id": char_id,
+            ":opponent_char": filter.opponent_char,
+            ":opponent_id": filter.opponent_id,
+            ":from": filter.from,
+            ":to": filter.to,
+            ":floor": filter.floor,
+            ":won": filter.won,
+            ":cursor_timestamp": cursor.as_ref().map(|c| c.timestamp),
+            ":cursor_opponent": cursor.as_ref().and_then(|c| c.opponent_id),
+        })
+        .unwrap();
+
+    let mut sets = Vec::<RawPlayerSet>::new();
+    let mut last_game = None;
+    let mut more = false;
+    while let Some(row) = rows.next().unwrap() {
+        let timestamp: i64 = row.get("timestamp").unwrap();
+        let opponent_id: i64 = row.get("opponent_id").unwrap();
+        let opponent_char: i64 = row.get("opponent_character").unwrap();
+        let valid: bool = row.get("valid").unwrap();
+        let opponent_hidden = row
+            .get::<_, Option<String>>("hidden_status")
+            .unwrap()
+            .is_some();
+
+        let continues_set = group_games
+            && sets.last().is_some_and(|set| {
+                set.opponent_id == opponent_id
+                    && set.opponent_char == opponent_char
+                    && set.valid == valid
+            });
+        if sets.len() == limit && !continues_set {
+            more = true;
+            break;
+        }
+
+        let opponent_platform: i64 = row.get("opponent_platform").unwrap();
+        let add_set = if group_games {
+            add_to_grouped_sets
+        } else {
+            add_ungrouped_set
+        };
+        add_set(
+            &mut sets,
+            timestamp,
+            row.get("game_floor").unwrap(),
+            row.get("own_value").unwrap(),
+            row.get("own_deviation").unwrap(),
+            row.get("opponent_name").unwrap(),
+            opponent_id,
+            opponent_char,
+            to_platform_string(opponent_platform),
+            row.get("opponent_value").unwrap(),
+            row.get("opponent_deviation").unwrap(),
+            row.get("won").unwrap(),
+            valid,
+            row.get::<_, Option<String>>("vip_status")
+                .unwrap()
+                .is_some(),
+            row.get::<_, Option<String>>("cheater_status")
+                .unwrap()
+                .is_some(),
+            opponent_hidden,
+        );
+
+        //A player can't play two games in the same second, so leaving a hidden opponent's id
+        //out of the cursor doesn't skip anything
+        last_game = Some(GameCursor {
+            timestamp,
+            opponent_id: if opponent_hidden {
+                None
+            } else {
+                Some(opponent_id)
+            },
+        });
+    }
+
+    PlayerGames {
+        games: sets.into_iter().map(RawPlayerSet::into_game_set).collect(),
+        next_cursor: if more {
+            last_game.map(|c| c.to_string())
+        } else {
+            None
+        },
+    }
+}
+
+const HEAD_TO_HEAD_MAX_SETS: usize = 100;
+//Only their games against each other are looked at, so sets are split by time instead of by
+//games against someone else in between
+const HEAD_TO_HEAD_SET_GAP: i64 = 60 * 60;
+
+#[derive(Serialize)]
+pub struct HeadToHead {
+    pub player_a: HeadToHeadPlayer,
+    pub player_b: HeadToHeadPlayer,
+    wins_a: i32,
+    wins_b: i32,
+    set_count: usize,
+    rating_change_a: f64,
+    rating_change_b: f64,
+    win_probability_a: Option<f64>,
+    characters: Vec<CharacterPairRecord>,
+    sets: Vec<HeadToHeadSet>,
+}
+
+#[derive(Serialize)]
+pub struct HeadToHeadPlayer {
+    id: String,
+    pub name: String,
+    platform: &'static str,
+    vip_status: Option<String>,
+    vip_colour: Option<String>,
+    cheater_status: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct CharacterPairRecord {
+    character_a: &'static str,
+    character_a_short: &'static str,
+    character_b: &'static str,
+    character_b_short: &'static str,
+    wins_a: i32,
+    wins_b: i32,
+    rating_change_a: f64,
+    rating_change_b: f64,
+    win_probability_a: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct HeadToHeadSet {
+    timestamp: i64,
+    date: String,
+    floor: String,
+    character_a: &'static str,
+    character_a_short: &'static str,
+    character_b: &'static str,
+    character_b_short: &'static str,
+    rating_a_value: i64,
+    rating_a_deviation: i64,
+    rating_b_value: i64,
+    rating_b_deviation: i64,
+    win_probability_a: f64,
+    wins_a: i32,
+    wins_b: i32,
+    rating_change_a: f64,
+    rating_change_b: f64,
+    valid: bool,
+}
+
+struct RawHeadToHeadSet {
+    timestamp: i64,
+    floor: i64,
+    char_a: i64,
+    char_b: i64,
+    rating_a: Rating,
+    rating_b: Rating,
+    wins_a: i32,
+    wins_b: i32,
+    rating_change_a: f64,
+    rating_change_b: f64,
+    valid: bool,
+}
+
+fn get_head_to_head_player(conn: &Connection, id: i64) -> Option<HeadToHeadPlayer> {
+    conn.query_row(
+        "SELECT name, platform, vip_status, badge_colour, cheater_status FROM players
+            LEFT JOIN vip_status ON vip_status.id = players.id
+            LEFT JOIN cheater_status ON cheater_status.id = players.id
+            WHERE players.id = ?",
+        params![id],
+        |r| {
+            Ok(HeadToHeadPlayer {
+                id: format!("{:X}", id),
+                name: r.get(0)?,
+                platform: to_platform_string(r.get(1)?),
+                vip_status: r.get(2)?,
+                vip_colour: r.get(3)?,
+                cheater_status: r.get(4)?,
+            })
+        },
+    )
+    .optional()
+    .unwrap()
+}
+
+fn get_character_ratings(conn: &Connection, id: i64) -> FxHashMap<i64, Rating> {
+    let mut stmt = conn
+        .prepare_cached("SELECT char_id, value, deviation FROM player_ratings WHERE id = ?")
+        .unwrap();
+    let mut rows = stmt.query(params![id]).unwrap();
+    let mut ratings = FxHashMap::default();
+    while let Some(row) = rows.next().unwrap() {
+        ratings.insert(
+            row.get(0).unwrap(),
+            Rating::new(row.get(1).unwrap(), row.get(2).unwrap()),
+        );
+    }
+    ratings
+}
+
+/// Every game between two players from `id_a`'s side, with their current chances of beating
+/// each other based on the characters they last played.
+pub fn get_head_to_head(conn: &Connection, id_a: i64, id_b: i64) -> Option<HeadToHead> {
+    let player_a = get_head_to_head_player(conn, id_a)?;
+    let player_b = get_head_to_head_player(conn, id_b)?;
+
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT timestamp, game_floor, id_a, char_a, char_b,
+                value_a, deviation_a, value_b, deviation_b, winner, valid
+            FROM games NATURAL JOIN game_ratings
+            WHERE (id_a = :a AND id_b = :b) OR (id_a = :b AND id_b = :a)
+            ORDER BY timestamp DESC",
+        )
+        .unwrap();
+    let mut rows = stmt
+        .query(named_params! { ":a": id_a, ":b": id_b })
+        .unwrap();
+
+    let mut sets = Vec::<RawHeadToHeadSet>::new();
+    let mut pairs: FxHashMap<(i64, i64), (i32, i32, f64, f64)> = FxHashMap::default();
+    while let Some(row) = rows.next().unwrap() {
+        let timestamp: i64 = row.get(0).unwrap();
+        let floor: i64 = row.get(1).unwrap();
+        let first: i64 = row.get(2).unwrap();
+        let (chars, ratings) = (
+            (row.get::<_, i64>(3).unwrap(), row.get::<_, i64>(4).unwrap()),
+            (
+                Rating::new(row.get(5).unwrap(), row.get(6).unwrap()),
+                Rating::new(row.get(7).unwrap(), row.get(8).unwrap()),
+            ),
+        );
+        let winner: i64 = row.get(9).unwrap();
+        let valid: bool = row.get(10).unwrap();
+
+        let ((char_a, char_b), (rating_a, rating_b), won) = if first == id_a {
+            (chars, ratings, winner == 1)
+        } else {
+            ((chars.1, chars.0), (ratings.1, ratings.0), winner == 2)
+        };
+
+        let (rating_change_a, rating_change_b) = if valid {
+            (
+                Rating::rating_change(rating_a, rating_b, if won { 1.0 } else { 0.0 }),
+                Rating::rating_change(rating_b, rating_a, if won { 0.0 } else { 1.0 }),
+            )
+        } else {
+            (0.0, 0.0)
+        };
+
+        let pair = pairs.entry((char_a, char_b)).or_default();
+        if won {
+            pair.0 += 1;
+        } else {
+            pair.1 += 1;
+        }
+        pair.2 += rating_change_a;
+        pair.3 += rating_change_b;
+
+        if let Some(set) = sets.last_mut().filter(|set| {
+            set.char_a == char_a
+                && set.char_b == char_b
+                && set.valid == valid
+                && set.timestamp - timestamp < HEAD_TO_HEAD_SET_GAP
+        }) {
+            set.timestamp = timestamp;
+            set.rating_a = rating_a;
+            set.rating_b = rating_b;
+            if won {
+                set.wins_a += 1;
+            } else {
+                set.wins_b += 1;
+            }
+            set.rating_change_a += rating_change_a;
+            set.rating_change_b += rating_change_b;
+        } else {
+            sets.push(RawHeadToHeadSet {
+                timestamp,
+                floor,
+                char_a,
+                char_b,
+                rating_a,
+                rating_b,
+                wins_a: if won { 1 } else { 0 },
+                wins_b: if won { 0 } else { 1 },
+                rating_change_a,
+                rating_change_b,
+                valid,
+            });
+        }
+    }
+
+    let ratings_a = get_character_ratings(conn, id_a);
+    let ratings_b = get_character_ratings(conn, id_b);
+    let win_probability = |char_a: i64, char_b: i64| {
+        Some((1000.0 * ratings_a.get(&char_a)?.expected(*ratings_b.get(&char_b)?)).round() / 10.0)
+    };
+
+    let mut characters: Vec<CharacterPairRecord> = pairs
+        .into_iter()
+        .map(
+            |((char_a, char_b), (wins_a, wins_b, rating_change_a, rating_change_b))| {
+                CharacterPairRecord {
+                    character_a: website::CHAR_NAMES[char_a as usize].1,
+                    character_a_short: website::CHAR_NAMES[char_a as usize].0,
+                    character_b: website::CHAR_NAMES[char_b as usize].1,
+                    character_b_short: website::CHAR_NAMES[char_b as usize].0,
+                    wins_a,
+                    wins_b,
+                    rating_change_a: (10.0 * rating_change_a).round() / 10.0,
+                    rating_change_b: (10.0 * rating_change_b).round() / 10.0,
+                    win_probability_a: win_probability(char_a, char_b),
+                }
+            },
+        )
+        .collect();
+    characters.sort_by_key(|c| -(c.wins_a + c.wins_b));
+
+    Some(HeadToHead {
+        player_a,
+        player_b,
+        wins_a: characters.iter().map(|c| c.wins_a).sum(),
+        wins_b: characters.iter().map(|c| c.wins_b).sum(),
+        set_count: sets.len(),
+        rating_change_a: (10.0 * sets.iter().map(|s| s.rating_change_a).sum::<f64>()).round()
+            / 10.0,
+        rating_change_b: (10.0 * sets.iter().map(|s| s.rating_change_b).sum::<f64>()).round()
+            / 10.0,
+        win_probability_a: sets
+            .first()
+            .and_then(|s| win_probability(s.char_a, s.char_b)),
+        characters,
+        sets: sets
+            .into_iter()
+            .take(HEAD_TO_HEAD_MAX_SETS)
+            .map(|s| HeadToHeadSet {
+                timestamp: s.timestamp,
+                date: DateTime::from_timestamp(s.timestamp, 0)
+                    .unwrap()
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string(),
+                floor: stringify_floor(s.floor),
+                character_a: website::CHAR_NAMES[s.char_a as usize].1,
+                character_a_short: website::CHAR_NAMES[s.char_a as usize].0,
+                character_b: website::CHAR_NAMES[s.char_b as usize].1,
+                character_b_short: website::CHAR_NAMES[s.char_b as usize].0,
+                rating_a_value: s.rating_a.value.round() as i64,
+                rating_a_deviation: (2.0 * s.rating_a.deviation).round() as i64,
+                rating_b_value: s.rating_b.value.round() as i64,
+                rating_b_deviation: (2.0 * s.rating_b.deviation).round() as i64,
+                win_probability_a: (1000.0 * s.rating_a.expected(s.rating_b)).round() / 10.0,
+                wins_a: s.wins_a,
+                wins_b: s.wins_b,
+                rating_change_a: (10.0 * s.rating_change_a).round() / 10.0,
+                rating_change_b: (10.0 * s.rating_change_b).round() / 10.0,
+                valid: s.valid,
+            })
+            .collect(),
+    })
+}
+
+/// How many deviations come off a rating when seeding, the same margin the rankings use.
+pub const SEEDING_DEVIATIONS: f64 = 2.0;
+/// Bigger lists than this are turned away, every entrant costs a few queries.
+pub const MAX_ENTRANTS: usize = 256;
+
+/// One line of a seeding list: a player id or name, optionally followed by a comma and the
+/// short name of the character they're entering with. Names can have commas in them too, so
+/// the part after the last comma only counts as the character if it is one.
+pub struct Entrant {
+    player: String,
+    character: Option<String>,
+}
+
+impl Entrant {
+    pub fn parse(line: &str) -> Option<Entrant> {
+        let line = line.trim();
+        if line.is_empty() {
+            return None;
+        }
+
+        if let Some((player, character)) = line.rsplit_once(',') {
+            let character = character.trim().to_uppercase();
+            if website::CHAR_NAMES.iter().any(|(c, _)| *c == character) {
+                return Some(Entrant {
+                    player: player.trim().to_owned(),
+                    character: Some(character),
+                });
+            }
+        }
+
+        Some(Entrant {
+            player: line.to_owned(),
+            character: None,
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct SeededEntrant {
+    pub seed: usize,
+    pub entrant: String,
+    id: Option<String>,
+    pub name: Option<String>,
+    pub character: Option<&'static str>,
+    character_short: Option<&'static str>,
+    rating_value: Option<i64>,
+    rating_deviation: Option<i64>,
+    seeding_rating: Option<i64>,
+    game_count: Option<i64>,
+    pub flags: Vec<&'static str>,
+    #[serde(skip)]
+    rating: Option<Rating>,
+}
+
+impl SeededEntrant {
+    /// The entrant's rating, or a brand new one when there isn't any.
+    pub fn rating(&self) -> Rating {
+        self.rating.unwrap_or_default()
+    }
+}
+
+/// Finds the player an entrant line means. Ids win over names, and a name several players
+/// share goes to whoever has played the most with the entrant's character.
+fn find_entrant(
+    conn: &Connection,
+    entrant: &Entrant,
+    flags: &mut Vec<&'static str>,
+) -> Option<i64> {
+    if let Ok(id) = i64::from_str_radix(&entrant.player, 16) {
+        let exists: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM players WHERE id = ?)",
+                params![id],
+                |r| r.get(0),
+            )
+            .unwrap();
+        if exists && !is_hidden(conn, id) {
+            return Some(id);
+        }
+    }
+
+    let players = search_players(conn, entrant.player.clone(), true, false).unwrap_or_default();
+    if players
+        .iter()
+        .map(|p| &p.id)
+        .collect::<FxHashSet<_>>()
+        .len()
+        > 1
+    {
+        flags.push("ambiguous name");
+    }
+
+    let player = players
+        .iter()
+        .find(|p| Some(&p.character_short) == entrant.character.as_ref())
+        .or_else(|| players.first());
+    match player {
+        Some(player) => Some(i64::from_str_radix(&player.id, 16).unwrap()),
+        None => {
+            flags.push("not found");
+            None
+        }
+    }
+}
+
+fn look_up_entrant(conn: &Connection, entrant: Entrant) -> SeededEntrant {
+    let mut flags = Vec::new();
+
+    let char_id = entrant
+        .character
+        .as_ref()
+        .and_then(|character| website::CHAR_NAMES.iter().position(|(c, _)| c == character));
+
+    let id = find_entrant(conn, &entrant, &mut flags);
+
+    let name = id.map(|id| {
+        conn.query_row("SELECT name FROM players WHERE id = ?", params![id], |r| {
+            r.get::<_, String>(0)
+        })
+        .unwrap()
+    });
+    //Without a character the entrant is seeded with their best one
+    let rating = match id {
+        Some(id) => conn
+            .query_row(
+                "SELECT char_id, value, deviation, wins + losses
+                FROM player_ratings
+                WHERE id = ?1 AND (?2 IS NULL OR char_id = ?2)
+                ORDER BY value - ?3 * deviation DESC
+                LIMIT 1",
+                params![id, char_id, SEEDING_DEVIATIONS],
+                |r| {
+                    Ok((
+                        r.get::<_, usize>(0)?,
+                        Rating::new(r.get(1)?, r.get(2)?),
+                        r.get::<_, i64>(3)?,
+                    ))
+                },
+            )
+            .optional()
+            .unwrap(),
+        _ => None,
+    };
+
+    match rating {
+        None if id.is_some() => flags.push("unrated"),
+        Some((_, rating, _)) if rating.deviation >= rater::LOW_DEVIATION => {
+            flags.push("uncertain rating")
+        }
+        _ => {}
+    }
+
+    let char_id = rating.map(|(char_id, _, _)| char_id).or(char_id);
+    SeededEntrant {
+        seed: 0,
+        entrant: entrant.player,
+        id: id.map(|id| format!("{:X}", id)),
+        name,
+        character: char_id.map(|c| website::CHAR_NAMES[c].1),
+        character_short: char_id.map(|c| website::CHAR_NAMES[c].0),
+        rating_value: rating.map(|(_, r, _)| r.value.round() as i64),
+        rating_deviation: rating.map(|(_, r, _)| (r.deviation * 2.0).round() as i64),
+        seeding_rating: rating
+            .map(|(_, r, _)| (r.value - SEEDING_DEVIATIONS * r.deviation).round() as i64),
+        game_count: rating.map(|(_, _, games)| games),
+        flags,
+        rating: rating.map(|(_, r, _)| r),
+    }
+}
+
+/// Looks up every entrant's player and rating, keeping them in the order they came in.
+pub fn look_up_entrants(conn: &Connection, entrants: Vec<Entrant>) -> Vec<SeededEntrant> {
+    entrants
+        .into_iter()
+        .enumerate()
+        .map(|(i, entrant)| SeededEntrant {
+            seed: i + 1,
+            ..look_up_entrant(conn, entrant)
+        })
+        .collect()
+}
+
+/// Orders entrants by their rating minus `SEEDING_DEVIATIONS` deviations, so a player the
+/// system isn't sure about yet doesn't get a top seed off a handful of lucky games.
+/// Entrants without a rating go last, in the order they were entered.
+pub async fn seed_entrants(conn: &RatingsDbConn, entrants: Vec<Entrant>) -> Vec<SeededEntrant> {
+    let mut seeded = conn.run(move |c| look_up_entrants(c, entrants)).await;
+
+    seeded.sort_by_key(|e| std::cmp::Reverse(e.seeding_rating));
+    for (i, e) in seeded.iter_mut().enumerate() {
+        e.seed = i + 1;
+    }
+    seeded
+}
+
+#[derive(Serialize)]
+pub struct MatchSuggestion {
+    id: String,
+    name: String,
+    platform: &'static str,
+    character: &'static str,
+    character_short: &'static str,
+    rating_value: i64,
+    rating_deviation: i64,
+    win_probability: f64,
+    last_seen_timestamp: i64,
+    last_seen: String,
+}
+
+/// How many of the newest games `get_match_suggestions` looks through, which is plenty to find
+/// active players without reading a whole month of games on every request.
+const MAX_SUGGESTION_GAMES: i64 = 20000;
+
+/// Players who played a game with a character since `since` that `id`'s `char_id` has a
+/// `min_probability` to `max_probability` chance to beat, most even matches first. Hidden and
+/// cheating players are left out, and only the newest `MAX_SUGGESTION_GAMES` games count.
+/// `None` if `id` has no rating with `char_id`.
+pub fn get_match_suggestions(
+    conn: &Connection,
+    id: i64,
+    char_id: i64,
+    min_probability: f64,
+    max_probability: f64,
+    since: i64,
+    limit: usize,
+) -> Option<Vec<MatchSuggestion>> {
+    let own: Rating = conn
+        .query_row(
+            "SELECT value, deviation FROM player_ratings WHERE id = ? AND char_id = ?",
+            params![id, char_id],
+            |r| Ok(Rating::new(r.get(0)?, r.get(1)?)),
+        )
+        .optional()
+        .unwrap()?;
+
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT recent.id, recent.char_id, MAX(recent.timestamp),
+                players.name, players.platform, player_ratings.value, player_ratings.deviation
+            FROM (
+                WITH newest AS (
+                    SELECT timestamp, id_a, char_a, id_b, char_b FROM games
+                    WHERE timestamp > ?1
+                    ORDER BY timestamp DESC
+                    LIMIT ?3
+                )
+                SELECT id_a AS id, char_a AS char_id, timestamp FROM newest
+                UNION ALL
+                SELECT id_b AS id, char_b AS char_id, timestamp FROM newest
+            ) AS recent
+            JOIN players ON players.id = recent.id
+            JOIN player_ratings
+                ON player_ratings.id = recent.id AND player_ratings.char_id = recent.char_id
+            LEFT JOIN hidden_status ON hidden_status.id = recent.id
+            LEFT JOIN cheater_status ON cheater_status.id = recent.id
+            WHERE recent.id != ?2 AND hidden_status.id IS NULL AND cheater_status.id IS NULL
+            GROUP BY recent.id, recent.char_id",
+        )
+        .unwrap();
+    let mut rows = stmt
+        .query(params![since, id, MAX_SUGGESTION_GAMES])
+        .unwrap();
+
+    let mut suggestions = Vec::new();
+    while let Some(row) = rows.next().unwrap() {
+        let rating = Rating::new(row.get(5).unwrap(), row.get(6).unwrap());
+        let win_probability = own.expected(rating);
+        if !(min_probability..=max_probability).contains(&win_probability) {
+            continue;
+        }
+
+        let char_id: usize = row.get(1).unwrap();
+        let last_seen: i64 = row.get(2).unwrap();
+        suggestions.push(MatchSuggestion {
+            id: format!("{:X}", row.get::<_, i64>(0).unwrap()),
+            name: row.get(3).unwrap(),
+            platform: to_platform_string(row.get(4).unwrap()),
+            character: website::CHAR_NAMES[char_id].1,
+            character_short: website::CHAR_NAMES[char_id].0,
+            rating_value: rating.value.round() as i64,
+            rating_deviation: (rating.deviation * 2.0).round() as i64,
+            win_probability,
+            last_seen_timestamp: last_seen,
+            last_seen: DateTime::from_timestamp(last_seen, 0)
+                .unwrap()
+                .format("%Y-%m-%d %H:%M")
+                .to_string(),
+        });
+    }
+
+    suggestions.sort_by(|a, b| {
+        (a.win_probability - 0.5)
+            .abs()
+            .partial_cmp(&(b.win_probability - 0.5).abs())
+            .unwrap()
+            .then(b.last_seen_timestamp.cmp(&a.last_seen_timestamp))
+    });
+    suggestions.truncate(limit);
+    Some(suggestions)
+}
+
+pub const MAX_COMPARED_PLAYERS: usize = 8;
+
+#[derive(Serialize)]
+pub struct Comparison {
+    pub players: Vec<ComparedPlayer>,
+    head_to_heads: Vec<ComparedHeadToHead>,
+}
+
+#[derive(Serialize)]
+pub struct ComparedPlayer {
+    #[serde(flatten)]
+    pub player: HeadToHeadPlayer,
+    characters: Vec<ComparedCharacter>,
+}
+
+#[derive(Serialize)]
+pub struct ComparedCharacter {
+    character: &'static str,
+    character_short: &'static str,
+    rating_value: i64,
+    rating_deviation: i64,
+    game_count: i32,
+    win_rate: f64,
+    top_rating_value: Option<i64>,
+    top_rating_deviation: Option<i64>,
+    top_rating_date: Option<String>,
+    top_defeated: Option<TopDefeated>,
+    history: Vec<RatingPoint>,
+}
+
+#[derive(Serialize)]
+pub struct TopDefeated {
+    id: Option<String>,
+    name: String,
+    character: &'static str,
+    character_short: &'static str,
+    rating_value: i64,
+    rating_deviation: i64,
+    date: String,
+}
+
+#[derive(Serialize)]
+pub struct RatingPoint {
+    timestamp: i64,
+    rating_value: i64,
+    rating_deviation: i64,
+}
+
+#[derive(Serialize)]
+pub struct ComparedHeadToHead {
+    player_a: String,
+    name_a: String,
+    player_b: String,
+    name_b: String,
+    wins_a: i32,
+    wins_b: i32,
+    set_count: usize,
+    rating_change_a: f64,
+    rating_change_b: f64,
+    win_probability_a: Option<f64>,
+    characters: Vec<CharacterPairRecord>,
+}
+
+fn format_date(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .unwrap()
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+fn get_compared_characters(conn: &Connection, id: i64) -> Vec<ComparedCharacter> {
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT char_id, wins, losses, value, deviation,
+                top_rating_value, top_rating_deviation, top_rating_timestamp,
+                top_defeated_id, top_defeated_char_id, top_defeated_name,
+                top_defeated_value, top_defeated_deviation, top_defeated_timestamp
+            FROM player_ratings
+            WHERE id = ?
+            ORDER BY wins + losses DESC",
+        )
+        .unwrap();
+    let mut history_stmt = conn
+        .prepare_cached(
+            "SELECT timestamp, value, deviation FROM daily_ratings
+            WHERE id = ? AND char_id = ?
+            ORDER BY timestamp",
+        )
+        .unwrap();
+    let mut rows = stmt.query(params![id]).unwrap();
+
+    let mut characters = Vec::new();
+    while let Some(row) = rows.next().unwrap() {
+        let char_id: usize = row.get(0).unwrap();
+        let wins: i32 = row.get(1).unwrap();
+        let losses: i32 = row.get(2).unwrap();
+        let top_rating = row
+            .get::<_, Option<f64>>(5)
+            .unwrap()
+            .map(|value| Rating::new(value, row.get::<_, Option<f64>>(6).unwrap().unwrap()));
+
+        let top_defeated_id: Option<i64> = row.get(8).unwrap();
+        let top_defeated = top_defeated_id.map(|top_id| {
+            let hidden = is_hidden(conn, top_id);
+            let top_char: usize = row.get(9).unwrap();
+            TopDefeated {
+                id: if hidden {
+                    None
+                } else {
+                    Some(format!("{:X}", top_id))
+                },
+                name: if hidden {
+                    HIDDEN_NAME.to_owned()
+                } else {
+                    row.get(10).unwrap()
+                },
+                character: website::CHAR_NAMES[top_char].1,
+                character_short: website::CHAR_NAMES[top_char].0,
+                rating_value: row.get::<_, f64>(11).unwrap().round() as i64,
+                rating_deviation: (row.get::<_, f64>(12).unwrap() * 2.0).round() as i64,
+                date: format_date(row.get(13).unwrap()),
+            }
+        });
+
+        let history = history_stmt
+            .query_map(params![id, char_id], |r| {
+                Ok(RatingPoint {
+                    timestamp: r.get(0)?,
+                    rating_value: r.get::<_, f64>(1)?.round() as i64,
+                    rating_deviation: (r.get::<_, f64>(2)? * 2.0).round() as i64,
+                })
+            })
+            .unwrap()
+            .map(|p| p.unwrap())
+            .collect();
+
+        characters.push(ComparedCharacter {
+            character: website::CHAR_NAMES[char_id].1,
+            character_short: website::CHAR_NAMES[char_id].0,
+            rating_value: row.get::<_, f64>(3).unwrap().round() as i64,
+            rating_deviation: (row.get::<_, f64>(4).unwrap() * 2.0).round() as i64,
+            game_count: wins + losses,
+            win_rate: (1000.0 * wins as f64 / (wins + losses) as f64).round() / 10.0,
+            top_rating_value: top_rating.map(|r| r.value.round() as i64),
+            top_rating_deviation: top_rating.map(|r| (r.deviation * 2.0).round() as i64),
+            top_rating_date: row.get::<_, Option<i64>>(7).unwrap().map(format_date),
+            top_defeated,
+            history,
+        });
+    }
+    characters
+}
+
+/// Several players' characters side by side, with the record of every pair of them that has
+/// played each other. `None` if any of them doesn't exist.
+pub fn get_comparison(conn: &Connection, ids: &[i64]) -> Option<Comparison> {
+    let mut players = Vec::with_capacity(ids.len());
+    for &id in ids {
+        players.push(ComparedPlayer {
+            player: get_head_to_head_player(conn, id)?,
+            characters: get_compared_characters(conn, id),
+        });
+    }
+
+    let mut head_to_heads = Vec::new();
+    for (i, &id_a) in ids.iter().enumerate() {
+        for &id_b in &ids[i + 1..] {
+            let h2h = get_head_to_head(conn, id_a, id_b)?;
+            if h2h.set_count > 0 {
+                head_to_heads.push(ComparedHeadToHead {
+                    player_a: h2h.player_a.id,
+                    name_a: h2h.player_a.name,
+                    player_b: h2h.player_b.id,
+                    name_b: h2h.player_b.name,
+                    wins_a: h2h.wins_a,
+                    wins_b: h2h.wins_b,
+                    set_count: h2h.set_count,
+                    rating_change_a: h2h.rating_change_a,
+                    rating_change_b: h2h.rating_change_b,
+                    win_probability_a: h2h.win_probability_a,
+                    characters: h2h.characters,
+                });
+            }
+        }
+    }
+
+    Some(Comparison {
+        players,
+        head_to_heads,
+    })
+}
+
+const CHART_WIDTH: f64 = 800.0;
+const CHART_HEIGHT: f64 = 300.0;
+const CHART_COLOURS: &[&str] = &[
+    "#e6194b", "#3cb44b", "#4363d8", "#f58231", "#911eb4", "#42d4f4", "#f032e6", "#9a6324",
+];
+
+/// The rating histories of a comparison as svg polylines sharing one set of axes.
+#[derive(Serialize)]
+pub struct RatingChart {
+    view_box: String,
+    width: f64,
+    height: f64,
+    min_rating: i64,
+    max_rating: i64,
+    first_date: String,
+    last_date: String,
+    lines: Vec<ChartLine>,
+}
+
+#[derive(Serialize)]
+pub struct ChartLine {
+    label: String,
+    colour: &'static str,
+    points: String,
+}
+
+pub fn rating_chart(comparison: &Comparison) -> Option<RatingChart> {
+    let series: Vec<(String, &[RatingPoint])> = comparison
+        .players
+        .iter()
+        .flat_map(|p| {
+            p.characters
+                .iter()
+                .filter(|c| !c.history.is_empty())
+                .map(move |c| {
+                    (
+                        format!("{} ({})", p.player.name, c.character),
+                        &c.history[..],
+                    )
+                })
+        })
+        .collect();
+    let points = || series.iter().flat_map(|(_, h)| h.iter());
+
+    let first = points().map(|p| p.timestamp).min()?;
+    let last = points().map(|p| p.timestamp).max()?;
+    let min_rating = points().map(|p| p.rating_value).min()? - 25;
+    let max_rating = points().map(|p| p.rating_value).max()? + 25;
+
+    let x = |t: i64| CHART_WIDTH * (t - first) as f64 / (last - first).max(1) as f64;
+    let y = |r: i64| {
+        CHART_HEIGHT - CHART_HEIGHT * (r - min_rating) as f64 / (max_rating - min_rating) as f64
+    };
+
+    Some(RatingChart {
+        //Room for the axis labels around the lines
+        view_box: format!("-50 -10 {} {}", CHART_WIDTH + 60.0, CHART_HEIGHT + 30.0),
+        width: CHART_WIDTH,
+        height: CHART_HEIGHT,
+        min_rating,
+        max_rating,
+        first_date: format_date(first),
+        last_date: format_date(last),
+        lines: series
+            .iter()
+            .zip(CHART_COLOURS.iter().cycle())
+            .map(|((label, history), colour)| ChartLine {
+                label: label.clone(),
+                colour,
+                points: history
+                    .iter()
+                    .map(|p| format!("{:.1},{:.1}", x(p.timestamp), y(p.rating_value)))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            })
+            .collect(),
+    })
+}
+
+pub async fn get_player_data_char(
+    conn: &RatingsDbConn,
+    id: i64,
+    char_id: i64,
+) -> Result<Option<PlayerDataChar>> {
+    conn.run(move |conn| {
+        if conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM players WHERE id=?)",
+            params![id],
+            |r| r.get(0),
+        )? {
+            let (name, platform, vip_status, cheater_status, hidden_status): (
+                String,
+                i64,
+                Option<String>,
+                Option<String>,
+                Option<String>,
+            ) = conn.query_row(
+                "SELECT name, platform, vip_status, cheater_status, hidden_status FROM players
+                        LEFT JOIN vip_status ON vip_status.id = players.id
+                        LEFT JOIN cheater_status ON cheater_status.id = players.id
+                        LEFT JOIN hidden_status ON hidden_status.id = players.id
+                           WHERE players.id=?
+                           ",
+                params![id],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?)),
+            )?;
+            info!(
+                "Loading data for {} ({})",
+                name,
+                website::CHAR_NAMES[char_id as usize].0
+            );
+
+            let other_names = get_player_other_names(conn, id, &name);
+
+            let other_characters = get_player_other_characters(conn, id);
+
+            let character_data = get_player_character_data(conn, id, char_id)?.unwrap();
+
+            Ok(Some(PlayerDataChar {
+                id: format!("{:X}", id),
+                name,
+                platform: to_platform_string(platform),
+                vip_status,
+                cheater_status,
+                other_characters,
+                other_names,
+                data: character_data,
+                hidden_status,
+            }))
+        } else {
+            Ok(None)
+        }
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct OtherName {
+    name: String,
+    first_seen: String,
+    last_seen: String,
+}
+
+/// Names the player has used other than `name`, oldest first.
+fn get_player_other_names(conn: &Connection, id: i64, name: &str) -> Option<Vec<OtherName>> {
+    //Names from before the dates were kept that had no games left to date them by are stored at 0
+    let format_date = |t: i64| match t {
+        0 => "unknown".to_owned(),
+        t => DateTime::from_timestamp(t, 0)
+            .unwrap()
+            .format("%Y-%m-%d")
+            .to_string(),
+    };
+
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT name, first_seen, last_seen FROM player_names
+                WHERE id=? AND name != ?
+                ORDER BY first_seen ASC",
+        )
         .unwrap();
-    let mut rows = stmt.query(params![id]).unwrap();
+    let mut rows = stmt.query(params![id, name]).unwrap();
     let mut other_names = Vec::new();
     while let Some(row) = rows.next().unwrap() {
-        let other_name: String = row.get(0).unwrap();
-        if other_name != name && !other_names.contains(&other_name) {
-            other_names.push(other_name);
-        }
+        other_names.push(OtherName {
+            name: row.get(0).unwrap(),
+            first_seen: format_date(row.get(1).unwrap()),
+            last_seen: format_date(row.get(2).unwrap()),
+        });
     }
 
     if other_names.is_empty() {
@@ -1251,7 +2556,7 @@ fn get_player_other_characters(conn: &Connection, id: i64) -> Vec<OtherPlayerCha
         });
     }
 
-    other_characters.sort_by_key(|c| c.rating_deviation as i64);
+    other_characters.sort_by_key(|c| c.rating_deviation);
 
     other_characters
 }
@@ -1327,6 +2632,9 @@ fn get_player_character_data(
     };
     {
         let character_name = website::CHAR_NAMES[char_id as usize].1.to_owned();
+        let top_defeated_hidden = top_defeated_id
+            .map(|id| is_hidden(conn, id))
+            .unwrap_or(false);
 
         let matchups = {
             let mut stmt = conn
@@ -1371,11 +2679,39 @@ fn get_player_character_data(
                 });
             }
 
-            matchups.sort_by_key(|m| -(m.rating as i64));
+            matchups.sort_by_key(|m| -m.rating);
 
             matchups
         };
 
+        let season_ratings = {
+            let mut stmt = conn
+                .prepare_cached(
+                    "SELECT season_id, name, wins, losses, value, deviation
+                    FROM season_player_ratings NATURAL JOIN seasons
+                    WHERE id = ? AND char_id = ?
+                    ORDER BY start_timestamp DESC",
+                )
+                .unwrap();
+
+            let mut rows = stmt.query(params![id, char_id]).unwrap();
+            let mut season_ratings = Vec::new();
+            while let Some(row) = rows.next().unwrap() {
+                let wins: i64 = row.get(2).unwrap();
+                let losses: i64 = row.get(3).unwrap();
+                season_ratings.push(PlayerSeasonRating {
+                    season_id: row.get(0).unwrap(),
+                    season_name: row.get(1).unwrap(),
+                    game_count: wins + losses,
+                    win_rate: (100.0 * wins as f64 / (wins + losses) as f64).round(),
+                    rating_value: row.get::<_, f64>(4).unwrap().round() as i64,
+                    rating_deviation: (row.get::<_, f64>(5).unwrap() * 2.0).round() as i64,
+                });
+            }
+
+            season_ratings
+        };
+
         Ok(Some(PlayerCharacterData {
             character_name,
             game_count: wins + losses,
@@ -1385,24 +2721,32 @@ fn get_player_character_data(
             top_rating_value: top_rating_value.map(|r| r.round() as i64),
             top_rating_deviation: top_rating_deviation.map(|d| (2.0 * d).round() as i64),
             top_rating_timestamp: top_rating_timestamp.map(|t| {
-                NaiveDateTime::from_timestamp_opt(t, 0)
+                DateTime::from_timestamp(t, 0)
                     .unwrap()
                     .format("%Y-%m-%d")
                     .to_string()
             }),
-            top_defeated_id: top_defeated_id.map(|id| format!("{:X}", id)),
+            top_defeated_id: top_defeated_id
+                .filter(|_| !top_defeated_hidden)
+                .map(|id| format!("{:X}", id)),
             top_defeated_char_id: top_defeated_char_id.map(|id| website::CHAR_NAMES[id as usize].0),
-            top_defeated_name,
+            top_defeated_name: if top_defeated_hidden {
+                Some(HIDDEN_NAME.to_owned())
+            } else {
+                top_defeated_name
+            },
             top_defeated_value: top_defeated_value.map(|r| r.round() as i64),
             top_defeated_deviation: top_defeated_deviation.map(|r| (2.0 * r).round() as i64),
             top_defeated_floor: top_defeated_floor.map(stringify_floor),
             top_defeated_timestamp: top_defeated_timestamp.map(|t| {
-                NaiveDateTime::from_timestamp_opt(t, 0)
+                DateTime::from_timestamp(t, 0)
                     .unwrap()
                     .format("%Y-%m-%d")
                     .to_string()
             }),
+            top_defeated_hidden,
             matchups,
+            season_ratings,
             character_rank,
             global_rank,
         }))
@@ -1431,8 +2775,44 @@ struct RawPlayerSet {
 }
 
 impl RawPlayerSet {
-    fn to_formatted_set(self) -> PlayerSet {
-        let timestamp = NaiveDateTime::from_timestamp_opt(self.timestamp, 0)
+    fn into_game_set(self) -> GameSet {
+        let own_rating = Rating::new(self.own_value, self.own_deviation);
+        let opp_rating = Rating::new(self.opponent_value, self.opponent_deviation);
+        let hidden = self.opponent_hidden;
+
+        GameSet {
+            timestamp: self.timestamp,
+            floor: self.floor,
+            own_rating_value: self.own_value,
+            own_rating_deviation: self.own_deviation,
+            opponent_id: if hidden {
+                None
+            } else {
+                Some(format!("{:X}", self.opponent_id))
+            },
+            opponent_name: if hidden {
+                HIDDEN_NAME.to_owned()
+            } else {
+                self.opponent_name
+            },
+            opponent_platform: if hidden { "" } else { self.opponent_platform },
+            opponent_character: website::CHAR_NAMES[self.opponent_char as usize].1,
+            opponent_character_short: website::CHAR_NAMES[self.opponent_char as usize].0,
+            opponent_rating_value: self.opponent_value,
+            opponent_rating_deviation: self.opponent_deviation,
+            opponent_vip: self.opponent_vip,
+            opponent_cheater: self.opponent_cheater,
+            opponent_hidden: hidden,
+            win_probability: own_rating.expected(opp_rating),
+            wins: self.result_wins,
+            losses: self.result_losses,
+            valid: self.valid,
+            rating_changes: self.rating_change_sequence.into_iter().rev().collect(),
+        }
+    }
+
+    fn into_formatted_set(self) -> PlayerSet {
+        let timestamp = DateTime::from_timestamp(self.timestamp, 0)
             .unwrap()
             .format("%Y-%m-%d %H:%M")
             .to_string();
@@ -1466,9 +2846,21 @@ impl RawPlayerSet {
             own_rating_value: self.own_value.round() as i64,
             own_rating_deviation: (2.0 * self.own_deviation).round() as i64,
             floor: stringify_floor(self.floor),
-            opponent_name: self.opponent_name,
-            opponent_platform: self.opponent_platform,
-            opponent_id: format!("{:X}", self.opponent_id),
+            opponent_name: if self.opponent_hidden {
+                HIDDEN_NAME.to_owned()
+            } else {
+                self.opponent_name
+            },
+            opponent_platform: if self.opponent_hidden {
+                ""
+            } else {
+                self.opponent_platform
+            },
+            opponent_id: if self.opponent_hidden {
+                String::new()
+            } else {
+                format!("{:X}", self.opponent_id)
+            },
             opponent_character_short: website::CHAR_NAMES[self.opponent_char as usize].0,
             opponent_character: website::CHAR_NAMES[self.opponent_char as usize].1,
 
@@ -1478,7 +2870,7 @@ impl RawPlayerSet {
             rating_change: if self.valid {
                 format!("{:+.1}", rating_change_sum,)
             } else {
-                format!("---")
+                "---".to_string()
             },
             rating_change_class: if !self.valid {
                 "rating-same"
@@ -1521,6 +2913,89 @@ fn stringify_floor(floor: i64) -> String {
     }
 }
 
+/// Groups the games `id` played against `opponent_id` since the given time into sets the same way
+/// the match history does, returning the number of sets and the rating `id` gained from them.
+pub fn pair_sets(conn: &Connection, id: i64, opponent_id: i64, since: i64) -> (usize, f64) {
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT
+                    timestamp,
+                    value_a AS own_value,
+                    deviation_a AS own_deviation,
+                    game_floor,
+                    name_b AS opponent_name,
+                    char_b AS opponent_character,
+                    platform_b AS opponent_platform,
+                    value_b AS opponent_value,
+                    deviation_b AS opponent_deviation,
+                    winner,
+                    valid
+                FROM games NATURAL JOIN game_ratings
+                WHERE games.id_a = :id AND games.id_b = :opponent_id AND timestamp > :since
+
+                UNION
+
+                SELECT
+                    timestamp,
+                    value_b AS own_value,
+                    deviation_b AS own_deviation,
+                    game_floor,
+                    name_a AS opponent_name,
+                    char_a AS opponent_character,
+                    platform_a AS opponent_platform,
+                    value_a AS opponent_value,
+                    deviation_a AS opponent_deviation,
+                    winner + 2  as winner,
+                    valid
+                FROM games NATURAL JOIN game_ratings
+                WHERE games.id_b = :id AND games.id_a = :opponent_id AND timestamp > :since
+
+                ORDER BY timestamp DESC",
+        )
+        .unwrap();
+
+    let mut rows = stmt
+        .query(named_params! {
+            ":id": id,
+            ":opponent_id": opponent_id,
+            ":since": since,
+        })
+        .unwrap();
+    let mut sets = Vec::<RawPlayerSet>::new();
+    while let Some(row) = rows.next().unwrap() {
+        let winner: i64 = row.get("winner").unwrap();
+        add_to_grouped_sets(
+            &mut sets,
+            row.get("timestamp").unwrap(),
+            row.get("game_floor").unwrap(),
+            row.get("own_value").unwrap(),
+            row.get("own_deviation").unwrap(),
+            row.get("opponent_name").unwrap(),
+            opponent_id,
+            row.get("opponent_character").unwrap(),
+            to_platform_string(row.get("opponent_platform").unwrap()),
+            row.get("opponent_value").unwrap(),
+            row.get("opponent_deviation").unwrap(),
+            match winner {
+                1 | 4 => true,
+                2 | 3 => false,
+                _ => panic!("Bad winner"),
+            },
+            row.get("valid").unwrap(),
+            false,
+            false,
+            false,
+        );
+    }
+
+    let rating_transferred = sets
+        .iter()
+        .flat_map(|set| set.rating_change_sequence.iter())
+        .sum();
+    (sets.len(), rating_transferred)
+}
+
+#[allow(clippy::too_many_arguments)]
 fn add_to_grouped_sets(
     sets: &mut Vec<RawPlayerSet>,
     timestamp: i64,
@@ -1585,6 +3060,7 @@ fn add_to_grouped_sets(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn add_ungrouped_set(
     sets: &mut Vec<RawPlayerSet>,
     timestamp: i64,
@@ -1606,126 +3082,318 @@ fn add_ungrouped_set(
     let own_rating = Rating::new(own_value, own_deviation);
     let opp_rating = Rating::new(opponent_value, opponent_deviation);
 
-    let rating_change = if valid {
-        Rating::rating_change(own_rating, opp_rating, if winner { 1.0 } else { 0.0 })
-    } else {
-        0.0
-    };
+    let rating_change = if valid {
+        Rating::rating_change(own_rating, opp_rating, if winner { 1.0 } else { 0.0 })
+    } else {
+        0.0
+    };
+
+    sets.push(RawPlayerSet {
+        timestamp,
+        own_value,
+        own_deviation,
+        floor,
+        opponent_name,
+        opponent_platform,
+        opponent_vip,
+        opponent_cheater,
+        opponent_hidden,
+        opponent_id,
+        opponent_char,
+        opponent_value,
+        opponent_deviation,
+        valid,
+        rating_change_sequence: vec![rating_change],
+        result_wins: if winner { 1 } else { 0 },
+        result_losses: if winner { 0 } else { 1 },
+    });
+}
+
+#[derive(Serialize)]
+pub struct CharacterMatchups {
+    name: String,
+    matchups: Vec<Matchup>,
+}
+
+#[derive(Serialize)]
+pub struct Matchup {
+    matchup: String,
+    win_rate: f64,
+    game_count: i64,
+    rating_delta: String,
+    expected: f64,
+    suspicious: bool,
+    evaluation: &'static str,
+}
+
+fn get_evaluation(r: f64, game_count: i64) -> &'static str {
+    if game_count < MATCHUP_MIN_GAMES {
+        return "none";
+    }
+
+    if r > 0.6 {
+        "verygood"
+    } else if r > 0.56 {
+        "good"
+    } else if r > 0.52 {
+        "slightlygood"
+    } else if r > 0.48 {
+        "ok"
+    } else if r > 0.44 {
+        "slightlybad"
+    } else if r > 0.40 {
+        "bad"
+    } else {
+        "verybad"
+    }
+}
+
+#[derive(Serialize)]
+pub struct TierMatchups {
+    tier_id: i64,
+    name: String,
+    description: String,
+    matchups: Vec<CharacterMatchups>,
+}
+
+pub async fn get_matchups(conn: &RatingsDbConn) -> Vec<TierMatchups> {
+    conn.run(move |conn| {
+        let tiers = rater::get_matchup_tiers(conn);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT char_id, opp_char_id, rating_value, rating_deviation, wins, losses
+                FROM matchups
+                WHERE tier_id = ?",
+            )
+            .unwrap();
+
+        tiers
+            .into_iter()
+            .map(|tier| {
+                let mut all_matchups = FxHashMap::default();
+                let mut rows = stmt.query(params![tier.tier_id]).unwrap();
+
+                while let Some(row) = rows.next().unwrap() {
+                    all_matchups.insert(
+                        (row.get::<_, i64>(0).unwrap(), row.get::<_, i64>(1).unwrap()),
+                        (
+                            row.get(2).unwrap(),
+                            row.get(3).unwrap(),
+                            row.get(4).unwrap(),
+                            row.get(5).unwrap(),
+                        ),
+                    );
+                }
+
+                TierMatchups {
+                    tier_id: tier.tier_id,
+                    name: tier.name,
+                    description: tier.description,
+                    matchups: to_character_matchups(&all_matchups),
+                }
+            })
+            .collect()
+    })
+    .await
+}
+
+pub async fn get_season_matchups(conn: &RatingsDbConn, season_id: i64) -> Vec<CharacterMatchups> {
+    conn.run(move |conn| {
+        let mut all_matchups = FxHashMap::default();
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT char_id, opp_char_id, rating_value, rating_deviation, wins, losses
+                FROM season_matchups
+                WHERE season_id = ?",
+            )
+            .unwrap();
+
+        let mut rows = stmt.query(params![season_id]).unwrap();
 
-    sets.push(RawPlayerSet {
-        timestamp,
-        own_value,
-        own_deviation,
-        floor,
-        opponent_name,
-        opponent_platform,
-        opponent_vip,
-        opponent_cheater,
-        opponent_hidden,
-        opponent_id,
-        opponent_char,
-        opponent_value,
-        opponent_deviation,
-        valid,
-        rating_change_sequence: vec![rating_change],
-        result_wins: if winner { 1 } else { 0 },
-        result_losses: if winner { 0 } else { 1 },
-    });
+        while let Some(row) = rows.next().unwrap() {
+            all_matchups.insert(
+                (row.get::<_, i64>(0).unwrap(), row.get::<_, i64>(1).unwrap()),
+                (
+                    row.get(2).unwrap(),
+                    row.get(3).unwrap(),
+                    row.get(4).unwrap(),
+                    row.get(5).unwrap(),
+                ),
+            );
+        }
+
+        to_character_matchups(&all_matchups)
+    })
+    .await
+}
+
+fn to_character_matchups(
+    all_matchups: &FxHashMap<(i64, i64), (f64, f64, i64, i64)>,
+) -> Vec<CharacterMatchups> {
+    (0..website::CHAR_NAMES.len() as i64)
+        .map(|c| CharacterMatchups {
+            name: website::CHAR_NAMES[c as usize].1.to_owned(),
+            matchups: (0..website::CHAR_NAMES.len() as i64)
+                .map(|o| {
+                    let (own_value, own_deviation, wins, losses) =
+                        *all_matchups.get(&(c, o)).unwrap_or(&(1500.0, 350.0, 0, 0));
+
+                    let (opp_value, opp_deviation, ..) =
+                        *all_matchups.get(&(o, c)).unwrap_or(&(1500.0, 350.0, 0, 0));
+
+                    let expected = Rating::new(own_value, own_deviation)
+                        .expected(Rating::new(opp_value, opp_deviation));
+
+                    Matchup {
+                        matchup: format!(
+                            "{} vs {}",
+                            website::CHAR_NAMES[c as usize].0,
+                            website::CHAR_NAMES[o as usize].0
+                        ),
+                        win_rate: (100.0 * wins as f64 / (wins + losses) as f64).round(),
+                        game_count: wins + losses,
+                        rating_delta: format!("{:+.0}", own_value - opp_value),
+                        expected: (100.0 * expected).round(),
+                        evaluation: get_evaluation(expected, wins + losses),
+                        suspicious: wins + losses < MATCHUP_MIN_GAMES,
+                    }
+                })
+                .collect(),
+        })
+        .collect()
 }
 
 #[derive(Serialize)]
-pub struct CharacterMatchups {
+pub struct CharacterMatchupChanges {
     name: String,
-    matchups: Vec<Matchup>,
+    matchups: Vec<MatchupChange>,
 }
 
 #[derive(Serialize)]
-pub struct Matchup {
+pub struct MatchupChange {
+    matchup: String,
+    expected_from: f64,
+    expected_to: f64,
+    delta: String,
+    suspicious: bool,
+    evaluation: &'static str,
+}
+
+/// Lines up two sets of matchup tables and shows how the expected win rates moved between them.
+pub fn compare_matchups(
+    from: &[CharacterMatchups],
+    to: &[CharacterMatchups],
+) -> Vec<CharacterMatchupChanges> {
+    from.iter()
+        .zip(to)
+        .map(|(f, t)| CharacterMatchupChanges {
+            name: f.name.clone(),
+            matchups: f
+                .matchups
+                .iter()
+                .zip(&t.matchups)
+                .map(|(f, t)| {
+                    let delta = t.expected - f.expected;
+                    let game_count = f.game_count.min(t.game_count);
+                    MatchupChange {
+                        matchup: f.matchup.clone(),
+                        expected_from: f.expected,
+                        expected_to: t.expected,
+                        delta: format!("{:+.0}", delta),
+                        suspicious: game_count < MATCHUP_MIN_GAMES,
+                        evaluation: get_evaluation(0.5 + delta / 100.0, game_count),
+                    }
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+pub struct CharacterMatchupEstimates {
+    name: String,
+    matchups: Vec<MatchupEstimate>,
+}
+
+#[derive(Serialize)]
+pub struct MatchupEstimate {
     matchup: String,
     win_rate: f64,
     game_count: i64,
+    advantage: f64,
+    std_error: f64,
     rating_delta: String,
     expected: f64,
+    lower: f64,
+    upper: f64,
+    interval: String,
     suspicious: bool,
     evaluation: &'static str,
 }
 
-fn get_evaluation(r: f64, game_count: i64) -> &'static str {
-    if game_count < MATCHUP_MIN_GAMES {
-        return "none";
-    }
-
-    if r > 0.6 {
-        "verygood"
-    } else if r > 0.56 {
-        "good"
-    } else if r > 0.52 {
-        "slightlygood"
-    } else if r > 0.48 {
-        "ok"
-    } else if r > 0.44 {
-        "slightlybad"
-    } else if r > 0.40 {
-        "bad"
-    } else {
-        "verybad"
-    }
+#[get("/api/matchup_estimates")]
+pub async fn matchup_estimates(conn: RatingsDbConn) -> Json<Vec<CharacterMatchupEstimates>> {
+    Json(get_matchup_estimates(&conn).await)
 }
 
-pub async fn get_matchups(conn: &RatingsDbConn, table: &'static str) -> Vec<CharacterMatchups> {
+/// Win rates at equal rating with 95% confidence intervals, see `rater::calc_matchup_estimates`.
+pub async fn get_matchup_estimates(conn: &RatingsDbConn) -> Vec<CharacterMatchupEstimates> {
     conn.run(move |conn| {
-        let tx = conn.transaction().unwrap();
-        let mut all_matchups = FxHashMap::default();
+        let mut estimates = FxHashMap::default();
 
-        let mut stmt = tx
-            .prepare(&format!(
-                "SELECT char_id, opp_char_id, rating_value, rating_deviation, wins, losses FROM {}",
-                table
-            ))
+        let mut stmt = conn
+            .prepare(
+                "SELECT char_id, opp_char_id, game_count, wins, advantage, std_error
+                FROM matchup_estimates",
+            )
             .unwrap();
 
         let mut rows = stmt.query([]).unwrap();
 
         while let Some(row) = rows.next().unwrap() {
-            let char_id: i64 = row.get(0).unwrap();
-            let opp_char_id: i64 = row.get(1).unwrap();
-            let rating_value: f64 = row.get(2).unwrap();
-            let rating_deviation: f64 = row.get(3).unwrap();
-            let wins: i64 = row.get(4).unwrap();
-            let losses: i64 = row.get(5).unwrap();
-
-            all_matchups.insert(
-                (char_id, opp_char_id),
-                (rating_value, rating_deviation, wins, losses),
+            estimates.insert(
+                (row.get::<_, i64>(0).unwrap(), row.get::<_, i64>(1).unwrap()),
+                (
+                    row.get::<_, i64>(2).unwrap(),
+                    row.get::<_, i64>(3).unwrap(),
+                    row.get::<_, f64>(4).unwrap(),
+                    row.get::<_, f64>(5).unwrap(),
+                ),
             );
         }
 
         (0..website::CHAR_NAMES.len() as i64)
-            .map(|c| CharacterMatchups {
+            .map(|c| CharacterMatchupEstimates {
                 name: website::CHAR_NAMES[c as usize].1.to_owned(),
                 matchups: (0..website::CHAR_NAMES.len() as i64)
                     .map(|o| {
-                        let (own_value, own_deviation, wins, losses) =
-                            *all_matchups.get(&(c, o)).unwrap_or(&(1500.0, 350.0, 0, 0));
-
-                        let (opp_value, opp_deviation, ..) =
-                            *all_matchups.get(&(o, c)).unwrap_or(&(1500.0, 350.0, 0, 0));
+                        let (game_count, wins, advantage, std_error) = *estimates
+                            .get(&(c, o))
+                            .unwrap_or(&(0, 0, 0.0, glicko::INITIAL_DEVIATION));
 
-                        let expected = Rating::new(own_value, own_deviation)
-                            .expected(Rating::new(opp_value, opp_deviation));
+                        let expected = glicko::advantage_to_probability(advantage);
+                        let lower = glicko::advantage_to_probability(advantage - 1.96 * std_error);
+                        let upper = glicko::advantage_to_probability(advantage + 1.96 * std_error);
 
-                        Matchup {
+                        MatchupEstimate {
                             matchup: format!(
                                 "{} vs {}",
                                 website::CHAR_NAMES[c as usize].0,
                                 website::CHAR_NAMES[o as usize].0
                             ),
-                            win_rate: (100.0 * wins as f64 / (wins + losses) as f64).round(),
-                            game_count: wins + losses,
-                            rating_delta: format!("{:+.0}", own_value - opp_value),
-                            expected: (100.0 * expected).round(),
-                            evaluation: get_evaluation(expected, wins + losses),
-                            suspicious: wins + losses < MATCHUP_MIN_GAMES,
+                            win_rate: (100.0 * wins as f64 / game_count as f64).round(),
+                            game_count,
+                            advantage,
+                            std_error,
+                            rating_delta: format!("{:+.0} ± {:.0}", advantage, 1.96 * std_error),
+                            expected: (1000.0 * expected).round() / 10.0,
+                            lower: (1000.0 * lower).round() / 10.0,
+                            upper: (1000.0 * upper).round() / 10.0,
+                            interval: format!("{:.1}% - {:.1}%", 100.0 * lower, 100.0 * upper),
+                            suspicious: 100.0 * (upper - lower) > MATCHUP_MAX_INTERVAL,
+                            evaluation: get_evaluation(expected, game_count),
                         }
                     })
                     .collect(),
@@ -1735,6 +3403,104 @@ pub async fn get_matchups(conn: &RatingsDbConn, table: &'static str) -> Vec<Char
     .await
 }
 
+#[derive(Serialize)]
+pub struct TierListBracket {
+    rating_min: usize,
+    rating_max: usize,
+    tiers: Vec<Tier>,
+}
+
+#[derive(Serialize)]
+pub struct Tier {
+    tier: &'static str,
+    characters: Vec<TierListCharacter>,
+}
+
+#[derive(Serialize)]
+pub struct TierListCharacter {
+    character: &'static str,
+    character_short: &'static str,
+    win_rate: f64,
+    fraud_offset: Option<f64>,
+}
+
+fn get_tier(win_rate: f64) -> &'static str {
+    if win_rate >= 0.53 {
+        "S"
+    } else if win_rate >= 0.51 {
+        "A"
+    } else if win_rate >= 0.49 {
+        "B"
+    } else if win_rate >= 0.47 {
+        "C"
+    } else {
+        "D"
+    }
+}
+
+#[get("/api/tier_list")]
+pub async fn tier_list(conn: RatingsDbConn) -> Json<Vec<TierListBracket>> {
+    Json(get_tier_list(&conn).await)
+}
+
+pub async fn get_tier_list(conn: &RatingsDbConn) -> Vec<TierListBracket> {
+    conn.run(move |conn| {
+        let mut stmt = conn
+            .prepare(
+                "SELECT char_id, win_rate, fraud_offset
+                FROM tier_list
+                WHERE rating_bracket = ?
+                ORDER BY win_rate DESC",
+            )
+            .unwrap();
+
+        let mut brackets = Vec::with_capacity(rater::POP_RATING_BRACKETS);
+        for r in 0..rater::POP_RATING_BRACKETS {
+            let mut tiers: Vec<Tier> = ["S", "A", "B", "C", "D"]
+                .iter()
+                .map(|&tier| Tier {
+                    tier,
+                    characters: Vec::new(),
+                })
+                .collect();
+
+            let mut rows = stmt.query(params![r]).unwrap();
+            while let Some(row) = rows.next().unwrap() {
+                let char_id: usize = row.get(0).unwrap();
+                let win_rate: f64 = row.get(1).unwrap();
+                let tier = get_tier(win_rate);
+                tiers
+                    .iter_mut()
+                    .find(|t| t.tier == tier)
+                    .unwrap()
+                    .characters
+                    .push(TierListCharacter {
+                        character: website::CHAR_NAMES[char_id].1,
+                        character_short: website::CHAR_NAMES[char_id].0,
+                        win_rate: (win_rate * 1000.0).round() / 10.0,
+                        fraud_offset: row
+                            .get::<_, Option<f64>>(2)
+                            .unwrap()
+                            .map(|f| (f * 10.0).round() / 10.0),
+                    });
+            }
+
+            brackets.push(TierListBracket {
+                rating_min: if r > 0 { 1000 + r * 100 } else { 0 },
+                rating_max: if r < rater::POP_RATING_BRACKETS - 1 {
+                    1000 + (r + 1) * 100
+                } else {
+                    3000
+                },
+                tiers,
+            });
+        }
+
+        brackets
+    })
+    .await
+}
+
 #[derive(Serialize)]
 pub struct FloorPlayers {
     floor: String,
@@ -1781,7 +3547,7 @@ pub async fn player_floors_distribution(conn: &RatingsDbConn) -> Vec<FloorPlayer
 
             res.push(FloorPlayers {
                 floor: match floor {
-                    99 => format!("Celestial"),
+                    99 => "Celestial".to_string(),
                     n => format!("Floor {}", n),
                 },
                 player_count,
@@ -2037,18 +3803,38 @@ pub struct VipPlayer {
     id: String,
     name: String,
     vip_status: Option<String>,
+    vip_colour: Option<String>,
+    tier: String,
+    since: String,
+    until: Option<String>,
 }
 
+/// Current supporters, most recent first.
 pub async fn get_supporters(conn: &RatingsDbConn) -> Vec<VipPlayer> {
     conn.run(move |conn| {
+        let now = Utc::now().timestamp();
         let mut stmt = conn
             .prepare(
-                "SELECT id, name, vip_status
-                    FROM vip_status NATURAL JOIN players",
+                "SELECT vip_status.id, name, vip_status, vip_status.badge_colour, tier,
+                    MAX(start_timestamp) AS since, expiry_timestamp
+                FROM vip_status
+                NATURAL JOIN players
+                JOIN supporters ON supporters.id = vip_status.id
+                WHERE start_timestamp <= :now
+                    AND (expiry_timestamp IS NULL OR expiry_timestamp > :now)
+                GROUP BY vip_status.id
+                ORDER BY since DESC",
             )
             .unwrap();
 
-        let mut rows = stmt.query(params![]).unwrap();
+        let mut rows = stmt.query(named_params! { ":now": now }).unwrap();
+
+        let to_date = |timestamp: i64| {
+            DateTime::from_timestamp(timestamp, 0)
+                .unwrap()
+                .format("%Y-%m-%d")
+                .to_string()
+        };
 
         let mut res = Vec::new();
         while let Some(row) = rows.next().unwrap() {
@@ -2056,11 +3842,13 @@ pub async fn get_supporters(conn: &RatingsDbConn) -> Vec<VipPlayer> {
                 id: format!("{:X}", row.get::<_, i64>(0).unwrap()),
                 name: row.get(1).unwrap(),
                 vip_status: row.get(2).unwrap(),
+                vip_colour: row.get(3).unwrap(),
+                tier: row.get(4).unwrap(),
+                since: to_date(row.get(5).unwrap()),
+                until: row.get::<_, Option<i64>>(6).unwrap().map(to_date),
             });
         }
 
-        res.reverse();
-
         res
     })
     .await
@@ -2204,13 +3992,9 @@ pub async fn rating_experience_player(
                 below_200: below_200 / total,
                 below_300: below_300 / total,
                 below_400: below_400 / total,
-                difference_amounts: (min_bucket..=max_bucket)
-                    .into_iter()
-                    .map(|r| r * 25.0 as i64)
-                    .collect(),
+                difference_amounts: (min_bucket..=max_bucket).map(|r| r * 25.0 as i64).collect(),
                 difference_counts: (min_bucket..=max_bucket)
-                    .into_iter()
-                    .map(|r| (counts.get(&r).copied().unwrap_or(0) as f64 / total * 100.0))
+                    .map(|r| counts.get(&r).copied().unwrap_or(0) as f64 / total * 100.0)
                     .collect(),
             }
         })
@@ -2351,13 +4135,9 @@ pub async fn rating_experience(
                 below_200: below_200 / total,
                 below_300: below_300 / total,
                 below_400: below_400 / total,
-                difference_amounts: (min_bucket..=max_bucket)
-                    .into_iter()
-                    .map(|r| r * 25.0 as i64)
-                    .collect(),
+                difference_amounts: (min_bucket..=max_bucket).map(|r| r * 25.0 as i64).collect(),
                 difference_counts: (min_bucket..=max_bucket)
-                    .into_iter()
-                    .map(|r| (counts.get(&r).copied().unwrap_or(0) as f64 / total * 100.0))
+                    .map(|r| counts.get(&r).copied().unwrap_or(0) as f64 / total * 100.0)
                     .collect(),
             }
         })
@@ -2404,10 +4184,7 @@ pub async fn floor_rating_distribution(conn: RatingsDbConn) -> Json<FloorRatingD
             let max_bucket = *totals.values().flat_map(|f| f.keys()).max().unwrap();
 
             FloorRatingDistributions {
-                ratings: (min_bucket..max_bucket)
-                    .into_iter()
-                    .map(|r| r * 50)
-                    .collect(),
+                ratings: (min_bucket..max_bucket).map(|r| r * 50).collect(),
                 floors: totals
                     .into_iter()
                     .map(|(f, sums)| {
@@ -2415,15 +4192,13 @@ pub async fn floor_rating_distribution(conn: RatingsDbConn) -> Json<FloorRatingD
                         (
                             f,
                             (min_bucket..max_bucket)
-                                .into_iter()
-                                .map(|r| (sums.get(&r).copied().unwrap_or(0) as f64))
+                                .map(|r| sums.get(&r).copied().unwrap_or(0) as f64)
                                 .collect(),
                         )
                     })
                     .collect(),
                 overall: (min_bucket..max_bucket)
-                    .into_iter()
-                    .map(|r| (overall.get(&r).copied().unwrap_or(0) as f64))
+                    .map(|r| overall.get(&r).copied().unwrap_or(0) as f64)
                     .collect(),
             }
         })
@@ -2461,123 +4236,442 @@ pub async fn outcomes(conn: RatingsDbConn) -> Json<(Vec<i64>, Vec<f64>, Vec<f64>
             }
 
             (
-                (0..=100).into_iter().collect(),
+                (0..=100).collect(),
                 outcomes
                     .into_iter()
                     .map(|(wins, total)| wins / total)
                     .collect(),
-                (0..=100).into_iter().map(|i| i as f64 / 100.0).collect(),
+                (0..=100).map(|i| i as f64 / 100.0).collect(),
             )
         })
         .await,
     )
 }
 
-#[get("/api/hide/<player>")]
-pub async fn start_hide_player(conn: RatingsDbConn, player: &str) -> Json<String> {
-    let id = i64::from_str_radix(&player, 16).unwrap();
-    let player_code = Alphanumeric.sample_string(&mut rand::thread_rng(), 8);
+const HIDE_CODE_LIFETIME: i64 = 10 * 60;
+const HIDE_REQUEST_COOLDOWN: i64 = 60;
+const HIDE_POLL_COOLDOWN: i64 = 4;
+
+/// Starts hiding (or unhiding) a player. The returned code has to be put in their R-Code
+/// comment before it expires, asking again while it's valid returns the same code.
+#[post("/api/hide/<player>")]
+pub async fn start_hide_player(
+    conn: RatingsDbConn,
+    player: &str,
+) -> std::result::Result<Json<String>, Status> {
+    let id = i64::from_str_radix(player, 16).map_err(|_| Status::BadRequest)?;
+
+    conn.run(move |conn| {
+        let now = Utc::now().timestamp();
+        let tx = conn.transaction().unwrap();
+
+        let exists: bool = tx
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM players WHERE id = ?)",
+                params![id],
+                |r| r.get(0),
+            )
+            .unwrap();
+        if !exists {
+            return Err(Status::NotFound);
+        }
+
+        let request: Option<(Option<String>, Option<i64>, i64)> = tx
+            .query_row(
+                "SELECT code, code_expiry, last_request FROM hide_requests WHERE id = ?",
+                params![id],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .optional()
+            .unwrap();
+
+        if let Some((code, code_expiry, last_request)) = request {
+            if let (Some(code), Some(code_expiry)) = (code, code_expiry) {
+                if code_expiry > now {
+                    return Ok(Json(code));
+                }
+            }
+            if now - last_request < HIDE_REQUEST_COOLDOWN {
+                return Err(Status::TooManyRequests);
+            }
+        }
+
+        //An earlier code can run out without being polled again, leaving the request pending
+        let from = match rater::get_hide_state(&tx, id) {
+            rater::HideState::Pending => {
+                let previous_state = tx
+                    .query_row(
+                        "SELECT previous_state FROM hide_requests WHERE id = ?",
+                        params![id],
+                        |r| r.get::<_, String>(0),
+                    )
+                    .map(|s| rater::HideState::from_db(&s))
+                    .unwrap();
+                rater::log_hide_transition(
+                    &tx,
+                    id,
+                    rater::HideState::Pending,
+                    previous_state,
+                    "expired",
+                );
+                previous_state
+            }
+            state => state,
+        };
+
+        let code = Alphanumeric.sample_string(&mut rand::thread_rng(), 8);
+        tx.execute(
+            "INSERT OR REPLACE INTO hide_requests VALUES(?, 'pending', ?, ?, ?, ?, NULL)",
+            params![id, from.as_str(), code, now + HIDE_CODE_LIFETIME, now],
+        )
+        .unwrap();
+        rater::log_hide_transition(&tx, id, from, rater::HideState::Pending, "player");
+
+        tx.commit().unwrap();
+        Ok(Json(code))
+    })
+    .await
+}
+
+/// State, previous state, code, code expiry and last poll of a `hide_requests` row.
+type HideRequest = (String, String, Option<String>, Option<i64>, Option<i64>);
+
+/// Checks whether the pending code shows up in the player's R-Code comment, and if so hides
+/// them, or unhides them if they were hidden.
+#[post("/api/hide/poll/<player>")]
+pub async fn poll_hide_player(
+    conn: RatingsDbConn,
+    player: &str,
+) -> std::result::Result<Json<bool>, Status> {
+    let id = i64::from_str_radix(player, 16).map_err(|_| Status::BadRequest)?;
 
     let code = conn
         .run(move |conn| {
-            let tx = conn.transaction().unwrap();
+            let now = Utc::now().timestamp();
+            let request: Option<HideRequest> = conn
+                .query_row(
+                    "SELECT state, previous_state, code, code_expiry, last_poll
+                    FROM hide_requests WHERE id = ?",
+                    params![id],
+                    |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?)),
+                )
+                .optional()
+                .unwrap();
+
+            match request {
+                Some((state, previous_state, Some(code), Some(code_expiry), last_poll))
+                    if state == "pending" =>
+                {
+                    if code_expiry <= now {
+                        info!("Hide code for {:X} expired", id);
+                        let previous_state = rater::HideState::from_db(&previous_state);
+                        conn.execute(
+                            "UPDATE hide_requests SET state = previous_state, code = NULL,
+                                code_expiry = NULL
+                            WHERE id = ?",
+                            params![id],
+                        )
+                        .unwrap();
+                        rater::log_hide_transition(
+                            conn,
+                            id,
+                            rater::HideState::Pending,
+                            previous_state,
+                            "expired",
+                        );
+                        Ok(None)
+                    } else if last_poll
+                        .map(|t| now - t < HIDE_POLL_COOLDOWN)
+                        .unwrap_or(false)
+                    {
+                        Err(Status::TooManyRequests)
+                    } else {
+                        conn.execute(
+                            "UPDATE hide_requests SET last_poll = ? WHERE id = ?",
+                            params![now, id],
+                        )
+                        .unwrap();
+                        Ok(Some(code))
+                    }
+                }
+                _ => Ok(None),
+            }
+        })
+        .await?;
+
+    let code = match code {
+        Some(code) => code,
+        None => return Ok(Json(false)),
+    };
+
+    if !code_in_comment(id, &code).await {
+        return Ok(Json(false));
+    }
+
+    //The request can change while the comment is fetched, so it's checked again before hiding
+    Ok(Json(
+        conn.run(move |conn| {
+            match rater::claim_hide_code(conn, id, &code, Utc::now().timestamp()) {
+                Some(previous_state) => {
+                    let hidden = previous_state != rater::HideState::Hidden;
+                    info!("Found hide code for {:X}, setting hidden to {}", id, hidden);
+                    rater::set_hidden(conn, id, hidden, "player", "PlayerAutomated");
+                    true
+                }
+                None => false,
+            }
+        })
+        .await,
+    ))
+}
+
+/// Whether the code shows up in the player's R-Code comment, which proves the account is theirs.
+async fn code_in_comment(id: i64, code: &str) -> bool {
+    match ggst_api::get_public_comment(id.to_string()).await {
+        Ok(Some(comment)) => comment.contains(code),
+        Ok(None) => false,
+        Err(e) => {
+            error!("Couldn't get the R-Code comment of {:X}: {}", id, e);
+            false
+        }
+    }
+}
+
+const EXPORT_CODE_LIFETIME: i64 = 10 * 60;
+const EXPORT_REQUEST_COOLDOWN: i64 = 60;
+const EXPORT_POLL_COOLDOWN: i64 = 4;
+
+/// Starts a data export. Like hiding, the returned code has to be put in the player's R-Code
+/// comment before `download_player_export` hands out the data.
+#[post("/api/export/<player>")]
+pub async fn start_player_export(
+    conn: RatingsDbConn,
+    player: &str,
+) -> std::result::Result<Json<String>, Status> {
+    let id = i64::from_str_radix(player, 16).map_err(|_| Status::BadRequest)?;
+
+    conn.run(move |conn| {
+        let now = Utc::now().timestamp();
+
+        let exists: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM players WHERE id = ?)",
+                params![id],
+                |r| r.get(0),
+            )
+            .unwrap();
+        if !exists {
+            return Err(Status::NotFound);
+        }
 
-            let exists: i64 = tx
+        let request: Option<(String, i64, i64)> = conn
+            .query_row(
+                "SELECT code, code_expiry, last_request FROM export_requests WHERE id = ?",
+                params![id],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .optional()
+            .unwrap();
+
+        if let Some((code, code_expiry, last_request)) = request {
+            if code_expiry > now {
+                return Ok(Json(code));
+            }
+            if now - last_request < EXPORT_REQUEST_COOLDOWN {
+                return Err(Status::TooManyRequests);
+            }
+        }
+
+        let code = Alphanumeric.sample_string(&mut rand::thread_rng(), 8);
+        conn.execute(
+            "INSERT OR REPLACE INTO export_requests VALUES(?, ?, ?, ?, NULL)",
+            params![id, code, now + EXPORT_CODE_LIFETIME, now],
+        )
+        .unwrap();
+        Ok(Json(code))
+    })
+    .await
+}
+
+#[derive(Responder)]
+#[response(content_type = "json")]
+pub struct PlayerExport {
+    json: String,
+    disposition: Header<'static>,
+}
+
+/// Returns everything stored about the player once their export code is in their R-Code
+/// comment. Forbidden until then.
+#[post("/api/export/<player>/download")]
+pub async fn download_player_export(
+    conn: RatingsDbConn,
+    player: &str,
+) -> std::result::Result<PlayerExport, Status> {
+    let id = i64::from_str_radix(player, 16).map_err(|_| Status::BadRequest)?;
+
+    let code = conn
+        .run(move |conn| {
+            let now = Utc::now().timestamp();
+            let request: Option<(String, i64, Option<i64>)> = conn
                 .query_row(
-                    "SELECT count(id) FROM hidden_status WHERE id=? and hidden_status is not null",
-                    params![&id],
-                    |r| r.get(0),
+                    "SELECT code, code_expiry, last_poll FROM export_requests WHERE id = ?",
+                    params![id],
+                    |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
                 )
+                .optional()
                 .unwrap();
 
-            if exists > 0 {
-                tx.execute(
-                    "UPDATE hidden_status SET code=? WHERE id=?",
-                    params![&player_code, &id],
-                )
-                .unwrap();
-            } else {
-                tx.execute(
-                    "INSERT or replace INTO hidden_status(id, hidden_status, code, notes)
-                VALUES(?, NULL, ?, 'PlayerAutomated')",
-                    params![&id, &player_code],
-                )
+            match request {
+                Some((code, code_expiry, last_poll)) if code_expiry > now => {
+                    if last_poll
+                        .map(|t| now - t < EXPORT_POLL_COOLDOWN)
+                        .unwrap_or(false)
+                    {
+                        return Err(Status::TooManyRequests);
+                    }
+                    conn.execute(
+                        "UPDATE export_requests SET last_poll = ? WHERE id = ?",
+                        params![now, id],
+                    )
+                    .unwrap();
+                    Ok(code)
+                }
+                _ => Err(Status::NotFound),
+            }
+        })
+        .await?;
+
+    if !code_in_comment(id, &code).await {
+        return Err(Status::Forbidden);
+    }
+
+    info!("Exporting the data of {:X}", id);
+    let json = conn
+        .run(move |conn| {
+            conn.execute("DELETE FROM export_requests WHERE id = ?", params![id])
                 .unwrap();
-            }
-            let _ = tx.commit();
-            player_code
+            export_player(conn, id).to_string()
         })
         .await;
 
-    Json(code)
+    Ok(PlayerExport {
+        json,
+        disposition: Header::new(
+            "Content-Disposition",
+            format!("attachment; filename=\"rating-update-{:X}.json\"", id),
+        ),
+    })
 }
 
-#[get("/api/hide/poll/<player>")]
-pub async fn poll_hide_player(conn: RatingsDbConn, player: &str) -> Json<bool> {
-    info!("Starting poll hide player");
-    if let Ok(id) = i64::from_str_radix(&player, 16) {
-        let code: String = conn
-            .run(move |conn| {
-                conn.query_row(
-                    "SELECT code FROM hidden_status WHERE id=?",
-                    params![&id],
-                    |r| r.get(0),
-                )
-                .unwrap_or_else(|_| "".to_owned())
-            })
-            .await;
-        info!("Grabbed code {code}");
-
-        if code.is_empty() {
-            info!("No code, returning false");
-            Json(false)
+/// Everything stored about a player: their names, ratings, matchups, rating history, statuses
+/// and every game they played. Hidden opponents are pseudonymised in the games.
+pub fn export_player(conn: &Connection, id: i64) -> Value {
+    let tables: &[(&str, &str)] = &[
+        ("players", "SELECT * FROM players WHERE id = :id"),
+        ("player_names", "SELECT * FROM player_names WHERE id = :id"),
+        (
+            "player_ratings",
+            "SELECT * FROM player_ratings WHERE id = :id",
+        ),
+        (
+            "season_player_ratings",
+            "SELECT * FROM season_player_ratings WHERE id = :id",
+        ),
+        (
+            "player_matchups",
+            "SELECT * FROM player_matchups WHERE id = :id",
+        ),
+        (
+            "daily_ratings",
+            "SELECT * FROM daily_ratings WHERE id = :id",
+        ),
+        ("vip_status", "SELECT * FROM vip_status WHERE id = :id"),
+        ("supporters", "SELECT * FROM supporters WHERE id = :id"),
+        (
+            "hidden_status",
+            "SELECT * FROM hidden_status WHERE id = :id",
+        ),
+        ("hide_log", "SELECT * FROM hide_log WHERE id = :id"),
+        (
+            "cheater_status",
+            "SELECT * FROM cheater_status WHERE id = :id",
+        ),
+        (
+            "games",
+            "SELECT
+                timestamp,
+                CASE WHEN hidden_a THEN NULL ELSE id_a END AS id_a,
+                CASE WHEN hidden_a THEN :hidden_name ELSE name_a END AS name_a,
+                char_a,
+                CASE WHEN hidden_a THEN NULL ELSE platform_a END AS platform_a,
+                value_a,
+                deviation_a,
+                CASE WHEN hidden_b THEN NULL ELSE id_b END AS id_b,
+                CASE WHEN hidden_b THEN :hidden_name ELSE name_b END AS name_b,
+                char_b,
+                CASE WHEN hidden_b THEN NULL ELSE platform_b END AS platform_b,
+                value_b,
+                deviation_b,
+                winner,
+                game_floor,
+                valid
+            FROM (
+                SELECT *,
+                    id_a != :id AND id_a IN (SELECT id FROM hidden_status) AS hidden_a,
+                    id_b != :id AND id_b IN (SELECT id FROM hidden_status) AS hidden_b
+                FROM games NATURAL LEFT JOIN game_ratings
+                WHERE id_a = :id OR id_b = :id
+            )
+            ORDER BY timestamp ASC",
+        ),
+    ];
+
+    let mut export = vec![
+        ("id".to_owned(), Value::from(format!("{:X}", id))),
+        (
+            "exported_at".to_owned(),
+            Value::from(Utc::now().timestamp()),
+        ),
+    ];
+
+    for (table, sql) in tables {
+        let mut stmt = conn.prepare(sql).unwrap();
+        let columns = stmt
+            .column_names()
+            .into_iter()
+            .map(|c| c.to_owned())
+            .collect::<Vec<_>>();
+
+        let mut rows = if sql.contains(":hidden_name") {
+            stmt.query(named_params! { ":id": id, ":hidden_name": HIDDEN_NAME })
         } else {
-            info!("Getting player stats");
-            let json = ggst_api::get_player_stats(id.to_string()).await;
-            let lookup = format!("PublicComment\":\"{code}");
-
-            let found = match json {
-                Ok(json) => json.contains(&lookup),
-                Err(er) => {
-                    error!("error {}", er);
-                    false
-                }
-            };
-
-            if found {
-                info!("Found code, setting hidden status");
-                conn.run(move |conn| {
-                    let exists: i64 = conn.query_row(
-                        "SELECT count(id) FROM hidden_status WHERE id=? and hidden_status is not null",
-                        params![&id],
-                        |r| r.get(0)
-                    ).unwrap();
-
-                    info!("Does code exist? {exists}");
-
-                    if exists > 0 {
-                        info!("Setting hidden status to null");
-                        conn.execute(
-                            "UPDATE hidden_status SET hidden_status=NULL, code=NULL WHERE id=?",
-                            params![&id],
-                        ).unwrap();
-
-                    } else {
-                        info!("Setting hidden status to enabled");
-                        conn.execute(
-                            "UPDATE hidden_status SET hidden_status='enabled', code=NULL WHERE id=?",
-                            params![&id]
-                        ).unwrap();
-                    }
-                }).await;
+            stmt.query(named_params! { ":id": id })
+        }
+        .unwrap();
 
-                return Json(true);
-            }
-            Json(false)
+        let mut values = Vec::new();
+        while let Some(row) = rows.next().unwrap() {
+            values.push(Value::Object(
+                columns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, column)| {
+                        let value = match row.get_ref(i).unwrap() {
+                            ValueRef::Null => Value::Null,
+                            ValueRef::Integer(i) => Value::from(i),
+                            ValueRef::Real(f) => Value::from(f),
+                            ValueRef::Text(t) => Value::from(String::from_utf8_lossy(t)),
+                            ValueRef::Blob(b) => Value::from(hex::encode(b)),
+                        };
+                        (column.clone(), value)
+                    })
+                    .collect(),
+            ));
         }
-    } else {
-        Json(false)
+        export.push((table.to_string(), Value::Array(values)));
     }
+
+    Value::Object(export.into_iter().collect())
 }
 
 #[get("/api/outcomes_delta")]
@@ -2604,7 +4698,7 @@ pub async fn outcomes_delta(conn: RatingsDbConn) -> Json<(Vec<i64>, Vec<f64>, Ve
                     * glicko::g(rating_b.deviation)
                     * (rating_a.value - rating_b.value);
                 let delta_category = (delta / 10.0).round() as i32 + 100;
-                if delta_category >= 0 && delta_category <= 200 {
+                if (0..=200).contains(&delta_category) {
                     let delta_category = delta_category as usize;
                     let o = outcomes.get_mut(delta_category).unwrap();
                     if winner == 1 {
@@ -2615,9 +4709,8 @@ pub async fn outcomes_delta(conn: RatingsDbConn) -> Json<(Vec<i64>, Vec<f64>, Ve
             }
 
             (
-                (0..=200).into_iter().map(|i| (i - 100) * 10).collect(),
+                (0..=200).map(|i| (i - 100) * 10).collect(),
                 (0..=200)
-                    .into_iter()
                     .map(|i| (i - 100) * 10)
                     .map(|i| crate::glicko::e(i as f64, 0.0, 0.0))
                     .collect(),
@@ -2630,3 +4723,768 @@ pub async fn outcomes_delta(conn: RatingsDbConn) -> Json<(Vec<i64>, Vec<f64>, Ve
         .await,
     )
 }
+
+#[derive(Serialize)]
+pub struct ModerationPlayer {
+    id: String,
+    name: String,
+    characters: Vec<OtherPlayerCharacter>,
+    vip_status: Option<ModerationStatus>,
+    cheater_status: Option<ModerationStatus>,
+    hidden_status: Option<ModerationStatus>,
+    hide_state: &'static str,
+    supporters: Vec<SupporterPeriod>,
+    moderation_log: Vec<ModerationLogEntry>,
+    cheater_log: Vec<CheaterLogEntry>,
+}
+
+#[derive(Serialize)]
+struct SupporterPeriod {
+    tier: String,
+    since: String,
+    until: Option<String>,
+    badge_text: Option<String>,
+    badge_colour: Option<String>,
+    notes: String,
+}
+
+#[derive(Serialize)]
+struct ModerationStatus {
+    status: Option<String>,
+    notes: String,
+}
+
+#[derive(Serialize)]
+struct ModerationLogEntry {
+    timestamp: String,
+    action: String,
+    moderator: String,
+    notes: String,
+}
+
+#[derive(Serialize)]
+struct CheaterLogEntry {
+    timestamp: String,
+    action: String,
+    cheater_status: Option<String>,
+    moderator: String,
+    notes: String,
+    rerated: i64,
+}
+
+pub async fn get_moderation_player(conn: &RatingsDbConn, id: i64) -> Option<ModerationPlayer> {
+    conn.run(move |conn| {
+        let name: String = conn
+            .query_row("SELECT name FROM players WHERE id = ?", params![id], |r| {
+                r.get(0)
+            })
+            .optional()
+            .unwrap()?;
+
+        let characters = {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT char_id, value, deviation, wins, losses
+                    FROM player_ratings
+                    WHERE id = ?
+                    ORDER BY wins + losses DESC",
+                )
+                .unwrap();
+            let mut rows = stmt.query(params![id]).unwrap();
+            let mut characters = Vec::new();
+            while let Some(row) = rows.next().unwrap() {
+                let char_id: usize = row.get(0).unwrap();
+                characters.push(OtherPlayerCharacter {
+                    character_name: website::CHAR_NAMES[char_id].1.to_owned(),
+                    character_shortname: website::CHAR_NAMES[char_id].0.to_owned(),
+                    rating_value: row.get::<_, f64>(1).unwrap().round() as i64,
+                    rating_deviation: (row.get::<_, f64>(2).unwrap() * 2.0).round() as i64,
+                    game_count: row.get::<_, i32>(3).unwrap() + row.get::<_, i32>(4).unwrap(),
+                });
+            }
+            characters
+        };
+
+        let get_status = |table: &str| {
+            conn.query_row(
+                &format!("SELECT {0}, notes FROM {0} WHERE id = ?", table),
+                params![id],
+                |r| {
+                    Ok(ModerationStatus {
+                        status: r.get(0)?,
+                        notes: r.get(1)?,
+                    })
+                },
+            )
+            .optional()
+            .unwrap()
+        };
+
+        let to_date = |timestamp: i64| {
+            DateTime::from_timestamp(timestamp, 0)
+                .unwrap()
+                .format("%Y-%m-%d")
+                .to_string()
+        };
+
+        let supporters = {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT tier, start_timestamp, expiry_timestamp, badge_text, badge_colour, notes
+                    FROM supporters
+                    WHERE id = ?
+                    ORDER BY start_timestamp DESC",
+                )
+                .unwrap();
+            let mut rows = stmt.query(params![id]).unwrap();
+            let mut supporters = Vec::new();
+            while let Some(row) = rows.next().unwrap() {
+                supporters.push(SupporterPeriod {
+                    tier: row.get(0).unwrap(),
+                    since: to_date(row.get(1).unwrap()),
+                    until: row.get::<_, Option<i64>>(2).unwrap().map(to_date),
+                    badge_text: row.get(3).unwrap(),
+                    badge_colour: row.get(4).unwrap(),
+                    notes: row.get(5).unwrap(),
+                });
+            }
+            supporters
+        };
+
+        let to_time = |timestamp: i64| {
+            DateTime::from_timestamp(timestamp, 0)
+                .unwrap()
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        };
+
+        let moderation_log = {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT timestamp, action, moderator, notes
+                    FROM moderation_log
+                    WHERE id = ?
+                    ORDER BY timestamp DESC, rowid DESC",
+                )
+                .unwrap();
+            let mut rows = stmt.query(params![id]).unwrap();
+            let mut log = Vec::new();
+            while let Some(row) = rows.next().unwrap() {
+                log.push(ModerationLogEntry {
+                    timestamp: to_time(row.get(0).unwrap()),
+                    action: row.get(1).unwrap(),
+                    moderator: row.get(2).unwrap(),
+                    notes: row.get(3).unwrap(),
+                });
+            }
+            log
+        };
+
+        let cheater_log = {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT timestamp, action, cheater_status, moderator, notes,
+                        (SELECT COUNT(*) FROM cheater_offsets
+                            WHERE cheater_offsets.log_id = cheater_log.log_id)
+                    FROM cheater_log
+                    WHERE id = ?
+                    ORDER BY log_id DESC",
+                )
+                .unwrap();
+            let mut rows = stmt.query(params![id]).unwrap();
+            let mut log = Vec::new();
+            while let Some(row) = rows.next().unwrap() {
+                log.push(CheaterLogEntry {
+                    timestamp: to_time(row.get(0).unwrap()),
+                    action: row.get(1).unwrap(),
+                    cheater_status: row.get(2).unwrap(),
+                    moderator: row.get(3).unwrap(),
+                    notes: row.get(4).unwrap(),
+                    rerated: row.get(5).unwrap(),
+                });
+            }
+            log
+        };
+
+        Some(ModerationPlayer {
+            id: format!("{:X}", id),
+            name,
+            characters,
+            vip_status: get_status("vip_status"),
+            cheater_status: get_status("cheater_status"),
+            hidden_status: get_status("hidden_status"),
+            hide_state: rater::get_hide_state(conn, id).as_str(),
+            supporters,
+            moderation_log,
+            cheater_log,
+        })
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct CheaterOffset {
+    id: String,
+    name: String,
+    character_short: &'static str,
+    offset: String,
+}
+
+/// The rating each opponent is estimated to get back if the player is marked as a cheater,
+/// largest first.
+pub async fn get_cheater_offset_preview(conn: &RatingsDbConn, id: i64) -> Vec<CheaterOffset> {
+    conn.run(move |conn| {
+        let mut offsets = rater::get_cheater_offsets(conn, id)
+            .into_iter()
+            .filter(|((opp_id, _), _)| *opp_id != id)
+            .collect::<Vec<_>>();
+        offsets.sort_by(|(_, a), (_, b)| b.abs().partial_cmp(&a.abs()).unwrap());
+
+        offsets
+            .into_iter()
+            .map(|((opp_id, char_id), offset)| CheaterOffset {
+                id: format!("{:X}", opp_id),
+                name: conn
+                    .query_row(
+                        "SELECT name FROM players WHERE id = ?",
+                        params![opp_id],
+                        |r| r.get(0),
+                    )
+                    .unwrap_or_default(),
+                character_short: website::CHAR_NAMES[char_id as usize].0,
+                offset: format!("{:+.1}", offset),
+            })
+            .collect()
+    })
+    .await
+}
+
+/// A moderation action from the admin pages. Empty optional fields count as left out, the
+/// moderator has to be filled in.
+#[derive(FromForm)]
+pub struct ModerationForm {
+    status: String,
+    revoke: bool,
+    notes: String,
+    moderator: String,
+    cheater_type: Option<String>,
+    tier: Option<String>,
+    expiry_date: Option<String>,
+    badge_text: Option<String>,
+    badge_colour: Option<String>,
+}
+
+/// Applies (or with `revoke` removes) a vip, cheater or hidden status and describes what
+/// happened.
+pub async fn set_moderation_status(conn: &RatingsDbConn, id: i64, form: ModerationForm) -> String {
+    let non_empty = |field: Option<String>| field.filter(|f| !f.is_empty());
+    let moderator = form.moderator.trim().to_owned();
+    if moderator.is_empty() {
+        return "Moderator can't be empty".to_owned();
+    }
+    let cheater_type = non_empty(form.cheater_type).unwrap_or_else(|| "cheater".to_owned());
+    let expiry_timestamp = match non_empty(form.expiry_date)
+        .map(|d| rater::date_to_timestamp(&d))
+        .transpose()
+    {
+        Ok(expiry_timestamp) => expiry_timestamp,
+        Err(_) => return "Expiry dates look like 2024-01-31".to_owned(),
+    };
+    let supporter = rater::Supporter {
+        tier: non_empty(form.tier).unwrap_or_else(|| "VIP".to_owned()),
+        expiry_timestamp,
+        badge_text: non_empty(form.badge_text),
+        badge_colour: non_empty(form.badge_colour),
+    };
+    let (status, revoke, notes) = (form.status, form.revoke, form.notes);
+
+    conn.run(move |conn| match (status.as_str(), revoke) {
+        ("vip", false) => {
+            rater::add_supporter(conn, id, &supporter, &notes).unwrap();
+            rater::log_moderation(conn, id, "vip", &moderator, &notes);
+            format!("Marked {:X} as {}", id, supporter.tier)
+        }
+        ("vip", true) => match rater::end_supporter(conn, id).unwrap() {
+            true => {
+                rater::log_moderation(conn, id, "end_vip", &moderator, &notes);
+                format!("Ended the supporter status of {:X}", id)
+            }
+            false => format!("{:X} is not a supporter", id),
+        },
+        ("hidden", false) => match rater::set_hidden(conn, id, true, "admin", &notes) {
+            true => {
+                rater::log_moderation(conn, id, "hide", &moderator, &notes);
+                format!("Hid {:X}", id)
+            }
+            false => format!("{:X} is already hidden", id),
+        },
+        ("hidden", true) => match rater::set_hidden(conn, id, false, "admin", &notes) {
+            true => {
+                rater::log_moderation(conn, id, "unhide", &moderator, &notes);
+                format!("Unhid {:X}", id)
+            }
+            false => format!("{:X} is not hidden", id),
+        },
+        //Re-rating can take a while, the rater picks it up on its next ranking update
+        ("cheater", false) => {
+            if rater::set_cheater(conn, id, &cheater_type, &notes, &moderator) {
+                rater::queue_rerate(conn, id);
+                format!(
+                    "Marked {:X} as {}, their opponents are re-rated within the hour",
+                    id, cheater_type
+                )
+            } else {
+                format!("{:X} was already marked, updated the status", id)
+            }
+        }
+        ("cheater", true) => {
+            if rater::remove_cheater(conn, id, &notes, &moderator) {
+                rater::queue_rerate(conn, id);
+                format!(
+                    "Unmarked {:X}, their opponents are re-rated within the hour",
+                    id
+                )
+            } else {
+                format!("{:X} is not marked as a cheater", id)
+            }
+        }
+        _ => format!("Unknown status {}", status),
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rater::tests::{play, test_db};
+
+    fn parse(line: &str) -> (String, Option<String>) {
+        let entrant = Entrant::parse(line).unwrap();
+        (entrant.player, entrant.character)
+    }
+
+    #[test]
+    fn entrant_lines() {
+        assert_eq!(
+            parse(" Sol Badguy, so "),
+            ("Sol Badguy".to_owned(), Some("SO".to_owned()))
+        );
+        assert_eq!(
+            parse("2EC3B2D0B4C1A13"),
+            ("2EC3B2D0B4C1A13".to_owned(), None)
+        );
+        //Only a character after the last comma splits the line
+        assert_eq!(parse("a,b"), ("a,b".to_owned(), None));
+        assert_eq!(parse("a,b,KY"), ("a,b".to_owned(), Some("KY".to_owned())));
+        assert!(Entrant::parse("  ").is_none());
+    }
+
+    #[test]
+    fn match_suggestions_leave_out_hidden_cheating_and_inactive_players() {
+        let mut conn = test_db();
+        let me = (1, 0);
+        play(&mut conn, 1000, me, (2, 1), 1);
+        play(&mut conn, 1100, (3, 2), (4, 3), 1);
+        play(&mut conn, 1200, (5, 4), (6, 5), 1);
+        conn.execute("INSERT INTO cheater_status VALUES(4, 'cheater', '')", [])
+            .unwrap();
+        conn.execute("INSERT INTO hidden_status VALUES(5, 'hidden', '')", [])
+            .unwrap();
+
+        let suggested = |conn: &Connection, since: i64, min_probability: f64, limit: usize| {
+            let mut ids: Vec<String> =
+                get_match_suggestions(conn, me.0, me.1, min_probability, 1.0, since, limit)
+                    .unwrap()
+                    .into_iter()
+                    .map(|s| s.id)
+                    .collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(suggested(&conn, 0, 0.0, 10), vec!["2", "3", "6"]);
+        assert_eq!(suggested(&conn, 1000, 0.0, 10), vec!["3", "6"]);
+        assert_eq!(suggested(&conn, 0, 0.0, 1).len(), 1);
+        //Nobody is that much weaker than someone with a single win
+        assert!(suggested(&conn, 0, 0.99, 10).is_empty());
+        assert!(get_match_suggestions(&conn, me.0, 7, 0.0, 1.0, 0, 10).is_none());
+    }
+
+    #[test]
+    fn default_season_is_the_ongoing_one() {
+        let season = |season_id, current| SeasonInfo {
+            season_id,
+            name: format!("Season {}", season_id),
+            start: String::new(),
+            end: None,
+            current,
+        };
+
+        assert_eq!(
+            default_season(&[season(3, false), season(2, true)]),
+            Some(2)
+        );
+        assert_eq!(
+            default_season(&[season(3, false), season(2, false)]),
+            Some(3)
+        );
+        assert_eq!(default_season(&[]), None);
+    }
+
+    #[test]
+    fn former_names_are_listed_and_searchable() {
+        let mut conn = test_db();
+        play(&mut conn, 1000, (1, 0), (2, 1), 1);
+        conn.execute(
+            "INSERT INTO games VALUES(2000, 1, 'Renamed', 0, 1, 3, 'p3', 2, 1, 1, 10)",
+            [],
+        )
+        .unwrap();
+        //Undated like names backfilled by migrate_player_names.sql without games to go by
+        conn.execute(
+            "INSERT INTO player_names VALUES(4, 'p4', 0, 0), (4, 'Undated', 0, 0)",
+            [],
+        )
+        .unwrap();
+        play(&mut conn, 3000, (1, 0), (4, 3), 1);
+
+        let names: Vec<(String, i64, i64)> = conn
+            .prepare("SELECT name, first_seen, last_seen FROM player_names WHERE id = 1 ORDER BY first_seen")
+            .unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("p1".to_owned(), 1000, 3000),
+                ("Renamed".to_owned(), 2000, 2000)
+            ]
+        );
+
+        let other_names = get_player_other_names(&conn, 1, "p1").unwrap();
+        assert_eq!(other_names.len(), 1);
+        assert_eq!(other_names[0].name, "Renamed");
+        assert_eq!(other_names[0].first_seen, "1970-01-01");
+        assert!(get_player_other_names(&conn, 2, "p2").is_none());
+
+        let dated: (i64, i64) = conn
+            .query_row(
+                "SELECT first_seen, last_seen FROM player_names WHERE id = 4 AND name = 'p4'",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(dated, (3000, 3000));
+        let undated = get_player_other_names(&conn, 4, "p4").unwrap();
+        assert_eq!(undated[0].first_seen, "unknown");
+        assert_eq!(undated[0].last_seen, "unknown");
+
+        for exact in [true, false] {
+            let found = search_players(&conn, "Renamed".to_owned(), exact, false).unwrap();
+            assert_eq!(found.len(), 1);
+            assert_eq!(found[0].name, "p1");
+            assert_eq!(found[0].former_name.as_deref(), Some("Renamed"));
+
+            let found = search_players(&conn, "p1".to_owned(), exact, false).unwrap();
+            assert_eq!(found[0].former_name, None);
+        }
+    }
+
+    #[test]
+    fn player_games_pages_and_filters() {
+        let mut conn = test_db();
+        play(&mut conn, 1000, (1, 0), (2, 1), 1);
+        play(&mut conn, 1100, (2, 1), (1, 0), 1);
+        play(&mut conn, 1200, (1, 0), (2, 1), 1);
+        play(&mut conn, 2000, (1, 0), (3, 2), 1);
+        play(&mut conn, 3000, (4, 1), (1, 0), 1);
+
+        let page = |filter: &GameFilter, cursor: Option<&str>, limit, group_games| {
+            let page = get_player_char_games(
+                &conn,
+                1,
+                0,
+                filter,
+                cursor.map(|c| GameCursor::parse(c).unwrap()),
+                limit,
+                group_games,
+            );
+            let games: Vec<(i64, i32, i32)> = page
+                .games
+                .iter()
+                .map(|g| (g.timestamp, g.wins, g.losses))
+                .collect();
+            (games, page.next_cursor)
+        };
+        let all = GameFilter::default();
+
+        assert_eq!(
+            page(&all, None, 2, false),
+            (vec![(3000, 0, 1), (2000, 1, 0)], Some("2000_3".to_owned()))
+        );
+        assert_eq!(
+            page(&all, Some("2000_3"), 2, false),
+            (vec![(1200, 1, 0), (1100, 0, 1)], Some("1100_2".to_owned()))
+        );
+        assert_eq!(
+            page(&all, Some("1100_2"), 2, false),
+            (vec![(1000, 1, 0)], None)
+        );
+
+        //Sets aren't split between pages, and go by when they started
+        assert_eq!(
+            page(&all, Some("2000_3"), 1, true),
+            (vec![(1000, 2, 1)], None)
+        );
+        assert_eq!(page(&all, None, 10, true).0.len(), 3);
+
+        let count = |filter: GameFilter| page(&filter, None, 10, false).0.len();
+        assert_eq!(
+            count(GameFilter {
+                opponent_char: Some(1),
+                ..Default::default()
+            }),
+            4
+        );
+        assert_eq!(
+            count(GameFilter {
+                opponent_id: Some(2),
+                ..Default::default()
+            }),
+            3
+        );
+        assert_eq!(
+            count(GameFilter {
+                won: Some(false),
+                ..Default::default()
+            }),
+            2
+        );
+        assert_eq!(
+            count(GameFilter {
+                from: Some(1100),
+                to: Some(2000),
+                ..Default::default()
+            }),
+            2
+        );
+        assert_eq!(
+            count(GameFilter {
+                floor: Some(9),
+                ..Default::default()
+            }),
+            0
+        );
+
+        //A hidden opponent can't be looked up by id, nor found through the cursor
+        conn.execute("INSERT INTO hidden_status VALUES(3, 'hidden', '')", [])
+            .unwrap();
+        assert_eq!(
+            count(GameFilter {
+                opponent_id: Some(3),
+                ..Default::default()
+            }),
+            0
+        );
+        assert_eq!(
+            page(&all, Some("3000_4"), 1, false),
+            (vec![(2000, 1, 0)], Some("2000".to_owned()))
+        );
+        assert_eq!(page(&all, Some("2000"), 1, false).0, vec![(1200, 1, 0)]);
+
+        assert!(GameCursor::parse("2000_").is_none());
+        assert!(GameCursor::parse("soon").is_none());
+    }
+
+    #[test]
+    fn head_to_head_groups_sets_from_either_side() {
+        let mut conn = test_db();
+        play(&mut conn, 1000, (1, 0), (2, 1), 1);
+        play(&mut conn, 1100, (2, 1), (1, 0), 1);
+        play(&mut conn, 1200, (1, 0), (2, 1), 1);
+        play(&mut conn, 1300, (1, 0), (3, 1), 1);
+        //Long enough after the last game to be a new set even with the same characters
+        play(&mut conn, 1200 + HEAD_TO_HEAD_SET_GAP, (1, 0), (2, 1), 2);
+        play(&mut conn, 1300 + HEAD_TO_HEAD_SET_GAP, (2, 1), (1, 3), 1);
+
+        let h2h = get_head_to_head(&conn, 1, 2).unwrap();
+        assert_eq!(h2h.player_a.name, "p1");
+        assert_eq!((h2h.wins_a, h2h.wins_b), (2, 3));
+        assert_eq!(h2h.set_count, 3);
+        let sets: Vec<(i64, &str, i32, i32)> = h2h
+            .sets
+            .iter()
+            .map(|s| (s.timestamp, s.character_a_short, s.wins_a, s.wins_b))
+            .collect();
+        assert_eq!(
+            sets,
+            vec![
+                (1300 + HEAD_TO_HEAD_SET_GAP, website::CHAR_NAMES[3].0, 0, 1),
+                (1200 + HEAD_TO_HEAD_SET_GAP, website::CHAR_NAMES[0].0, 0, 1),
+                (1000, website::CHAR_NAMES[0].0, 2, 1),
+            ]
+        );
+        assert_eq!(h2h.characters.len(), 2);
+        assert_eq!((h2h.characters[0].wins_a, h2h.characters[0].wins_b), (2, 2));
+
+        let reverse = get_head_to_head(&conn, 2, 1).unwrap();
+        assert_eq!((reverse.wins_a, reverse.wins_b), (3, 2));
+        assert_eq!(reverse.rating_change_a, h2h.rating_change_b);
+        assert_eq!(reverse.rating_change_b, h2h.rating_change_a);
+        let chances = h2h.win_probability_a.unwrap() + reverse.win_probability_a.unwrap();
+        assert!((chances - 100.0).abs() < 0.11);
+
+        assert!(get_head_to_head(&conn, 1, 5).is_none());
+    }
+
+    #[test]
+    fn comparisons_include_only_pairs_that_have_played() {
+        let mut conn = test_db();
+        play(&mut conn, 1000, (1, 0), (2, 1), 1);
+        play(&mut conn, 1100, (2, 1), (1, 0), 2);
+        play(&mut conn, 1200, (1, 4), (3, 2), 2);
+
+        assert!(get_comparison(&conn, &[1, 9]).is_none());
+
+        let comparison = get_comparison(&conn, &[1, 2, 3]).unwrap();
+        assert_eq!(comparison.players.len(), 3);
+        let characters: Vec<(&str, i32)> = comparison.players[0]
+            .characters
+            .iter()
+            .map(|c| (c.character_short, c.game_count))
+            .collect();
+        assert_eq!(
+            characters,
+            vec![(website::CHAR_NAMES[0].0, 2), (website::CHAR_NAMES[4].0, 1)]
+        );
+        let pairs: Vec<(&str, &str, i32, i32)> = comparison
+            .head_to_heads
+            .iter()
+            .map(|h| (&h.player_a[..], &h.player_b[..], h.wins_a, h.wins_b))
+            .collect();
+        assert_eq!(pairs, vec![("1", "2", 2, 0), ("1", "3", 0, 1)]);
+
+        conn.execute("DELETE FROM daily_ratings", []).unwrap();
+        assert!(rating_chart(&get_comparison(&conn, &[1, 2]).unwrap()).is_none());
+
+        conn.execute_batch(
+            "INSERT INTO daily_ratings VALUES(1, 0, 0, 1500.0, 100.0);
+            INSERT INTO daily_ratings VALUES(1, 0, 86400, 1600.0, 80.0);
+            INSERT INTO daily_ratings VALUES(2, 1, 86400, 1400.0, 90.0);",
+        )
+        .unwrap();
+        let chart = rating_chart(&get_comparison(&conn, &[1, 2]).unwrap()).unwrap();
+        assert_eq!((chart.min_rating, chart.max_rating), (1375, 1625));
+        let lines: Vec<(&str, &str)> = chart
+            .lines
+            .iter()
+            .map(|l| (&l.label[..], &l.points[..]))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                (
+                    &format!("p1 ({})", website::CHAR_NAMES[0].1)[..],
+                    "0.0,150.0 800.0,30.0"
+                ),
+                (
+                    &format!("p2 ({})", website::CHAR_NAMES[1].1)[..],
+                    "800.0,270.0"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn hidden_players_are_pseudonymised_for_others() {
+        let mut conn = test_db();
+        play(&mut conn, 1000, (1, 0), (2, 1), 1);
+        conn.execute(
+            "UPDATE player_ratings SET
+                top_defeated_id = 2, top_defeated_char_id = 1, top_defeated_name = 'p2',
+                top_defeated_value = 1600.0, top_defeated_deviation = 50.0,
+                top_defeated_floor = 10, top_defeated_timestamp = 1000
+            WHERE id = 1",
+            [],
+        )
+        .unwrap();
+
+        let data = get_player_character_data(&conn, 1, 0).unwrap().unwrap();
+        assert_eq!(data.top_defeated_id.as_deref(), Some("2"));
+        assert_eq!(data.top_defeated_name.as_deref(), Some("p2"));
+        assert!(!data.top_defeated_hidden);
+
+        conn.execute("INSERT INTO hidden_status VALUES(2, 'hidden', '')", [])
+            .unwrap();
+
+        let data = get_player_character_data(&conn, 1, 0).unwrap().unwrap();
+        assert_eq!(data.top_defeated_id, None);
+        assert_eq!(data.top_defeated_name.as_deref(), Some(HIDDEN_NAME));
+        assert!(data.top_defeated_hidden);
+
+        let compared = get_compared_characters(&conn, 1);
+        let top_defeated = compared[0].top_defeated.as_ref().unwrap();
+        assert_eq!(top_defeated.id, None);
+        assert_eq!(top_defeated.name, HIDDEN_NAME);
+
+        let set = |opponent_hidden| RawPlayerSet {
+            timestamp: 1000,
+            own_value: 1500.0,
+            own_deviation: 100.0,
+            floor: 10,
+            opponent_name: "p2".to_owned(),
+            opponent_platform: "PS",
+            opponent_vip: false,
+            opponent_cheater: false,
+            opponent_hidden,
+            opponent_id: 2,
+            opponent_char: 1,
+            opponent_value: 1500.0,
+            opponent_deviation: 100.0,
+            valid: true,
+            rating_change_sequence: vec![10.0],
+            result_wins: 1,
+            result_losses: 0,
+        };
+
+        let game_set = set(false).into_game_set();
+        assert_eq!(game_set.opponent_id.as_deref(), Some("2"));
+        assert_eq!(game_set.opponent_name, "p2");
+        let game_set = set(true).into_game_set();
+        assert_eq!(game_set.opponent_id, None);
+        assert_eq!(game_set.opponent_name, HIDDEN_NAME);
+        assert_eq!(game_set.opponent_platform, "");
+
+        let formatted = set(true).into_formatted_set();
+        assert_eq!(formatted.opponent_id, "");
+        assert_eq!(formatted.opponent_name, HIDDEN_NAME);
+        assert_eq!(formatted.opponent_platform, "");
+    }
+
+    #[test]
+    fn exports_pseudonymise_hidden_opponents() {
+        let mut conn = test_db();
+        play(&mut conn, 1000, (1, 0), (2, 1), 1);
+        play(&mut conn, 1100, (1, 0), (3, 2), 2);
+        conn.execute("INSERT INTO hidden_status VALUES(2, 'hidden', '')", [])
+            .unwrap();
+
+        let export = export_player(&conn, 1);
+        let games = export["games"].as_array().unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0]["id_a"], 1);
+        assert_eq!(games[0]["id_b"], Value::Null);
+        assert_eq!(games[0]["name_b"], HIDDEN_NAME);
+        assert_eq!(games[0]["platform_b"], Value::Null);
+        assert_eq!(games[1]["id_b"], 3);
+        assert_eq!(games[1]["name_b"], "p3");
+
+        //Hidden players still get their own data
+        let export = export_player(&conn, 2);
+        assert_eq!(export["games"][0]["id_b"], 2);
+        assert_eq!(export["games"][0]["name_b"], "p2");
+        assert_eq!(export["hidden_status"].as_array().unwrap().len(), 1);
+    }
+}
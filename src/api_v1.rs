@@ -0,0 +1,2244 @@
+//! The versioned JSON API, mounted under `/api/v1`.
+//!
+//! Responses are structs with named fields and every failure is an [`ApiError`] with a matching
+//! HTTP status. The unversioned `/api/*` routes in `api.rs` stay around with their old shapes
+//! for existing users, most of the handlers here wrap them.
+use crate::{
+    api, bracket,
+    glicko::{self, Rating},
+    rater,
+    website::{self, RatingsDbConn},
+};
+use chrono::Utc;
+use rocket::{
+    http::Status,
+    response::{self, Responder},
+    serde::{
+        json::{json, Json, Value},
+        Serialize,
+    },
+    Request,
+};
+use rusqlite::{params, Connection, OptionalExtension};
+
+const MAX_DAYS: i64 = 365;
+const MAX_WEEKS: i64 = 104;
+const DEFAULT_GAMES: usize = 100;
+const MAX_GAMES: usize = 500;
+
+#[derive(Serialize)]
+pub struct ErrorBody {
+    status: u16,
+    error: &'static str,
+    message: String,
+}
+
+pub struct ApiError {
+    status: Status,
+    body: ErrorBody,
+}
+
+impl ApiError {
+    pub(crate) fn new(status: Status, error: &'static str, message: String) -> Self {
+        Self {
+            status,
+            body: ErrorBody {
+                status: status.code,
+                error,
+                message,
+            },
+        }
+    }
+
+    fn from_status(status: Status) -> Self {
+        let error = match status.code {
+            400 => "bad_request",
+            403 => "forbidden",
+            404 => "not_found",
+            422 => "bad_parameter",
+            429 => "rate_limited",
+            _ => "internal_error",
+        };
+        Self::new(
+            status,
+            error,
+            status.reason().unwrap_or("Unknown error").to_owned(),
+        )
+    }
+
+    fn player_not_found(player: &str) -> Self {
+        Self::new(
+            Status::NotFound,
+            "player_not_found",
+            format!("There is no player with the id {}", player),
+        )
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        (self.status, Json(self.body)).respond_to(req)
+    }
+}
+
+pub(crate) type ApiResult<T> = std::result::Result<Json<T>, ApiError>;
+
+/// Gives everything under `/api/v1`, including unknown routes and bad query parameters, the
+/// same error body.
+#[catch(default)]
+pub fn catch_default(status: Status, _req: &Request) -> ApiError {
+    ApiError::from_status(status)
+}
+
+fn parse_player(player: &str) -> std::result::Result<i64, ApiError> {
+    i64::from_str_radix(player, 16).map_err(|_| {
+        ApiError::new(
+            Status::BadRequest,
+            "bad_player_id",
+            format!("{} is not a hexadecimal player id", player),
+        )
+    })
+}
+
+pub(crate) fn parse_character(character: &str) -> std::result::Result<usize, ApiError> {
+    website::CHAR_NAMES
+        .iter()
+        .position(|(c, _)| *c == character)
+        .ok_or_else(|| {
+            ApiError::new(
+                Status::NotFound,
+                "unknown_character",
+                format!("{} is not a known character", character),
+            )
+        })
+}
+
+/// Hidden players don't exist as far as the public endpoints are concerned, but they still
+/// need to reach the hide and export endpoints.
+pub(crate) async fn require_player(
+    conn: &RatingsDbConn,
+    player: &str,
+    include_hidden: bool,
+) -> std::result::Result<i64, ApiError> {
+    let id = parse_player(player)?;
+    let found = conn
+        .run(move |c| {
+            let exists: bool = c
+                .query_row(
+                    "SELECT EXISTS(SELECT 1 FROM players WHERE id = ?)",
+                    params![id],
+                    |r| r.get(0),
+                )
+                .unwrap();
+            exists && (include_hidden || !api::is_hidden(c, id))
+        })
+        .await;
+
+    if found {
+        Ok(id)
+    } else {
+        Err(ApiError::player_not_found(player))
+    }
+}
+
+async fn require_season(
+    conn: &RatingsDbConn,
+    season: Option<i64>,
+) -> std::result::Result<(), ApiError> {
+    if let Some(season) = season {
+        if !api::get_seasons(conn)
+            .await
+            .iter()
+            .any(|s| s.season_id == season)
+        {
+            return Err(ApiError::new(
+                Status::NotFound,
+                "unknown_season",
+                format!("There is no season {}", season),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn require_length(length: Option<i64>, max: i64) -> std::result::Result<(), ApiError> {
+    match length {
+        Some(length) if !(1..=max).contains(&length) => Err(ApiError::new(
+            Status::BadRequest,
+            "bad_length",
+            format!("length has to be between 1 and {}", max),
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[get("/stats")]
+pub async fn stats(conn: RatingsDbConn) -> Json<api::Stats> {
+    api::stats(conn).await
+}
+
+#[derive(Serialize)]
+pub struct GameCounts {
+    dates: Vec<String>,
+    games: Vec<i64>,
+    players: Vec<i64>,
+}
+
+#[get("/daily_games?<length>")]
+pub async fn daily_games(conn: RatingsDbConn, length: Option<i64>) -> ApiResult<GameCounts> {
+    require_length(length, MAX_DAYS)?;
+    let (dates, games, players) = api::daily_games(conn, length).await.into_inner();
+    Ok(Json(GameCounts {
+        dates,
+        games,
+        players,
+    }))
+}
+
+#[get("/weekly_games?<length>")]
+pub async fn weekly_games(conn: RatingsDbConn, length: Option<i64>) -> ApiResult<GameCounts> {
+    require_length(length, MAX_WEEKS)?;
+    let (dates, games, players) = api::weekly_games(conn, length).await.into_inner();
+    Ok(Json(GameCounts {
+        dates,
+        games,
+        players,
+    }))
+}
+
+#[derive(Serialize)]
+pub struct CharacterGameCounts {
+    dates: Vec<String>,
+    characters: Vec<CharacterGames>,
+}
+
+#[derive(Serialize)]
+pub struct CharacterGames {
+    character_short: String,
+    games: Vec<i64>,
+}
+
+#[get("/daily_character_games?<length>")]
+pub async fn daily_character_games(
+    conn: RatingsDbConn,
+    length: Option<i64>,
+) -> ApiResult<CharacterGameCounts> {
+    require_length(length, MAX_DAYS)?;
+    let (dates, characters, games) = api::daily_character_games(conn, length).await.into_inner();
+    Ok(Json(CharacterGameCounts {
+        dates,
+        characters: characters
+            .into_iter()
+            .zip(games)
+            .map(|(character_short, games)| CharacterGames {
+                character_short,
+                games,
+            })
+            .collect(),
+    }))
+}
+
+#[derive(Serialize)]
+pub struct ActiveDay {
+    days_ago: i64,
+    players: i64,
+}
+
+#[get("/active_players")]
+pub async fn active_players(conn: RatingsDbConn) -> Json<Vec<ActiveDay>> {
+    Json(
+        api::active_players(conn)
+            .await
+            .into_inner()
+            .into_iter()
+            .zip(0..)
+            .map(|(players, days_ago)| ActiveDay { days_ago, players })
+            .collect(),
+    )
+}
+
+#[get("/top/all?<season>")]
+pub async fn top_all(
+    conn: RatingsDbConn,
+    season: Option<i64>,
+) -> ApiResult<Vec<api::RankingPlayer>> {
+    require_season(&conn, season).await?;
+    Ok(api::top_all(conn, season).await)
+}
+
+#[get("/top/<character>?<season>")]
+pub async fn top_char(
+    conn: RatingsDbConn,
+    character: &str,
+    season: Option<i64>,
+) -> ApiResult<Vec<api::RankingPlayer>> {
+    let char_id = parse_character(character)?;
+    require_season(&conn, season).await?;
+    Ok(api::top_char(conn, char_id as i64, season).await)
+}
+
+#[derive(Serialize)]
+pub struct CharacterRating {
+    character: &'static str,
+    character_short: &'static str,
+    value: f64,
+    deviation: f64,
+    game_count: i64,
+}
+
+fn get_character_ratings(
+    conn: &Connection,
+    id: i64,
+    char_id: Option<usize>,
+) -> Vec<CharacterRating> {
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT char_id, value, deviation, wins + losses
+                FROM player_ratings
+                WHERE id = ? AND (? IS NULL OR char_id = ?)
+                ORDER BY char_id",
+        )
+        .unwrap();
+    let mut rows = stmt.query(params![id, char_id, char_id]).unwrap();
+
+    let mut res = Vec::new();
+    while let Some(row) = rows.next().unwrap() {
+        let char_id: usize = row.get(0).unwrap();
+        res.push(CharacterRating {
+            character: website::CHAR_NAMES[char_id].1,
+            character_short: website::CHAR_NAMES[char_id].0,
+            value: row.get(1).unwrap(),
+            deviation: row.get(2).unwrap(),
+            game_count: row.get(3).unwrap(),
+        });
+    }
+    res
+}
+
+#[get("/players/<player>/ratings")]
+pub async fn player_ratings(conn: RatingsDbConn, player: &str) -> ApiResult<Vec<CharacterRating>> {
+    let id = require_player(&conn, player, false).await?;
+    Ok(Json(
+        conn.run(move |c| get_character_ratings(c, id, None)).await,
+    ))
+}
+
+#[get("/players/<player>/ratings/<character>")]
+pub async fn player_rating(
+    conn: RatingsDbConn,
+    player: &str,
+    character: &str,
+) -> ApiResult<CharacterRating> {
+    let char_id = parse_character(character)?;
+    let id = require_player(&conn, player, false).await?;
+    conn.run(move |c| get_character_ratings(c, id, Some(char_id)))
+        .await
+        .pop()
+        .map(Json)
+        .ok_or_else(|| {
+            ApiError::new(
+                Status::NotFound,
+                "no_rating",
+                format!("{} has no rating with {}", player, character),
+            )
+        })
+}
+
+fn parse_date(date: &str) -> std::result::Result<i64, ApiError> {
+    rater::date_to_timestamp(date).map_err(|_| {
+        ApiError::new(
+            Status::BadRequest,
+            "bad_date",
+            format!("{} is not a date like 2023-01-31", date),
+        )
+    })
+}
+
+#[derive(FromForm)]
+pub struct GamesQuery {
+    cursor: Option<String>,
+    limit: Option<usize>,
+    opponent_character: Option<String>,
+    opponent: Option<String>,
+    from: Option<String>,
+    to: Option<String>,
+    floor: Option<i64>,
+    result: Option<String>,
+    group_games: Option<bool>,
+}
+
+/// A player's games with a character, newest first. Pass `next_cursor` back as `cursor` to get
+/// the page after. `from` and `to` are inclusive dates.
+#[get("/players/<player>/games/<character>?<query..>")]
+pub async fn player_games(
+    conn: RatingsDbConn,
+    player: &str,
+    character: &str,
+    query: GamesQuery,
+) -> ApiResult<api::PlayerGames> {
+    let char_id = parse_character(character)?;
+    let id = require_player(&conn, player, false).await?;
+
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(|c| {
+            api::GameCursor::parse(c).ok_or_else(|| {
+                ApiError::new(
+                    Status::BadRequest,
+                    "bad_cursor",
+                    format!("{} is not a cursor from an earlier page", c),
+                )
+            })
+        })
+        .transpose()?;
+    let limit = query.limit.unwrap_or(DEFAULT_GAMES);
+    if !(1..=MAX_GAMES).contains(&limit) {
+        return Err(ApiError::new(
+            Status::BadRequest,
+            "bad_limit",
+            format!("limit has to be between 1 and {}", MAX_GAMES),
+        ));
+    }
+    let filter = api::GameFilter {
+        opponent_char: query
+            .opponent_character
+            .as_deref()
+            .map(parse_character)
+            .transpose()?
+            .map(|c| c as i64),
+        opponent_id: query.opponent.as_deref().map(parse_player).transpose()?,
+        from: query.from.as_deref().map(parse_date).transpose()?,
+        to: query
+            .to
+            .as_deref()
+            .map(parse_date)
+            .transpose()?
+            .map(|t| t + 24 * 60 * 60),
+        floor: query.floor,
+        won: match query.result.as_deref() {
+            None => None,
+            Some("win") => Some(true),
+            Some("loss") => Some(false),
+            Some(r) => {
+                return Err(ApiError::new(
+                    Status::BadRequest,
+                    "bad_result",
+                    format!("result has to be win or loss, not {}", r),
+                ))
+            }
+        },
+    };
+    let group_games = query.group_games.unwrap_or(true);
+
+    Ok(Json(
+        conn.run(move |c| {
+            api::get_player_char_games(c, id, char_id as i64, &filter, cursor, limit, group_games)
+        })
+        .await,
+    ))
+}
+
+/// `player_games` under the `/player/<id>/<char>/games` path it was first asked for.
+#[get("/player/<player>/<character>/games?<query..>")]
+pub async fn player_char_games(
+    conn: RatingsDbConn,
+    player: &str,
+    character: &str,
+    query: GamesQuery,
+) -> ApiResult<api::PlayerGames> {
+    player_games(conn, player, character, query).await
+}
+
+/// Every game the two players have played against each other, from `player_a`'s side.
+#[get("/h2h/<player_a>/<player_b>")]
+pub async fn head_to_head(
+    conn: RatingsDbConn,
+    player_a: &str,
+    player_b: &str,
+) -> ApiResult<api::HeadToHead> {
+    let id_a = require_player(&conn, player_a, false).await?;
+    let id_b = require_player(&conn, player_b, false).await?;
+    if id_a == id_b {
+        return Err(ApiError::new(
+            Status::BadRequest,
+            "same_player",
+            "A head to head needs two different players".to_owned(),
+        ));
+    }
+
+    conn.run(move |c| api::get_head_to_head(c, id_a, id_b))
+        .await
+        .map(Json)
+        .ok_or_else(|| ApiError::player_not_found(player_a))
+}
+
+/// Several players side by side, `ids` is a comma separated list of player ids.
+#[get("/compare?<ids>")]
+pub async fn compare(conn: RatingsDbConn, ids: &str) -> ApiResult<api::Comparison> {
+    let mut player_ids = Vec::new();
+    for player in ids.split(',') {
+        let id = require_player(&conn, player.trim(), false).await?;
+        if !player_ids.contains(&id) {
+            player_ids.push(id);
+        }
+    }
+    if !(2..=api::MAX_COMPARED_PLAYERS).contains(&player_ids.len()) {
+        return Err(ApiError::new(
+            Status::BadRequest,
+            "bad_ids",
+            format!(
+                "Between 2 and {} different players are needed",
+                api::MAX_COMPARED_PLAYERS
+            ),
+        ));
+    }
+
+    conn.run(move |c| api::get_comparison(c, &player_ids))
+        .await
+        .map(Json)
+        .ok_or_else(|| ApiError::player_not_found(ids))
+}
+
+const FIRST_TO: &[u32] = &[2, 3, 5, 7];
+
+#[derive(FromForm)]
+pub struct PredictQuery {
+    player_a: Option<String>,
+    character_a: Option<String>,
+    rating_a: Option<f64>,
+    deviation_a: Option<f64>,
+    player_b: Option<String>,
+    character_b: Option<String>,
+    rating_b: Option<f64>,
+    deviation_b: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct Prediction {
+    rating_a: Rating,
+    rating_b: Rating,
+    win_probability_a: f64,
+    sets: Vec<SetPrediction>,
+    a_wins: RatingChanges,
+    b_wins: RatingChanges,
+}
+
+impl Prediction {
+    fn new(rating_a: Rating, rating_b: Rating) -> Self {
+        let p = rating_a.expected(rating_b);
+        Self {
+            rating_a,
+            rating_b,
+            win_probability_a: p,
+            sets: FIRST_TO
+                .iter()
+                .map(|&first_to| SetPrediction {
+                    first_to,
+                    win_probability_a: glicko::first_to_probability(p, first_to),
+                })
+                .collect(),
+            a_wins: RatingChanges {
+                rating_change_a: rating_a.rating_change(rating_b, 1.0),
+                rating_change_b: rating_b.rating_change(rating_a, 0.0),
+            },
+            b_wins: RatingChanges {
+                rating_change_a: rating_a.rating_change(rating_b, 0.0),
+                rating_change_b: rating_b.rating_change(rating_a, 1.0),
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SetPrediction {
+    first_to: u32,
+    win_probability_a: f64,
+}
+
+#[derive(Serialize)]
+pub struct RatingChanges {
+    rating_change_a: f64,
+    rating_change_b: f64,
+}
+
+/// The rating of one side of a prediction, either a player's character or a raw rating.
+async fn prediction_rating(
+    conn: &RatingsDbConn,
+    side: &str,
+    player: Option<&str>,
+    character: Option<&str>,
+    rating: Option<f64>,
+    deviation: Option<f64>,
+) -> std::result::Result<Rating, ApiError> {
+    match (player, character, rating, deviation) {
+        (Some(player), Some(character), None, None) => {
+            let char_id = parse_character(character)?;
+            let id = require_player(conn, player, false).await?;
+            conn.run(move |c| {
+                c.query_row(
+                    "SELECT value, deviation FROM player_ratings WHERE id = ? AND char_id = ?",
+                    params![id, char_id],
+                    |r| Ok(Rating::new(r.get(0)?, r.get(1)?)),
+                )
+                .optional()
+                .unwrap()
+            })
+            .await
+            .ok_or_else(|| {
+                ApiError::new(
+                    Status::NotFound,
+                    "no_rating",
+                    format!("{} has no rating with {}", player, character),
+                )
+            })
+        }
+        (None, None, Some(value), Some(deviation)) => {
+            if !(0.0..=5000.0).contains(&value)
+                || !(glicko::MIN_DEVIATION..=glicko::INITIAL_DEVIATION).contains(&deviation)
+            {
+                return Err(ApiError::new(
+                    Status::BadRequest,
+                    "bad_rating",
+                    format!(
+                        "rating_{} has to be between 0 and 5000 and deviation_{} between {} and {}",
+                        side,
+                        side,
+                        glicko::MIN_DEVIATION,
+                        glicko::INITIAL_DEVIATION
+                    ),
+                ));
+            }
+            Ok(Rating::new(value, deviation))
+        }
+        _ => Err(ApiError::new(
+            Status::BadRequest,
+            "bad_prediction",
+            format!(
+                "Either player_{} and character_{} or rating_{} and deviation_{} are needed",
+                side, side, side, side
+            ),
+        )),
+    }
+}
+
+/// Game and set odds between two players' characters (or raw ratings), and how their ratings
+/// would change after a game either way. Deviations are the stored ones, which the site shows
+/// doubled.
+#[get("/predict?<query..>")]
+pub async fn predict(conn: RatingsDbConn, query: PredictQuery) -> ApiResult<Prediction> {
+    let rating_a = prediction_rating(
+        &conn,
+        "a",
+        query.player_a.as_deref(),
+        query.character_a.as_deref(),
+        query.rating_a,
+        query.deviation_a,
+    )
+    .await?;
+    let rating_b = prediction_rating(
+        &conn,
+        "b",
+        query.player_b.as_deref(),
+        query.character_b.as_deref(),
+        query.rating_b,
+        query.deviation_b,
+    )
+    .await?;
+
+    Ok(Json(Prediction::new(rating_a, rating_b)))
+}
+
+fn parse_entrants(
+    entrant: &[String],
+    min: usize,
+) -> std::result::Result<Vec<api::Entrant>, ApiError> {
+    let entrants: Vec<api::Entrant> = entrant
+        .iter()
+        .filter_map(|e| api::Entrant::parse(e))
+        .collect();
+    if entrants.len() < min || entrants.len() > api::MAX_ENTRANTS {
+        return Err(ApiError::new(
+            Status::BadRequest,
+            "bad_entrants",
+            format!(
+                "Between {} and {} entrants are needed",
+                min,
+                api::MAX_ENTRANTS
+            ),
+        ));
+    }
+    Ok(entrants)
+}
+
+/// Seeds a tournament from `entrant` parameters, each a player id or name optionally followed
+/// by a comma and a character.
+#[get("/seeding?<entrant>")]
+pub async fn seeding(
+    conn: RatingsDbConn,
+    entrant: Vec<String>,
+) -> ApiResult<Vec<api::SeededEntrant>> {
+    let entrants = parse_entrants(&entrant, 1)?;
+    Ok(Json(api::seed_entrants(&conn, entrants).await))
+}
+
+const MAX_FIRST_TO: u32 = 10;
+const MAX_RUNS: usize = 100000;
+/// Bigger brackets get fewer runs, so a request can't play more sets than this.
+const MAX_SIMULATED_SETS: usize = 2_000_000;
+
+#[derive(FromForm)]
+pub struct SimulateQuery {
+    entrant: Vec<String>,
+    format: Option<String>,
+    first_to: Option<u32>,
+    runs: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct Simulation {
+    format: &'static str,
+    first_to: u32,
+    runs: usize,
+    placements: Vec<usize>,
+    entrants: Vec<SimulatedEntrant>,
+}
+
+#[derive(Serialize)]
+pub struct SimulatedEntrant {
+    player: api::SeededEntrant,
+    reach: Vec<f64>,
+}
+
+/// Plays a bracket seeded in the order of the `entrant` parameters out `runs` times, `reach`
+/// is each entrant's chance of finishing at the matching placement or better.
+#[get("/simulate?<query..>")]
+pub async fn simulate(conn: RatingsDbConn, query: SimulateQuery) -> ApiResult<Simulation> {
+    let entrants = parse_entrants(&query.entrant, 2)?;
+    let format = match query.format.as_deref() {
+        None => bracket::Format::Double,
+        Some(format) => bracket::Format::parse(format).ok_or_else(|| {
+            ApiError::new(
+                Status::BadRequest,
+                "bad_format",
+                format!("{} is not single or double", format),
+            )
+        })?,
+    };
+    let first_to = query.first_to.unwrap_or(bracket::DEFAULT_FIRST_TO);
+    if !(1..=MAX_FIRST_TO).contains(&first_to) {
+        return Err(ApiError::new(
+            Status::BadRequest,
+            "bad_first_to",
+            format!("first_to has to be between 1 and {}", MAX_FIRST_TO),
+        ));
+    }
+    let max_runs = (MAX_SIMULATED_SETS / format.max_sets(entrants.len()).max(1)).min(MAX_RUNS);
+    let runs = query
+        .runs
+        .unwrap_or_else(|| bracket::DEFAULT_RUNS.min(max_runs));
+    if !(1..=max_runs).contains(&runs) {
+        return Err(ApiError::new(
+            Status::BadRequest,
+            "bad_runs",
+            format!(
+                "runs has to be between 1 and {} for {} entrants",
+                max_runs,
+                entrants.len()
+            ),
+        ));
+    }
+
+    let entrants = conn.run(move |c| api::look_up_entrants(c, entrants)).await;
+    let ratings: Vec<Rating> = entrants.iter().map(|e| e.rating()).collect();
+    let odds = rocket::tokio::task::spawn_blocking(move || {
+        bracket::simulate(&ratings, format, first_to, runs, &mut rand::thread_rng())
+    })
+    .await
+    .unwrap();
+
+    Ok(Json(Simulation {
+        format: format.name(),
+        first_to,
+        runs,
+        placements: odds.placements,
+        entrants: entrants
+            .into_iter()
+            .zip(odds.reach)
+            .map(|(player, reach)| SimulatedEntrant { player, reach })
+            .collect(),
+    }))
+}
+
+#[derive(Serialize)]
+pub struct AccuracyBucket {
+    expected: f64,
+    actual: Option<f64>,
+}
+
+/// How often the player actually won games, grouped by how likely they were to win them.
+#[get("/players/<player>/accuracy/<character>")]
+pub async fn player_accuracy(
+    conn: RatingsDbConn,
+    player: &str,
+    character: &str,
+) -> ApiResult<Vec<AccuracyBucket>> {
+    let char_id = parse_character(character)?;
+    let id = require_player(&conn, player, false).await?;
+    let buckets = conn
+        .run(move |c| api::rating_accuracy(c, id, char_id))
+        .await;
+    Ok(Json(
+        buckets
+            .into_iter()
+            .zip(0..)
+            .map(|(actual, i)| AccuracyBucket {
+                expected: i as f64 / 10.0,
+                actual: if actual.is_nan() { None } else { Some(actual) },
+            })
+            .collect(),
+    ))
+}
+
+const DEFAULT_MATCHMAKING_DAYS: i64 = 7;
+const MAX_MATCHMAKING_DAYS: i64 = 30;
+const DEFAULT_SUGGESTIONS: usize = 50;
+const MAX_SUGGESTIONS: usize = 200;
+
+#[derive(FromForm)]
+pub struct MatchmakingQuery {
+    min_probability: Option<f64>,
+    max_probability: Option<f64>,
+    days: Option<i64>,
+    limit: Option<usize>,
+}
+
+/// Players who were active in the last `days` and who the player's character has a
+/// `min_probability` to `max_probability` chance (40% to 60% by default) of beating in a game.
+#[get("/players/<player>/matchmaking/<character>?<query..>")]
+pub async fn matchmaking(
+    conn: RatingsDbConn,
+    player: &str,
+    character: &str,
+    query: MatchmakingQuery,
+) -> ApiResult<Vec<api::MatchSuggestion>> {
+    let char_id = parse_character(character)?;
+    let id = require_player(&conn, player, false).await?;
+
+    let min_probability = query.min_probability.unwrap_or(0.4);
+    let max_probability = query.max_probability.unwrap_or(0.6);
+    if !(0.0..=1.0).contains(&min_probability)
+        || !(0.0..=1.0).contains(&max_probability)
+        || min_probability > max_probability
+    {
+        return Err(ApiError::new(
+            Status::BadRequest,
+            "bad_probability",
+            "min_probability and max_probability have to be between 0 and 1, in that order"
+                .to_owned(),
+        ));
+    }
+    let days = query.days.unwrap_or(DEFAULT_MATCHMAKING_DAYS);
+    if !(1..=MAX_MATCHMAKING_DAYS).contains(&days) {
+        return Err(ApiError::new(
+            Status::BadRequest,
+            "bad_days",
+            format!("days has to be between 1 and {}", MAX_MATCHMAKING_DAYS),
+        ));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_SUGGESTIONS);
+    if !(1..=MAX_SUGGESTIONS).contains(&limit) {
+        return Err(ApiError::new(
+            Status::BadRequest,
+            "bad_limit",
+            format!("limit has to be between 1 and {}", MAX_SUGGESTIONS),
+        ));
+    }
+
+    let since = Utc::now().timestamp() - days * 60 * 60 * 24;
+    conn.run(move |c| {
+        api::get_match_suggestions(
+            c,
+            id,
+            char_id as i64,
+            min_probability,
+            max_probability,
+            since,
+            limit,
+        )
+    })
+    .await
+    .map(Json)
+    .ok_or_else(|| {
+        ApiError::new(
+            Status::NotFound,
+            "no_rating",
+            format!("{} has no rating with {}", player, character),
+        )
+    })
+}
+
+#[get("/players/<player>/rating_experience")]
+pub async fn player_rating_experience(
+    conn: RatingsDbConn,
+    player: &str,
+) -> ApiResult<api::RatingDiffStats> {
+    require_player(&conn, player, false).await?;
+    Ok(api::rating_experience_player(conn, player).await)
+}
+
+#[get("/rating_experience?<min_rating>&<max_rating>")]
+pub async fn rating_experience(
+    conn: RatingsDbConn,
+    min_rating: i64,
+    max_rating: i64,
+) -> ApiResult<api::RatingDiffStats> {
+    if min_rating >= max_rating {
+        return Err(ApiError::new(
+            Status::BadRequest,
+            "bad_rating_range",
+            "min_rating has to be below max_rating".to_owned(),
+        ));
+    }
+    Ok(api::rating_experience(conn, min_rating, max_rating).await)
+}
+
+#[get("/search?<name>&<exact>")]
+pub async fn search(
+    conn: RatingsDbConn,
+    name: String,
+    exact: Option<bool>,
+) -> ApiResult<Vec<api::SearchResultPlayer>> {
+    if name.is_empty() {
+        return Err(ApiError::new(
+            Status::BadRequest,
+            "empty_search",
+            "name can't be empty".to_owned(),
+        ));
+    }
+    Ok(Json(
+        api::search_inner(&conn, name, exact.unwrap_or(false), false).await,
+    ))
+}
+
+#[get("/autocomplete?<q>")]
+pub async fn autocomplete(conn: RatingsDbConn, q: String) -> Json<Vec<crate::search::Suggestion>> {
+    Json(
+        conn.run(move |c| crate::search::suggest_players(c, &q))
+            .await,
+    )
+}
+
+#[get("/player_lookup?<name>")]
+pub async fn player_lookup(
+    conn: RatingsDbConn,
+    name: String,
+) -> Json<Vec<api::PlayerLookupPlayer>> {
+    api::player_lookup(conn, name).await
+}
+
+#[get("/seasons")]
+pub async fn seasons(conn: RatingsDbConn) -> Json<Vec<api::SeasonInfo>> {
+    api::seasons(conn).await
+}
+
+#[get("/matchup_estimates")]
+pub async fn matchup_estimates(conn: RatingsDbConn) -> Json<Vec<api::CharacterMatchupEstimates>> {
+    api::matchup_estimates(conn).await
+}
+
+#[get("/tier_list")]
+pub async fn tier_list(conn: RatingsDbConn) -> Json<Vec<api::TierListBracket>> {
+    api::tier_list(conn).await
+}
+
+#[get("/floor_rating_distribution")]
+pub async fn floor_rating_distribution(conn: RatingsDbConn) -> Json<api::FloorRatingDistributions> {
+    api::floor_rating_distribution(conn).await
+}
+
+#[derive(Serialize)]
+pub struct Outcomes {
+    predicted_percent: Vec<i64>,
+    expected: Vec<f64>,
+    actual: Vec<f64>,
+}
+
+#[get("/outcomes")]
+pub async fn outcomes(conn: RatingsDbConn) -> Json<Outcomes> {
+    let (predicted_percent, actual, expected) = api::outcomes(conn).await.into_inner();
+    Json(Outcomes {
+        predicted_percent,
+        expected,
+        actual,
+    })
+}
+
+#[derive(Serialize)]
+pub struct OutcomesDelta {
+    rating_difference: Vec<i64>,
+    expected: Vec<f64>,
+    actual: Vec<f64>,
+}
+
+#[get("/outcomes_delta")]
+pub async fn outcomes_delta(conn: RatingsDbConn) -> Json<OutcomesDelta> {
+    let (rating_difference, expected, actual) = api::outcomes_delta(conn).await.into_inner();
+    Json(OutcomesDelta {
+        rating_difference,
+        expected,
+        actual,
+    })
+}
+
+#[derive(Serialize)]
+pub struct VerificationCode {
+    code: String,
+}
+
+#[post("/players/<player>/hide")]
+pub async fn start_hide(conn: RatingsDbConn, player: &str) -> ApiResult<VerificationCode> {
+    require_player(&conn, player, true).await?;
+    api::start_hide_player(conn, player)
+        .await
+        .map(|code| {
+            Json(VerificationCode {
+                code: code.into_inner(),
+            })
+        })
+        .map_err(ApiError::from_status)
+}
+
+#[derive(Serialize)]
+pub struct HidePoll {
+    confirmed: bool,
+}
+
+#[post("/players/<player>/hide/poll")]
+pub async fn poll_hide(conn: RatingsDbConn, player: &str) -> ApiResult<HidePoll> {
+    parse_player(player)?;
+    api::poll_hide_player(conn, player)
+        .await
+        .map(|confirmed| {
+            Json(HidePoll {
+                confirmed: confirmed.into_inner(),
+            })
+        })
+        .map_err(ApiError::from_status)
+}
+
+#[post("/players/<player>/export")]
+pub async fn start_export(conn: RatingsDbConn, player: &str) -> ApiResult<VerificationCode> {
+    require_player(&conn, player, true).await?;
+    api::start_player_export(conn, player)
+        .await
+        .map(|code| {
+            Json(VerificationCode {
+                code: code.into_inner(),
+            })
+        })
+        .map_err(ApiError::from_status)
+}
+
+#[post("/players/<player>/export/download")]
+pub async fn download_export(
+    conn: RatingsDbConn,
+    player: &str,
+) -> std::result::Result<api::PlayerExport, ApiError> {
+    parse_player(player)?;
+    api::download_player_export(conn, player)
+        .await
+        .map_err(|status| match status.code {
+            404 => ApiError::new(
+                status,
+                "no_export_request",
+                format!("There is no pending export for {}", player),
+            ),
+            403 => ApiError::new(
+                status,
+                "code_not_found",
+                "The export code isn't in the player's R-Code comment".to_owned(),
+            ),
+            _ => ApiError::from_status(status),
+        })
+}
+
+/// Everything mounted under `/api/v1`. Apart from the OpenAPI document itself, each of them has
+/// to be described in `ENDPOINTS`.
+pub fn routes() -> Vec<rocket::Route> {
+    routes![
+        stats,
+        daily_games,
+        weekly_games,
+        daily_character_games,
+        active_players,
+        top_all,
+        top_char,
+        player_ratings,
+        player_rating,
+        player_accuracy,
+        matchmaking,
+        player_games,
+        player_char_games,
+        head_to_head,
+        compare,
+        predict,
+        seeding,
+        simulate,
+        player_rating_experience,
+        rating_experience,
+        search,
+        autocomplete,
+        player_lookup,
+        seasons,
+        matchup_estimates,
+        tier_list,
+        floor_rating_distribution,
+        outcomes,
+        outcomes_delta,
+        start_hide,
+        poll_hide,
+        start_export,
+        download_export,
+        openapi,
+    ]
+}
+
+struct Endpoint {
+    method: &'static str,
+    path: &'static str,
+    summary: &'static str,
+    params: &'static [Param],
+    response: &'static str,
+}
+
+struct Param {
+    name: &'static str,
+    location: &'static str,
+    kind: &'static str,
+    required: bool,
+}
+
+const PLAYER: Param = Param {
+    name: "player",
+    location: "path",
+    kind: "string",
+    required: true,
+};
+const CHARACTER: Param = Param {
+    name: "character",
+    location: "path",
+    kind: "string",
+    required: true,
+};
+const SEASON: Param = Param {
+    name: "season",
+    location: "query",
+    kind: "integer",
+    required: false,
+};
+const LENGTH: Param = Param {
+    name: "length",
+    location: "query",
+    kind: "integer",
+    required: false,
+};
+const NAME: Param = Param {
+    name: "name",
+    location: "query",
+    kind: "string",
+    required: true,
+};
+const ENTRANT: Param = Param {
+    name: "entrant",
+    location: "query",
+    kind: "[]string",
+    required: true,
+};
+
+/// The parameters of a player's games, see `GamesQuery`.
+const GAMES_PARAMS: &[Param] = &[
+    PLAYER,
+    CHARACTER,
+    Param {
+        name: "cursor",
+        location: "query",
+        kind: "string",
+        required: false,
+    },
+    Param {
+        name: "limit",
+        location: "query",
+        kind: "integer",
+        required: false,
+    },
+    Param {
+        name: "opponent_character",
+        location: "query",
+        kind: "string",
+        required: false,
+    },
+    Param {
+        name: "opponent",
+        location: "query",
+        kind: "string",
+        required: false,
+    },
+    Param {
+        name: "from",
+        location: "query",
+        kind: "string",
+        required: false,
+    },
+    Param {
+        name: "to",
+        location: "query",
+        kind: "string",
+        required: false,
+    },
+    Param {
+        name: "floor",
+        location: "query",
+        kind: "integer",
+        required: false,
+    },
+    Param {
+        name: "result",
+        location: "query",
+        kind: "string",
+        required: false,
+    },
+    Param {
+        name: "group_games",
+        location: "query",
+        kind: "boolean",
+        required: false,
+    },
+];
+
+/// Every route mounted under `/api/v1`, the OpenAPI document is built from these.
+const ENDPOINTS: &[Endpoint] = &[
+    Endpoint {
+        method: "get",
+        path: "/stats",
+        summary: "Game and player counts",
+        params: &[],
+        response: "#Stats",
+    },
+    Endpoint {
+        method: "get",
+        path: "/daily_games",
+        summary: "Games and players per day",
+        params: &[LENGTH],
+        response: "#GameCounts",
+    },
+    Endpoint {
+        method: "get",
+        path: "/weekly_games",
+        summary: "Games and players per week",
+        params: &[LENGTH],
+        response: "#GameCounts",
+    },
+    Endpoint {
+        method: "get",
+        path: "/daily_character_games",
+        summary: "Games per character per day",
+        params: &[LENGTH],
+        response: "#CharacterGameCounts",
+    },
+    Endpoint {
+        method: "get",
+        path: "/active_players",
+        summary: "Active players for each of the last 14 days",
+        params: &[],
+        response: "[]#ActiveDay",
+    },
+    Endpoint {
+        method: "get",
+        path: "/top/all",
+        summary: "Top 100 players, for the current rankings or a season",
+        params: &[SEASON],
+        response: "[]#RankingPlayer",
+    },
+    Endpoint {
+        method: "get",
+        path: "/top/{character}",
+        summary: "Top 100 players of a character",
+        params: &[CHARACTER, SEASON],
+        response: "[]#RankingPlayer",
+    },
+    Endpoint {
+        method: "get",
+        path: "/players/{player}/ratings",
+        summary: "Ratings of every character a player has played",
+        params: &[PLAYER],
+        response: "[]#CharacterRating",
+    },
+    Endpoint {
+        method: "get",
+        path: "/players/{player}/ratings/{character}",
+        summary: "Rating of a player's character",
+        params: &[PLAYER, CHARACTER],
+        response: "#CharacterRating",
+    },
+    Endpoint {
+        method: "get",
+        path: "/players/{player}/accuracy/{character}",
+        summary: "Actual win rates of a player's character against their expected win rates",
+        params: &[PLAYER, CHARACTER],
+        response: "[]#AccuracyBucket",
+    },
+    Endpoint {
+        method: "get",
+        path: "/players/{player}/matchmaking/{character}",
+        summary: "Recently active players the player's character has an even-ish chance against",
+        params: &[
+            PLAYER,
+            CHARACTER,
+            Param {
+                name: "min_probability",
+                location: "query",
+                kind: "number",
+                required: false,
+            },
+            Param {
+                name: "max_probability",
+                location: "query",
+                kind: "number",
+                required: false,
+            },
+            Param {
+                name: "days",
+                location: "query",
+                kind: "integer",
+                required: false,
+            },
+            Param {
+                name: "limit",
+                location: "query",
+                kind: "integer",
+                required: false,
+            },
+        ],
+        response: "[]#MatchSuggestion",
+    },
+    Endpoint {
+        method: "get",
+        path: "/players/{player}/games/{character}",
+        summary: "A player's games with a character, newest first and grouped into sets by default",
+        params: GAMES_PARAMS,
+        response: "#PlayerGames",
+    },
+    Endpoint {
+        method: "get",
+        path: "/player/{player}/{character}/games",
+        summary: "The same as /players/{player}/games/{character}",
+        params: GAMES_PARAMS,
+        response: "#PlayerGames",
+    },
+    Endpoint {
+        method: "get",
+        path: "/h2h/{player_a}/{player_b}",
+        summary: "Record, sets and win probability between two players, from player_a's side",
+        params: &[
+            Param {
+                name: "player_a",
+                location: "path",
+                kind: "string",
+                required: true,
+            },
+            Param {
+                name: "player_b",
+                location: "path",
+                kind: "string",
+                required: true,
+            },
+        ],
+        response: "#HeadToHead",
+    },
+    Endpoint {
+        method: "get",
+        path: "/compare",
+        summary: "Several players' characters, rating histories and records against each other",
+        params: &[Param {
+            name: "ids",
+            location: "query",
+            kind: "string",
+            required: true,
+        }],
+        response: "#Comparison",
+    },
+    Endpoint {
+        method: "get",
+        path: "/predict",
+        summary: "Game and set odds and rating changes for two players' characters or raw ratings",
+        params: &[
+            Param {
+                name: "player_a",
+                location: "query",
+                kind: "string",
+                required: false,
+            },
+            Param {
+                name: "character_a",
+                location: "query",
+                kind: "string",
+                required: false,
+            },
+            Param {
+                name: "rating_a",
+                location: "query",
+                kind: "number",
+                required: false,
+            },
+            Param {
+                name: "deviation_a",
+                location: "query",
+                kind: "number",
+                required: false,
+            },
+            Param {
+                name: "player_b",
+                location: "query",
+                kind: "string",
+                required: false,
+            },
+            Param {
+                name: "character_b",
+                location: "query",
+                kind: "string",
+                required: false,
+            },
+            Param {
+                name: "rating_b",
+                location: "query",
+                kind: "number",
+                required: false,
+            },
+            Param {
+                name: "deviation_b",
+                location: "query",
+                kind: "number",
+                required: false,
+            },
+        ],
+        response: "#Prediction",
+    },
+    Endpoint {
+        method: "get",
+        path: "/players/{player}/rating_experience",
+        summary: "Rating differences of the games a player has played",
+        params: &[PLAYER],
+        response: "#RatingDiffStats",
+    },
+    Endpoint {
+        method: "get",
+        path: "/rating_experience",
+        summary: "Rating differences of games played within a rating range",
+        params: &[
+            Param {
+                name: "min_rating",
+                location: "query",
+                kind: "integer",
+                required: true,
+            },
+            Param {
+                name: "max_rating",
+                location: "query",
+                kind: "integer",
+                required: true,
+            },
+        ],
+        response: "#RatingDiffStats",
+    },
+    Endpoint {
+        method: "get",
+        path: "/search",
+        summary: "Players whose current or former name contains (or is) the search",
+        params: &[
+            NAME,
+            Param {
+                name: "exact",
+                location: "query",
+                kind: "boolean",
+                required: false,
+            },
+        ],
+        response: "[]#SearchResultPlayer",
+    },
+    Endpoint {
+        method: "get",
+        path: "/autocomplete",
+        summary: "Search box suggestions, active players with a name starting with q",
+        params: &[Param {
+            name: "q",
+            location: "query",
+            kind: "string",
+            required: true,
+        }],
+        response: "[]#Suggestion",
+    },
+    Endpoint {
+        method: "get",
+        path: "/player_lookup",
+        summary: "Players who have used a name, with their ratings",
+        params: &[NAME],
+        response: "[]#PlayerLookupPlayer",
+    },
+    Endpoint {
+        method: "get",
+        path: "/seasons",
+        summary: "All seasons",
+        params: &[],
+        response: "[]#SeasonInfo",
+    },
+    Endpoint {
+        method: "get",
+        path: "/matchup_estimates",
+        summary: "Matchup estimates of every character",
+        params: &[],
+        response: "[]#CharacterMatchupEstimates",
+    },
+    Endpoint {
+        method: "get",
+        path: "/tier_list",
+        summary: "Tier lists by rating bracket",
+        params: &[],
+        response: "[]#TierListBracket",
+    },
+    Endpoint {
+        method: "get",
+        path: "/floor_rating_distribution",
+        summary: "Rating distribution of each floor",
+        params: &[],
+        response: "#FloorRatingDistributions",
+    },
+    Endpoint {
+        method: "get",
+        path: "/outcomes",
+        summary: "Actual against predicted win rates",
+        params: &[],
+        response: "#Outcomes",
+    },
+    Endpoint {
+        method: "get",
+        path: "/outcomes_delta",
+        summary: "Actual against expected win rates by rating difference",
+        params: &[],
+        response: "#OutcomesDelta",
+    },
+    Endpoint {
+        method: "get",
+        path: "/seeding",
+        summary:
+            "Entrants ordered by rating minus two deviations, with anything worth a look flagged",
+        params: &[ENTRANT],
+        response: "[]#SeededEntrant",
+    },
+    Endpoint {
+        method: "get",
+        path: "/simulate",
+        summary:
+            "Each entrant's chance of finishing at each placement or better in a simulated bracket",
+        params: &[
+            ENTRANT,
+            Param {
+                name: "format",
+                location: "query",
+                kind: "string",
+                required: false,
+            },
+            Param {
+                name: "first_to",
+                location: "query",
+                kind: "integer",
+                required: false,
+            },
+            Param {
+                name: "runs",
+                location: "query",
+                kind: "integer",
+                required: false,
+            },
+        ],
+        response: "#Simulation",
+    },
+    Endpoint {
+        method: "post",
+        path: "/players/{player}/hide",
+        summary: "Starts hiding or unhiding a player, the code goes in their R-Code comment",
+        params: &[PLAYER],
+        response: "#VerificationCode",
+    },
+    Endpoint {
+        method: "post",
+        path: "/players/{player}/hide/poll",
+        summary: "Checks the player's R-Code comment for their hide code",
+        params: &[PLAYER],
+        response: "#HidePoll",
+    },
+    Endpoint {
+        method: "post",
+        path: "/players/{player}/export",
+        summary: "Starts a data export, the code goes in the player's R-Code comment",
+        params: &[PLAYER],
+        response: "#VerificationCode",
+    },
+    Endpoint {
+        method: "post",
+        path: "/players/{player}/export/download",
+        summary: "Everything stored about the player, once the export code is in their comment",
+        params: &[PLAYER],
+        response: "object",
+    },
+];
+
+/// Field types are `integer`, `number`, `string`, `boolean` or `object`, `?` makes them
+/// nullable, `[]` is an array, `{}` a map and `#` a reference to another schema.
+const SCHEMAS: &[(&str, &[(&str, &str)])] = &[
+    (
+        "ErrorBody",
+        &[
+            ("status", "integer"),
+            ("error", "string"),
+            ("message", "string"),
+        ],
+    ),
+    (
+        "Stats",
+        &[
+            ("game_count", "integer"),
+            ("player_count", "integer"),
+            ("activity_7d", "#Activity"),
+            ("activity_24h", "#Activity"),
+            ("activity_1h", "#Activity"),
+        ],
+    ),
+    (
+        "Activity",
+        &[
+            ("players", "integer"),
+            ("games", "integer"),
+            ("over_1700", "integer"),
+            ("over_1900", "integer"),
+            ("over_2100", "integer"),
+            ("sub_1300", "integer"),
+            ("sub_1100", "integer"),
+            ("sub_900", "integer"),
+        ],
+    ),
+    (
+        "GameCounts",
+        &[
+            ("dates", "[]string"),
+            ("games", "[]integer"),
+            ("players", "[]integer"),
+        ],
+    ),
+    (
+        "CharacterGameCounts",
+        &[("dates", "[]string"), ("characters", "[]#CharacterGames")],
+    ),
+    (
+        "CharacterGames",
+        &[("character_short", "string"), ("games", "[]integer")],
+    ),
+    (
+        "ActiveDay",
+        &[("days_ago", "integer"), ("players", "integer")],
+    ),
+    (
+        "RankingPlayer",
+        &[
+            ("pos", "integer"),
+            ("id", "string"),
+            ("platform", "string"),
+            ("character", "string"),
+            ("character_short", "string"),
+            ("name", "string"),
+            ("game_count", "integer"),
+            ("rating_value", "integer"),
+            ("rating_deviation", "integer"),
+            ("vip_status", "string?"),
+            ("vip_colour", "string?"),
+            ("cheater_status", "string?"),
+            ("hidden_status", "string?"),
+        ],
+    ),
+    (
+        "CharacterRating",
+        &[
+            ("character", "string"),
+            ("character_short", "string"),
+            ("value", "number"),
+            ("deviation", "number"),
+            ("game_count", "integer"),
+        ],
+    ),
+    (
+        "AccuracyBucket",
+        &[("expected", "number"), ("actual", "number?")],
+    ),
+    (
+        "PlayerGames",
+        &[("games", "[]#GameSet"), ("next_cursor", "string?")],
+    ),
+    (
+        "GameSet",
+        &[
+            ("timestamp", "integer"),
+            ("floor", "integer"),
+            ("own_rating_value", "number"),
+            ("own_rating_deviation", "number"),
+            ("opponent_id", "string?"),
+            ("opponent_name", "string"),
+            ("opponent_platform", "string"),
+            ("opponent_character", "string"),
+            ("opponent_character_short", "string"),
+            ("opponent_rating_value", "number"),
+            ("opponent_rating_deviation", "number"),
+            ("opponent_vip", "boolean"),
+            ("opponent_cheater", "boolean"),
+            ("opponent_hidden", "boolean"),
+            ("win_probability", "number"),
+            ("wins", "integer"),
+            ("losses", "integer"),
+            ("valid", "boolean"),
+            ("rating_changes", "[]number"),
+        ],
+    ),
+    (
+        "HeadToHead",
+        &[
+            ("player_a", "#HeadToHeadPlayer"),
+            ("player_b", "#HeadToHeadPlayer"),
+            ("wins_a", "integer"),
+            ("wins_b", "integer"),
+            ("set_count", "integer"),
+            ("rating_change_a", "number"),
+            ("rating_change_b", "number"),
+            ("win_probability_a", "number?"),
+            ("characters", "[]#CharacterPairRecord"),
+            ("sets", "[]#HeadToHeadSet"),
+        ],
+    ),
+    (
+        "HeadToHeadPlayer",
+        &[
+            ("id", "string"),
+            ("name", "string"),
+            ("platform", "string"),
+            ("vip_status", "string?"),
+            ("vip_colour", "string?"),
+            ("cheater_status", "string?"),
+        ],
+    ),
+    (
+        "CharacterPairRecord",
+        &[
+            ("character_a", "string"),
+            ("character_a_short", "string"),
+            ("character_b", "string"),
+            ("character_b_short", "string"),
+            ("wins_a", "integer"),
+            ("wins_b", "integer"),
+            ("rating_change_a", "number"),
+            ("rating_change_b", "number"),
+            ("win_probability_a", "number?"),
+        ],
+    ),
+    (
+        "HeadToHeadSet",
+        &[
+            ("timestamp", "integer"),
+            ("date", "string"),
+            ("floor", "string"),
+            ("character_a", "string"),
+            ("character_a_short", "string"),
+            ("character_b", "string"),
+            ("character_b_short", "string"),
+            ("rating_a_value", "integer"),
+            ("rating_a_deviation", "integer"),
+            ("rating_b_value", "integer"),
+            ("rating_b_deviation", "integer"),
+            ("win_probability_a", "number"),
+            ("wins_a", "integer"),
+            ("wins_b", "integer"),
+            ("rating_change_a", "number"),
+            ("rating_change_b", "number"),
+            ("valid", "boolean"),
+        ],
+    ),
+    (
+        "Prediction",
+        &[
+            ("rating_a", "#Rating"),
+            ("rating_b", "#Rating"),
+            ("win_probability_a", "number"),
+            ("sets", "[]#SetPrediction"),
+            ("a_wins", "#RatingChanges"),
+            ("b_wins", "#RatingChanges"),
+        ],
+    ),
+    ("Rating", &[("value", "number"), ("deviation", "number")]),
+    (
+        "SetPrediction",
+        &[("first_to", "integer"), ("win_probability_a", "number")],
+    ),
+    (
+        "RatingChanges",
+        &[("rating_change_a", "number"), ("rating_change_b", "number")],
+    ),
+    (
+        "RatingDiffStats",
+        &[
+            ("below_400", "number?"),
+            ("below_300", "number?"),
+            ("below_200", "number?"),
+            ("below_100", "number?"),
+            ("over_100", "number?"),
+            ("over_200", "number?"),
+            ("over_300", "number?"),
+            ("over_400", "number?"),
+            ("difference_amounts", "[]integer"),
+            ("difference_counts", "[]number?"),
+        ],
+    ),
+    (
+        "SearchResultPlayer",
+        &[
+            ("name", "string"),
+            ("former_name", "string?"),
+            ("platform", "string"),
+            ("vip_status", "string?"),
+            ("cheater_status", "string?"),
+            ("hidden_status", "string?"),
+            ("id", "string"),
+            ("character", "string"),
+            ("character_short", "string"),
+            ("rating_value", "integer"),
+            ("rating_deviation", "integer"),
+            ("game_count", "integer"),
+        ],
+    ),
+    (
+        "Suggestion",
+        &[
+            ("id", "string"),
+            ("name", "string"),
+            ("former_name", "string?"),
+            ("platform", "string"),
+            ("character", "string"),
+            ("character_short", "string"),
+            ("rating_value", "integer"),
+            ("rating_deviation", "integer"),
+            ("game_count", "integer"),
+        ],
+    ),
+    (
+        "PlayerLookupPlayer",
+        &[
+            ("id", "string"),
+            ("name", "string"),
+            ("characters", "[]#PlayerLookupCharacter"),
+        ],
+    ),
+    (
+        "PlayerLookupCharacter",
+        &[
+            ("shortname", "string"),
+            ("rating", "integer"),
+            ("deviation", "integer"),
+            ("game_count", "integer"),
+        ],
+    ),
+    (
+        "SeasonInfo",
+        &[
+            ("season_id", "integer"),
+            ("name", "string"),
+            ("start", "string"),
+            ("end", "string?"),
+            ("current", "boolean"),
+        ],
+    ),
+    (
+        "CharacterMatchupEstimates",
+        &[("name", "string"), ("matchups", "[]#MatchupEstimate")],
+    ),
+    (
+        "MatchupEstimate",
+        &[
+            ("matchup", "string"),
+            ("win_rate", "number"),
+            ("game_count", "integer"),
+            ("advantage", "number"),
+            ("std_error", "number"),
+            ("rating_delta", "string"),
+            ("expected", "number"),
+            ("lower", "number"),
+            ("upper", "number"),
+            ("interval", "string"),
+            ("suspicious", "boolean"),
+            ("evaluation", "string"),
+        ],
+    ),
+    (
+        "TierListBracket",
+        &[
+            ("rating_min", "integer"),
+            ("rating_max", "integer"),
+            ("tiers", "[]#Tier"),
+        ],
+    ),
+    (
+        "Tier",
+        &[("tier", "string"), ("characters", "[]#TierListCharacter")],
+    ),
+    (
+        "TierListCharacter",
+        &[
+            ("character", "string"),
+            ("character_short", "string"),
+            ("win_rate", "number"),
+            ("fraud_offset", "number?"),
+        ],
+    ),
+    (
+        "FloorRatingDistributions",
+        &[
+            ("ratings", "[]integer"),
+            ("floors", "{}[]number"),
+            ("overall", "[]number"),
+        ],
+    ),
+    (
+        "Outcomes",
+        &[
+            ("predicted_percent", "[]integer"),
+            ("expected", "[]number"),
+            ("actual", "[]number?"),
+        ],
+    ),
+    (
+        "OutcomesDelta",
+        &[
+            ("rating_difference", "[]integer"),
+            ("expected", "[]number"),
+            ("actual", "[]number?"),
+        ],
+    ),
+    (
+        "SeededEntrant",
+        &[
+            ("seed", "integer"),
+            ("entrant", "string"),
+            ("id", "string?"),
+            ("name", "string?"),
+            ("character", "string?"),
+            ("character_short", "string?"),
+            ("rating_value", "integer?"),
+            ("rating_deviation", "integer?"),
+            ("seeding_rating", "integer?"),
+            ("game_count", "integer?"),
+            ("flags", "[]string"),
+        ],
+    ),
+    (
+        "Simulation",
+        &[
+            ("format", "string"),
+            ("first_to", "integer"),
+            ("runs", "integer"),
+            ("placements", "[]integer"),
+            ("entrants", "[]#SimulatedEntrant"),
+        ],
+    ),
+    (
+        "SimulatedEntrant",
+        &[("player", "#SeededEntrant"), ("reach", "[]number")],
+    ),
+    (
+        "MatchSuggestion",
+        &[
+            ("id", "string"),
+            ("name", "string"),
+            ("platform", "string"),
+            ("character", "string"),
+            ("character_short", "string"),
+            ("rating_value", "integer"),
+            ("rating_deviation", "integer"),
+            ("win_probability", "number"),
+            ("last_seen_timestamp", "integer"),
+            ("last_seen", "string"),
+        ],
+    ),
+    (
+        "Comparison",
+        &[
+            ("players", "[]#ComparedPlayer"),
+            ("head_to_heads", "[]#ComparedHeadToHead"),
+        ],
+    ),
+    (
+        "ComparedPlayer",
+        &[
+            ("id", "string"),
+            ("name", "string"),
+            ("platform", "string"),
+            ("vip_status", "string?"),
+            ("vip_colour", "string?"),
+            ("cheater_status", "string?"),
+            ("characters", "[]#ComparedCharacter"),
+        ],
+    ),
+    (
+        "ComparedCharacter",
+        &[
+            ("character", "string"),
+            ("character_short", "string"),
+            ("rating_value", "integer"),
+            ("rating_deviation", "integer"),
+            ("game_count", "integer"),
+            ("win_rate", "number"),
+            ("top_rating_value", "integer?"),
+            ("top_rating_deviation", "integer?"),
+            ("top_rating_date", "string?"),
+            ("top_defeated", "#TopDefeated?"),
+            ("history", "[]#RatingPoint"),
+        ],
+    ),
+    (
+        "TopDefeated",
+        &[
+            ("id", "string?"),
+            ("name", "string"),
+            ("character", "string"),
+            ("character_short", "string"),
+            ("rating_value", "integer"),
+            ("rating_deviation", "integer"),
+            ("date", "string"),
+        ],
+    ),
+    (
+        "RatingPoint",
+        &[
+            ("timestamp", "integer"),
+            ("rating_value", "integer"),
+            ("rating_deviation", "integer"),
+        ],
+    ),
+    (
+        "ComparedHeadToHead",
+        &[
+            ("player_a", "string"),
+            ("name_a", "string"),
+            ("player_b", "string"),
+            ("name_b", "string"),
+            ("wins_a", "integer"),
+            ("wins_b", "integer"),
+            ("set_count", "integer"),
+            ("rating_change_a", "number"),
+            ("rating_change_b", "number"),
+            ("win_probability_a", "number?"),
+            ("characters", "[]#CharacterPairRecord"),
+        ],
+    ),
+    ("VerificationCode", &[("code", "string")]),
+    ("HidePoll", &[("confirmed", "boolean")]),
+];
+
+fn type_schema(kind: &str) -> Value {
+    if let Some(kind) = kind.strip_suffix('?') {
+        let mut schema = type_schema(kind);
+        schema["nullable"] = json!(true);
+        schema
+    } else if let Some(kind) = kind.strip_prefix("[]") {
+        json!({ "type": "array", "items": type_schema(kind) })
+    } else if let Some(kind) = kind.strip_prefix("{}") {
+        json!({ "type": "object", "additionalProperties": type_schema(kind) })
+    } else if let Some(name) = kind.strip_prefix('#') {
+        json!({ "$ref": format!("#/components/schemas/{}", name) })
+    } else {
+        json!({ "type": kind })
+    }
+}
+
+pub fn openapi_document() -> Value {
+    let mut paths = json!({});
+    for e in ENDPOINTS {
+        let parameters: Vec<Value> = e
+            .params
+            .iter()
+            .map(|p| {
+                json!({
+                    "name": p.name,
+                    "in": p.location,
+                    "required": p.required,
+                    "schema": type_schema(p.kind),
+                })
+            })
+            .collect();
+        paths[format!("/api/v1{}", e.path)][e.method] = json!({
+            "summary": e.summary,
+            "parameters": parameters,
+            "responses": {
+                "200": {
+                    "description": "OK",
+                    "content": { "application/json": { "schema": type_schema(e.response) } },
+                },
+                "default": {
+                    "description": "Error",
+                    "content": { "application/json": { "schema": type_schema("#ErrorBody") } },
+                },
+            },
+        });
+    }
+
+    let mut schemas = json!({});
+    for (name, fields) in SCHEMAS {
+        let mut properties = json!({});
+        for (field, kind) in fields.iter() {
+            properties[*field] = type_schema(kind);
+        }
+        schemas[*name] = json!({
+            "type": "object",
+            "properties": properties,
+            "required": fields.iter().map(|(f, _)| *f).collect::<Vec<_>>(),
+        });
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Rating Update API",
+            "version": "1",
+        },
+        "paths": paths,
+        "components": { "schemas": schemas },
+    })
+}
+
+#[get("/openapi.json")]
+pub fn openapi() -> Json<Value> {
+    Json(openapi_document())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoints_describe_the_mounted_routes() {
+        let mut mounted: Vec<(String, String)> = routes()
+            .iter()
+            .map(|route| {
+                let path = route
+                    .uri
+                    .path()
+                    .to_string()
+                    .replace('<', "{")
+                    .replace('>', "}");
+                (route.method.as_str().to_lowercase(), path)
+            })
+            .filter(|(_, path)| path != "/openapi.json")
+            .collect();
+        let mut described: Vec<(String, String)> = ENDPOINTS
+            .iter()
+            .map(|e| (e.method.to_owned(), e.path.to_owned()))
+            .collect();
+        mounted.sort();
+        described.sort();
+        assert_eq!(mounted, described);
+
+        for e in ENDPOINTS {
+            for segment in e.path.split('/').filter(|s| s.starts_with('{')) {
+                let name = segment.trim_matches(|c| c == '{' || c == '}');
+                assert!(
+                    e.params
+                        .iter()
+                        .any(|p| p.name == name && p.location == "path"),
+                    "{} doesn't describe {}",
+                    e.path,
+                    name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn openapi_document_lists_every_route() {
+        let document = openapi_document();
+        for route in routes() {
+            let path = route
+                .uri
+                .path()
+                .to_string()
+                .replace('<', "{")
+                .replace('>', "}");
+            if path == "/openapi.json" {
+                continue;
+            }
+            let method = route.method.as_str().to_lowercase();
+            assert!(
+                document["paths"][format!("/api/v1{}", path)][&method].is_object(),
+                "{} {} is missing from the OpenAPI document",
+                method,
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn referenced_schemas_exist() {
+        let kinds = ENDPOINTS.iter().map(|e| e.response).chain(
+            SCHEMAS
+                .iter()
+                .flat_map(|(_, fields)| fields.iter().map(|(_, kind)| *kind)),
+        );
+        for kind in kinds {
+            let kind = kind
+                .trim_end_matches('?')
+                .trim_start_matches(|c| "[]{}".contains(c));
+            if let Some(name) = kind.strip_prefix('#') {
+                assert!(
+                    SCHEMAS.iter().any(|(schema, _)| *schema == name),
+                    "Missing schema {}",
+                    name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn predictions_favour_the_stronger_side() {
+        let even = Prediction::new(Rating::new(1500.0, 100.0), Rating::new(1500.0, 100.0));
+        assert!((even.win_probability_a - 0.5).abs() < 1e-12);
+        assert_eq!(
+            even.sets.iter().map(|s| s.first_to).collect::<Vec<_>>(),
+            FIRST_TO
+        );
+        assert!(even
+            .sets
+            .iter()
+            .all(|s| (s.win_probability_a - 0.5).abs() < 1e-12));
+        assert!((even.a_wins.rating_change_a + even.b_wins.rating_change_a).abs() < 1e-9);
+        assert!((even.a_wins.rating_change_a - even.b_wins.rating_change_b).abs() < 1e-9);
+
+        let a = Rating::new(1700.0, 60.0);
+        let b = Rating::new(1500.0, 120.0);
+        let uneven = Prediction::new(a, b);
+        assert_eq!(uneven.win_probability_a, a.expected(b));
+        //Longer sets leave less room for upsets
+        assert!(uneven
+            .sets
+            .windows(2)
+            .all(|s| s[1].win_probability_a > s[0].win_probability_a));
+        assert!(uneven.sets[0].win_probability_a > uneven.win_probability_a);
+        //The favourite gains less for winning than they lose for losing
+        assert!(uneven.a_wins.rating_change_a > 0.0);
+        assert!(uneven.a_wins.rating_change_a < -uneven.b_wins.rating_change_a);
+        //The less certain rating moves further
+        assert!(uneven.b_wins.rating_change_b > uneven.b_wins.rating_change_a.abs());
+    }
+}
@@ -0,0 +1,323 @@
+//! Monte Carlo simulation of tournament brackets from current ratings.
+use crate::{
+    api,
+    glicko::{self, Rating},
+    rater,
+};
+use rand::Rng;
+use rusqlite::Connection;
+
+pub const DEFAULT_RUNS: usize = 10000;
+pub const DEFAULT_FIRST_TO: u32 = 2;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Format {
+    Single,
+    Double,
+}
+
+impl Format {
+    pub fn parse(format: &str) -> Option<Format> {
+        match format {
+            "single" => Some(Format::Single),
+            "double" => Some(Format::Double),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Single => "single",
+            Format::Double => "double",
+        }
+    }
+
+    /// The most sets a run of a bracket with this many entrants can take, counting a bracket
+    /// reset in grand finals.
+    pub fn max_sets(self, entrants: usize) -> usize {
+        match self {
+            Format::Single => entrants.saturating_sub(1),
+            Format::Double => (2 * entrants).saturating_sub(1),
+        }
+    }
+}
+
+pub struct Odds {
+    /// Every placement someone finished at, best first. Players knocked out in the same round
+    /// share a placement, so these skip numbers the way tournament results do.
+    pub placements: Vec<usize>,
+    /// For each entrant in seed order, how often they finished at `placements[i]` or better.
+    pub reach: Vec<Vec<f64>>,
+}
+
+/// Bracket positions for seeds `0..size`, arranged so the top seeds only meet in the last
+/// rounds. `size` has to be a power of two.
+fn seed_order(size: usize) -> Vec<usize> {
+    let mut order = vec![0];
+    while order.len() < size {
+        let n = order.len() * 2;
+        order = order.iter().flat_map(|&s| [s, n - 1 - s]).collect();
+    }
+    order
+}
+
+type Slots = Vec<Option<usize>>;
+
+struct Run<'a, R: Rng> {
+    /// The chance of the row entrant winning a set against the column entrant.
+    set_odds: &'a [Vec<f64>],
+    rng: &'a mut R,
+    alive: usize,
+    placed: Vec<usize>,
+}
+
+impl<'a, R: Rng> Run<'a, R> {
+    /// Plays a set and returns the winner and loser. Empty bracket slots lose to everyone.
+    fn play(&mut self, a: Option<usize>, b: Option<usize>) -> (Option<usize>, Option<usize>) {
+        match (a, b) {
+            (Some(x), Some(y)) => {
+                if self.rng.gen::<f64>() < self.set_odds[x][y] {
+                    (a, b)
+                } else {
+                    (b, a)
+                }
+            }
+            (None, _) => (b, a),
+            _ => (a, b),
+        }
+    }
+
+    /// Plays neighbouring slots against each other and returns the winners and losers.
+    fn play_round(&mut self, slots: &[Option<usize>]) -> (Slots, Slots) {
+        slots
+            .chunks(2)
+            .map(|pair| self.play(pair[0], pair[1]))
+            .unzip()
+    }
+
+    fn eliminate(&mut self, losers: &[Option<usize>]) {
+        self.alive -= losers.len();
+        for &loser in losers.iter().flatten() {
+            self.placed[loser] = self.alive + 1;
+        }
+    }
+
+    fn single_elimination(&mut self, mut slots: Slots) {
+        while slots.len() > 1 {
+            let (winners, losers) = self.play_round(&slots);
+            self.eliminate(&losers);
+            slots = winners;
+        }
+        self.placed[slots[0].unwrap()] = 1;
+    }
+
+    /// Losers of each winners round drop into the losers bracket, where they play whoever made
+    /// it through the round before. Between those drop rounds the losers bracket halves itself.
+    fn double_elimination(&mut self, mut winners: Slots) {
+        let mut losers = Vec::new();
+        let mut round = 0;
+        while winners.len() > 1 {
+            let (next, dropped) = self.play_round(&winners);
+            winners = next;
+
+            if round == 0 {
+                losers = dropped;
+            } else {
+                //Every other round the drops come in reversed so they don't meet the same
+                //players again straight away
+                let dropped = if round % 2 == 0 {
+                    dropped.into_iter().rev().collect()
+                } else {
+                    dropped
+                };
+                let slots: Slots = losers
+                    .iter()
+                    .zip(dropped.iter())
+                    .flat_map(|(&l, &d)| [l, d])
+                    .collect();
+                let (next, out) = self.play_round(&slots);
+                self.eliminate(&out);
+                losers = next;
+            }
+
+            if losers.len() > 1 && (round == 0 || winners.len() > 1) {
+                let (next, out) = self.play_round(&losers);
+                self.eliminate(&out);
+                losers = next;
+            }
+            round += 1;
+        }
+
+        //The losers bracket winner has to take two sets off the winners bracket winner
+        let (winner, loser) = match self.play(winners[0], losers[0]) {
+            (winner, _) if winner == winners[0] => (winners[0], losers[0]),
+            _ => self.play(winners[0], losers[0]),
+        };
+        self.eliminate(&[loser]);
+        self.placed[winner.unwrap()] = 1;
+    }
+}
+
+/// Plays out the bracket `runs` times. `ratings` are in seed order, and brackets that aren't
+/// a power of two get byes for the top seeds.
+pub fn simulate(
+    ratings: &[Rating],
+    format: Format,
+    first_to: u32,
+    runs: usize,
+    rng: &mut impl Rng,
+) -> Odds {
+    let size = ratings.len().next_power_of_two();
+    let slots: Slots = seed_order(size)
+        .into_iter()
+        .map(|s| if s < ratings.len() { Some(s) } else { None })
+        .collect();
+    let set_odds: Vec<Vec<f64>> = ratings
+        .iter()
+        .map(|&a| {
+            ratings
+                .iter()
+                .map(|&b| glicko::first_to_probability(a.expected(b), first_to))
+                .collect()
+        })
+        .collect();
+
+    let mut counts = vec![vec![0usize; size + 1]; ratings.len()];
+    for _ in 0..runs {
+        let mut run = Run {
+            set_odds: &set_odds,
+            rng: &mut *rng,
+            alive: size,
+            placed: vec![0; ratings.len()],
+        };
+        match format {
+            Format::Single => run.single_elimination(slots.clone()),
+            Format::Double => run.double_elimination(slots.clone()),
+        }
+        for (entrant, &placement) in run.placed.iter().enumerate() {
+            counts[entrant][placement] += 1;
+        }
+    }
+
+    let placements: Vec<usize> = (1..=size)
+        .filter(|&p| counts.iter().any(|c| c[p] > 0))
+        .collect();
+    let reach = counts
+        .iter()
+        .map(|c| {
+            placements
+                .iter()
+                .map(|&p| c[..=p].iter().sum::<usize>() as f64 / runs as f64)
+                .collect()
+        })
+        .collect();
+    Odds { placements, reach }
+}
+
+fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+/// Prints how likely each entrant in the file at `path` is to finish at each placement or
+/// better. The file has one entrant per line in seed order, written like on the seeding page.
+pub fn print_simulation(
+    path: &str,
+    format: Option<&str>,
+    first_to: Option<&str>,
+    runs: Option<&str>,
+) {
+    let conn = Connection::open(rater::DB_NAME).unwrap();
+
+    let format = format.map_or(Format::Double, |f| Format::parse(f).unwrap());
+    let first_to = first_to.map_or(DEFAULT_FIRST_TO, |f| f.parse().unwrap());
+    let runs = runs.map_or(DEFAULT_RUNS, |r| r.parse().unwrap());
+
+    let entrants = std::fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .filter_map(api::Entrant::parse)
+        .collect();
+    let entrants = api::look_up_entrants(&conn, entrants);
+    let ratings: Vec<Rating> = entrants.iter().map(|e| e.rating()).collect();
+    let odds = simulate(&ratings, format, first_to, runs, &mut rand::thread_rng());
+
+    println!(
+        "| Seed | Name | Character | {} | Notes |",
+        odds.placements
+            .iter()
+            .map(|&p| ordinal(p))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    );
+    println!(
+        "|------|------|-----------|{}-------|",
+        "------|".repeat(odds.placements.len())
+    );
+    for (entrant, reach) in entrants.iter().zip(odds.reach.iter()) {
+        println!(
+            "| {} | {} | {} | {} | {} |",
+            entrant.seed,
+            entrant.name.as_deref().unwrap_or(&entrant.entrant),
+            entrant.character.unwrap_or("-"),
+            reach
+                .iter()
+                .map(|r| format!("{:.1}%", 100.0 * r))
+                .collect::<Vec<_>>()
+                .join(" | "),
+            entrant.flags.join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn seeds_meet_late() {
+        assert_eq!(seed_order(8), vec![0, 7, 3, 4, 1, 6, 2, 5]);
+    }
+
+    #[test]
+    fn placements() {
+        let ratings = vec![Rating::default(); 8];
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let single = simulate(&ratings, Format::Single, 2, 1000, &mut rng);
+        assert_eq!(single.placements, vec![1, 2, 3, 5]);
+        let double = simulate(&ratings, Format::Double, 2, 1000, &mut rng);
+        assert_eq!(double.placements, vec![1, 2, 3, 4, 5, 7]);
+
+        for odds in [single, double] {
+            let wins: f64 = odds.reach.iter().map(|r| r[0]).sum();
+            assert!((wins - 1.0).abs() < 1e-9);
+            assert!(odds.reach.iter().all(|r| r[r.len() - 1] == 1.0));
+        }
+    }
+
+    #[test]
+    fn max_sets() {
+        assert_eq!(Format::Single.max_sets(8), 7);
+        assert_eq!(Format::Double.max_sets(8), 15);
+        assert_eq!(Format::Double.max_sets(0), 0);
+    }
+
+    #[test]
+    fn byes_go_to_top_seeds() {
+        let ratings = vec![Rating::new(1500.0, 100.0); 6];
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let odds = simulate(&ratings, Format::Single, 2, 1000, &mut rng);
+        //Seeds 1 and 2 skip the first round so they can't go out before the semi finals
+        assert_eq!(odds.reach[0][odds.placements.len() - 2], 1.0);
+        assert_eq!(odds.reach[1][odds.placements.len() - 2], 1.0);
+    }
+}
@@ -4,9 +4,9 @@ use aes_gcm::{
     Aes256Gcm, KeyInit,
 };
 //use getrandom::getrandom;
-use hex;
 use lazy_static::lazy_static;
 use reqwest::header;
+use rocket::serde::json::from_str;
 use serde::{Deserialize, Serialize};
 use std::{error::Error, ops::Deref};
 use tokio::sync::Mutex;
@@ -34,10 +34,28 @@ pub async fn get_player_stats(player_id: String) -> Result<String, String> {
     if let Ok(r) = decrypt_response::<responses::PlayerStats>(&response_bytes) {
         Ok(r.body.json)
     } else {
-        return Err("Couldn't get player stats".to_owned());
+        Err("Couldn't get player stats".to_owned())
     }
 }
 
+/// The part of the R-Code stats that's needed, the comment is a field of the top level object.
+#[derive(Deserialize)]
+struct RCodeStats {
+    #[serde(rename = "PublicComment")]
+    public_comment: Option<String>,
+}
+
+/// The player's R-Code comment, None if they don't have one.
+pub async fn get_public_comment(player_id: String) -> Result<Option<String>, String> {
+    let json = get_player_stats(player_id).await?;
+    parse_public_comment(&json)
+}
+
+fn parse_public_comment(json: &str) -> Result<Option<String>, String> {
+    let stats: RCodeStats = from_str(json).map_err(|e| format!("Bad player stats: {}", e))?;
+    Ok(stats.public_comment)
+}
+
 pub async fn get_token() -> Result<String, String> {
     {
         let token = TOKEN.lock().await;
@@ -71,7 +89,7 @@ pub async fn get_token() -> Result<String, String> {
         *t = Some(r.header.token.to_owned());
         Ok(r.header.token)
     } else {
-        return Err("Couldn't get strive token".to_owned());
+        Err("Couldn't get strive token".to_owned())
     }
 }
 
@@ -124,9 +142,7 @@ fn encrypt_data<T: Serialize>(data: &T) -> String {
     data.extend_from_slice(&nonce);
     data.extend_from_slice(&encrypted);
 
-    let r = base64_url::encode(&data);
-
-    r
+    base64_url::encode(&data)
 }
 
 fn decrypt_response<T: for<'a> Deserialize<'a>>(
@@ -137,9 +153,7 @@ fn decrypt_response<T: for<'a> Deserialize<'a>>(
     let aes_gcm = Aes256Gcm::new_from_slice(&key).unwrap();
 
     let mut nonce = [0; 12];
-    for i in 0..12 {
-        nonce[i] = bytes[i];
-    }
+    nonce.copy_from_slice(&bytes[..12]);
 
     //let nonce: GenericArray<_, _> = todo!();// GenericArray::from(&response_bytes[..12]);
     let nonce = GenericArray::from(nonce);
@@ -164,3 +178,22 @@ fn decrypt_response<T: for<'a> Deserialize<'a>>(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_comment_is_read_from_the_top_level() {
+        assert_eq!(
+            parse_public_comment(r#"{"NowRank":1,"PublicComment":"abc"}"#),
+            Ok(Some("abc".to_owned()))
+        );
+        //Anything else that happens to be called PublicComment doesn't count
+        assert_eq!(
+            parse_public_comment(r#"{"Friends":[{"PublicComment":"abc"}]}"#),
+            Ok(None)
+        );
+        assert!(parse_public_comment("[]").is_err());
+    }
+}
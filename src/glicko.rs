@@ -61,21 +61,17 @@ impl Rating {
                 .max(min_deviation),
         };
 
-        if result == 0.0 {
-            if res.value >= self.value {
-                panic!(
-                    "{:#?} lost against {:#?} but rating went up to: {:#?}",
-                    self, other, res
-                );
-            }
+        if result == 0.0 && res.value >= self.value {
+            panic!(
+                "{:#?} lost against {:#?} but rating went up to: {:#?}",
+                self, other, res
+            );
         }
-        if result == 1.0 {
-            if res.value <= self.value {
-                panic!(
-                    "{:#?} won against {:#?} but rating went down to: {:#?}",
-                    self, other, res
-                );
-            }
+        if result == 1.0 && res.value <= self.value {
+            panic!(
+                "{:#?} won against {:#?} but rating went down to: {:#?}",
+                self, other, res
+            );
         }
 
         res
@@ -109,6 +105,57 @@ pub fn e(r: f64, r_j: f64, rd_j: f64) -> f64 {
     1.0 / (1.0 + 10.0f64.powf((1.0 - UNCERTAINTY) * -g(rd_j) * (r - r_j) / 400.0))
 }
 
+/// Win probability against an equally rated opponent for a given rating advantage.
+pub fn advantage_to_probability(advantage: f64) -> f64 {
+    1.0 / (1.0 + 10.0f64.powf(-advantage / 400.0))
+}
+
+/// Maximum likelihood fit of a constant rating advantage, assuming each game follows the
+/// expected outcome shifted by that advantage. Samples are (effective rating difference,
+/// wins, games). Returns the advantage and its standard error.
+pub fn fit_advantage(samples: &[(f64, f64, f64)]) -> (f64, f64) {
+    let k = std::f64::consts::LN_10 / 400.0;
+    let mut advantage = 0.0;
+    let mut information = 0.0;
+
+    for _ in 0..50 {
+        let mut residual = 0.0;
+        information = 0.0;
+        for &(difference, wins, games) in samples {
+            let p = advantage_to_probability(difference + advantage);
+            residual += wins - games * p;
+            information += games * p * (1.0 - p);
+        }
+
+        if information <= 0.0 {
+            break;
+        }
+
+        let step = residual / (k * information);
+        advantage = (advantage + step).clamp(-800.0, 800.0);
+        if step.abs() < 1e-6 {
+            break;
+        }
+    }
+
+    (advantage, 1.0 / (k * information.sqrt()))
+}
+
+/// Chance of winning a first to `wins` set when each game is won with probability `p`.
+pub fn first_to_probability(p: f64, wins: u32) -> f64 {
+    //Sum over how many games are lost before the last win, each ordering of the earlier games
+    //counted by the binomial coefficient
+    let mut total = 0.0;
+    let mut orderings = 1.0;
+    for losses in 0..wins {
+        if losses > 0 {
+            orderings *= (wins - 1 + losses) as f64 / losses as f64;
+        }
+        total += orderings * p.powi(wins as i32) * (1.0 - p).powi(losses as i32);
+    }
+    total
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -131,4 +178,34 @@ mod test {
 
         assert_eq!(a, b);
     }
+
+    #[test]
+    fn fit_advantage_recovers_offset() {
+        let samples: Vec<(f64, f64, f64)> = (-4..=4)
+            .map(|i| {
+                let difference = i as f64 * 50.0;
+                (
+                    difference,
+                    1000.0 * advantage_to_probability(difference + 100.0),
+                    1000.0,
+                )
+            })
+            .collect();
+
+        let (advantage, std_error) = fit_advantage(&samples);
+        assert!((advantage - 100.0).abs() < 1e-3);
+        assert!(std_error > 0.0 && std_error < 10.0);
+    }
+
+    #[test]
+    fn first_to_probability_is_symmetric() {
+        assert!((first_to_probability(0.6, 1) - 0.6).abs() < 1e-12);
+        assert!((first_to_probability(0.6, 2) - 0.648).abs() < 1e-12);
+        for wins in [2, 3, 5, 7] {
+            assert!((first_to_probability(0.5, wins) - 0.5).abs() < 1e-12);
+            let p = first_to_probability(0.7, wins);
+            assert!((p + first_to_probability(0.3, wins) - 1.0).abs() < 1e-12);
+            assert!(p > 0.7);
+        }
+    }
 }
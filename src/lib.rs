@@ -4,9 +4,12 @@ extern crate rocket;
 extern crate log;
 
 mod api;
+mod api_v1;
+pub mod bracket;
 mod ggst_api;
 mod glicko;
 pub mod rater;
 mod requests;
 mod responses;
+pub mod search;
 pub mod website;
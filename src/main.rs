@@ -5,7 +5,7 @@ use std::{fs::File, ops::Deref};
 use tokio::try_join;
 use dotenv::dotenv;
 
-use rating_update::{rater, website};
+use rating_update::{bracket, rater, search, website};
 
 fn init_logging() {
     if cfg!(debug_assertions) {
@@ -37,7 +37,7 @@ async fn main() {
     init_logging();
 
     let args = std::env::args().skip(1).collect::<Vec<_>>();
-    match args.get(0).map(|r| r.deref()) {
+    match args.first().map(|r| r.deref()) {
         Some("init") => {
             rater::init_database().unwrap();
         }
@@ -53,23 +53,93 @@ async fn main() {
         Some("fraud") => {
             rater::update_fraud_once().await;
         }
+        Some("matchup_estimates") => {
+            rater::update_matchup_estimates_once().await;
+        }
         Some("mark_cheater") => {
             rater::mark_cheater(
                 args.get(1).map(|r| r.deref()),
                 args.get(2).map(|r| r.deref()),
                 args.get(3).map(|r| r.deref()),
+                args.get(4).map(|r| r.deref()),
             )
             .await;
         }
+        Some("detect_suspicious") => {
+            rater::detect_suspicious_once().await;
+        }
+        Some("suspicious") => {
+            rater::print_suspicious();
+        }
+        Some("dismiss_suspicious") => {
+            rater::dismiss_suspicious(args.get(1).unwrap(), args.get(2).map(|r| r.deref()));
+        }
+        Some("detect_trading") => {
+            rater::detect_trading_once().await;
+        }
+        Some("trading_pairs") => {
+            rater::print_trading_pairs();
+        }
+        Some("trading_pair") => {
+            rater::set_trading_pair_status(
+                args.get(1).unwrap(),
+                args.get(2).unwrap(),
+                args.get(3).unwrap(),
+            );
+        }
+        Some("cheater_offsets") => {
+            rater::print_cheater_offsets(args.get(1).unwrap());
+        }
+        Some("unmark_cheater") => {
+            rater::unmark_cheater(
+                args.get(1).unwrap(),
+                args.get(2).map(|r| r.deref()),
+                args.get(3).map(|r| r.deref()),
+            );
+        }
         Some("mark_vip") => {
-            rater::mark_vip(args.get(1).unwrap(), args.get(2).unwrap());
+            rater::mark_vip(
+                args.get(1).unwrap(),
+                args.get(2).unwrap(),
+                args.get(3).map(|r| r.deref()),
+                args.get(4).map(|r| r.deref()),
+                args.get(5).map(|r| r.deref()),
+                args.get(6).map(|r| r.deref()),
+            )
+            .unwrap();
+        }
+        Some("export_player") => {
+            rater::export_player_once(args.get(1).unwrap(), args.get(2).map(|r| r.deref()))
+                .unwrap();
         }
         Some("mark_hidden") => {
             rater::mark_hidden(args.get(1).unwrap(), args.get(2).unwrap());
         }
+        Some("add_season") => {
+            rater::add_season(
+                args.get(1).unwrap(),
+                args.get(2).unwrap(),
+                args.get(3).map(|r| r.deref()),
+            )
+            .unwrap();
+        }
+        Some("rebuild_season") => {
+            rater::rebuild_season_once(args.get(1).unwrap()).unwrap();
+        }
         Some("print_rankings") => {
             rater::print_rankings();
         }
+        Some("simulate") => {
+            bracket::print_simulation(
+                args.get(1).unwrap(),
+                args.get(2).map(|r| r.deref()),
+                args.get(3).map(|r| r.deref()),
+                args.get(4).map(|r| r.deref()),
+            );
+        }
+        Some("search_index") => {
+            search::rebuild_index().unwrap();
+        }
         Some("decay") => {
             rater::update_decay_once().await;
         }
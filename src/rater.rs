@@ -1,15 +1,37 @@
-use crate::{ggst_api, glicko, glicko::Rating, responses, website};
-use anyhow::Context;
-use chrono::{NaiveDateTime, Utc};
+use crate::{api, ggst_api, glicko, glicko::Rating, responses, search, website};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use fxhash::{FxHashMap, FxHashSet};
 use lazy_static::lazy_static;
 use rusqlite::{
     functions::FunctionFlags, named_params, params, Connection, OptionalExtension, Row, Transaction,
 };
-use std::{sync::Mutex, time::Duration};
-use tokio::{time, try_join};
+use std::{hash::Hash, sync::Mutex, time::Duration};
+use tokio::time;
 
 const DECAY_CONSTANT: f64 = 3.1;
+const SOFT_RESET_DEVIATION: f64 = 200.0;
+const MATCHUP_ESTIMATE_MAX_DEVIATION: f64 = 150.0;
+/// Fewer games than this in a rating bracket and the tier list uses the matchup's overall
+/// estimate there instead.
+const BRACKET_ESTIMATE_MIN_GAMES: i64 = 100;
+
+const SUSPICIOUS_WINDOW: i64 = 60 * 60 * 24 * 7;
+const SUSPICIOUS_UPSET_EXPECTED: f64 = 0.15;
+const SUSPICIOUS_UPSET_WINS: i64 = 10;
+const SUSPICIOUS_FRESH_GAMES: i64 = 100;
+const SUSPICIOUS_FRESH_GAIN: f64 = 600.0;
+const SUSPICIOUS_PAIR_GAMES: i64 = 50;
+const SUSPICIOUS_PAIR_WIN_RATE: f64 = 0.9;
+const SUSPICIOUS_NAMES: i64 = 4;
+
+const TRADING_WINDOW: i64 = 60 * 60 * 24 * 30;
+const TRADING_MIN_GAMES: i64 = 30;
+const TRADING_MIN_RATIO: f64 = 100.0;
+const TRADING_WIN_RATE: f64 = 0.8;
+
+/// How far a re-rated rating can drift from the stored one before the opponents it was used
+/// against are re-rated too.
+const RERATE_THRESHOLD: f64 = 0.5;
 
 pub const LOW_DEVIATION: f64 = 75.0;
 pub const HIGH_RATING: f64 = 1800.0;
@@ -19,7 +41,7 @@ const CHAR_COUNT: usize = website::CHAR_NAMES.len();
 pub const POP_RATING_BRACKETS: usize = 13;
 
 pub const RATING_PERIOD: i64 = 60 * 60;
-pub const RANKING_PERIOD: i64 = 1 * 60 * 60;
+pub const RANKING_PERIOD: i64 = 60 * 60;
 pub const STATISTICS_PERIOD: i64 = 6 * 60 * 60;
 
 lazy_static! {
@@ -66,8 +88,22 @@ pub fn reset_names() -> Result<()> {
     };
 
     for g in games {
-        update_player(&tx, g.id_a, &g.name_a, g.game_floor, g.platform_a);
-        update_player(&tx, g.id_b, &g.name_b, g.game_floor, g.platform_b);
+        update_player(
+            &tx,
+            g.id_a,
+            &g.name_a,
+            g.game_floor,
+            g.platform_a,
+            g.timestamp,
+        );
+        update_player(
+            &tx,
+            g.id_b,
+            &g.name_b,
+            g.game_floor,
+            g.platform_b,
+            g.timestamp,
+        );
     }
 
     tx.commit()?;
@@ -137,8 +173,23 @@ pub async fn update_statistics(
         if let Err(e) = calc_character_popularity(conn, *last_ranking_update) {
             error!("calc_character_popularity failed: {}", e);
         }
+        if let Err(e) = calc_matchup_estimates(conn, *last_ranking_update) {
+            error!("calc_matchup_estimates failed: {}", e);
+        }
+        if let Err(e) = calc_tier_list(conn) {
+            error!("calc_tier_list failed: {}", e);
+        }
+        if let Err(e) = detect_suspicious_accounts(conn, *last_ranking_update) {
+            error!("detect_suspicious_accounts failed: {}", e);
+        }
+        if let Err(e) = detect_trading_pairs(conn, *last_ranking_update) {
+            error!("detect_trading_pairs failed: {}", e);
+        }
     }
 
+    if let Err(e) = rerate_queued(conn) {
+        error!("rerate_queued failed: {}", e);
+    }
     if let Err(e) = update_decay(conn, Utc::now().timestamp()) {
         error!("update_decay failed: {}", e);
     }
@@ -148,6 +199,9 @@ pub async fn update_statistics(
     if let Err(e) = update_rankings(conn) {
         error!("update_rankings failed: {}", e);
     }
+    if let Err(e) = expire_supporters(conn, Utc::now().timestamp()) {
+        error!("expire_supporters failed: {}", e);
+    }
 
     while now - *last_ranking_update > RANKING_PERIOD {
         *last_ranking_update += RANKING_PERIOD;
@@ -155,7 +209,9 @@ pub async fn update_statistics(
 
     info!(
         "Last ranking period: {}",
-        NaiveDateTime::from_timestamp_opt(*last_ranking_update, 0).unwrap()
+        DateTime::from_timestamp(*last_ranking_update, 0)
+            .unwrap()
+            .naive_utc()
     );
 
     conn.execute(
@@ -232,209 +288,1541 @@ pub fn print_rankings() {
     }
 }
 
-pub fn mark_vip(vip_id: &str, notes: &str) {
-    let vip_id = i64::from_str_radix(vip_id, 16).unwrap();
+pub fn mark_vip(
+    vip_id: &str,
+    notes: &str,
+    tier: Option<&str>,
+    expiry_date: Option<&str>,
+    badge_text: Option<&str>,
+    badge_colour: Option<&str>,
+) -> Result<()> {
+    let vip_id = i64::from_str_radix(vip_id, 16)?;
+    let supporter = Supporter {
+        tier: tier.unwrap_or("VIP").to_owned(),
+        expiry_timestamp: expiry_date.map(date_to_timestamp).transpose()?,
+        badge_text: badge_text.map(|b| b.to_owned()),
+        badge_colour: badge_colour.map(|c| c.to_owned()),
+    };
 
-    let conn = Connection::open(DB_NAME).unwrap();
-    conn.execute(
-        "INSERT INTO vip_status
-            VALUES(?, 'VIP', ?)",
-        params![vip_id, notes],
-    )
-    .unwrap();
+    let mut conn = Connection::open(DB_NAME)?;
+    add_supporter(&mut conn, vip_id, &supporter, notes)?;
+    log_moderation(&conn, vip_id, "vip", "cli", notes);
+    Ok(())
 }
 
-pub fn mark_hidden(hidden_id: &str, notes: &str) {
-    let hidden_id = i64::from_str_radix(hidden_id, 16).unwrap();
+pub struct Supporter {
+    pub tier: String,
+    pub expiry_timestamp: Option<i64>,
+    pub badge_text: Option<String>,
+    pub badge_colour: Option<String>,
+}
 
-    let conn = Connection::open(DB_NAME).unwrap();
+/// Records a new supporter period starting now and refreshes the badges. Without an expiry it
+/// lasts until it's ended by hand.
+pub fn add_supporter(
+    conn: &mut Connection,
+    id: i64,
+    supporter: &Supporter,
+    notes: &str,
+) -> Result<()> {
+    let now = Utc::now().timestamp();
     conn.execute(
-        "INSERT INTO hidden_status
-            VALUES(?, 'hidden', ?)",
-        params![hidden_id, notes],
-    )
-    .unwrap();
+        "INSERT OR REPLACE INTO supporters(
+            id, tier, start_timestamp, expiry_timestamp, badge_text, badge_colour, notes)
+        VALUES(?, ?, ?, ?, ?, ?, ?)",
+        params![
+            id,
+            supporter.tier,
+            now,
+            supporter.expiry_timestamp,
+            supporter.badge_text,
+            supporter.badge_colour,
+            notes
+        ],
+    )?;
+    update_vip_status(conn, now)?;
+    Ok(())
 }
 
-pub async fn mark_cheater(
-    cheater_id: Option<&str>,
-    cheater_type: Option<&str>,
-    notes: Option<&str>,
-) {
-    let cheater_id = i64::from_str_radix(cheater_id.unwrap(), 16).unwrap();
+/// Ends every running supporter period of a player, returns false if there were none.
+pub fn end_supporter(conn: &mut Connection, id: i64) -> Result<bool> {
+    let now = Utc::now().timestamp();
+    let ended = conn.execute(
+        "UPDATE supporters SET expiry_timestamp = ?
+        WHERE id = ? AND (expiry_timestamp IS NULL OR expiry_timestamp > ?)",
+        params![now, id, now],
+    )?;
+    update_vip_status(conn, now)?;
+    Ok(ended > 0)
+}
 
-    let conn = Connection::open(DB_NAME).unwrap();
+/// Rebuilds `vip_status` from the supporter periods running at the given time. When someone has
+/// more than one, the most recent one decides the badge. Returns the number of current VIPs.
+pub fn update_vip_status(conn: &mut Connection, timestamp: i64) -> Result<usize> {
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM vip_status", [])?;
+    tx.execute(
+        "INSERT OR REPLACE INTO vip_status(id, vip_status, notes, badge_colour)
+        SELECT id, COALESCE(badge_text, 'VIP'), notes, badge_colour
+        FROM supporters
+        WHERE start_timestamp <= ? AND (expiry_timestamp IS NULL OR expiry_timestamp > ?)
+        ORDER BY start_timestamp ASC",
+        params![timestamp, timestamp],
+    )?;
+    //Replaced rows count as inserted too, so players with several periods are counted here
+    let count: i64 = tx.query_row("SELECT COUNT(*) FROM vip_status", [], |r| r.get(0))?;
+    tx.commit()?;
+    Ok(count as usize)
+}
 
-    struct Game {
-        id_a: i64,
-        char_a: i64,
-        value_a: f64,
-        deviation_a: f64,
-        id_b: i64,
-        char_b: i64,
-        value_b: f64,
-        deviation_b: f64,
-        winner: i64,
+pub fn expire_supporters(conn: &mut Connection, timestamp: i64) -> Result<()> {
+    let before: i64 = conn.query_row("SELECT COUNT(*) FROM vip_status", [], |r| r.get(0))?;
+    let after = update_vip_status(conn, timestamp)?;
+    if before as usize != after {
+        info!("VIP statuses changed from {} to {}", before, after);
     }
+    Ok(())
+}
 
-    let games = {
-        let mut stmt = conn.prepare(
-            "SELECT id_a, char_a, value_a, deviation_a, id_b, char_b, value_b, deviation_b, winner
-            FROM game_ratings
-            NATURAL JOIN games
-            WHERE id_a = ? OR id_b = ?").unwrap();
-
-        let mut games = Vec::new();
-        let mut rows = stmt.query(params![cheater_id, cheater_id]).unwrap();
-
-        while let Some(row) = rows.next().unwrap() {
-            games.push(Game {
-                id_a: row.get(0).unwrap(),
-                char_a: row.get(1).unwrap(),
-                value_a: row.get(2).unwrap(),
-                deviation_a: row.get(3).unwrap(),
-                id_b: row.get(4).unwrap(),
-                char_b: row.get(5).unwrap(),
-                value_b: row.get(6).unwrap(),
-                deviation_b: row.get(7).unwrap(),
-                winner: row.get(8).unwrap(),
-            });
-        }
-
-        games
-    };
+pub fn date_to_timestamp(date: &str) -> Result<i64> {
+    Ok(chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")?
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc()
+        .timestamp())
+}
 
-    let mut player_offsets = FxHashMap::<(i64, i64), f64>::default();
+pub fn mark_hidden(hidden_id: &str, notes: &str) {
+    let hidden_id = i64::from_str_radix(hidden_id, 16).unwrap();
 
-    for g in games {
-        if g.id_a == cheater_id {
-            let change = Rating::new(g.value_b, g.deviation_b).rating_change(
-                Rating::new(g.value_a, g.deviation_a),
-                if g.winner == 1 { 0.0 } else { 1.0 },
-            );
+    let mut conn = Connection::open(DB_NAME).unwrap();
+    if set_hidden(&mut conn, hidden_id, true, "cli", notes) {
+        log_moderation(&conn, hidden_id, "hide", "cli", notes);
+    }
+}
 
-            *player_offsets.entry((g.id_b, g.char_b)).or_default() -= change;
-        } else {
-            let change = Rating::new(g.value_a, g.deviation_a).rating_change(
-                Rating::new(g.value_b, g.deviation_b),
-                if g.winner == 1 { 1.0 } else { 0.0 },
-            );
+/// Where a player is in the self-service hide flow. Pending players are waiting for their code
+/// to show up in their R-Code comment, and stay visible (or hidden) as they were until then.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HideState {
+    Unhidden,
+    Pending,
+    Hidden,
+}
 
-            *player_offsets.entry((g.id_a, g.char_a)).or_default() -= change;
+impl HideState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HideState::Unhidden => "unhidden",
+            HideState::Pending => "pending",
+            HideState::Hidden => "hidden",
         }
     }
 
-    for (key, value) in &player_offsets {
-        println!("{:?}: {:.1}", key, value);
+    pub fn from_db(state: &str) -> Self {
+        match state {
+            "unhidden" => HideState::Unhidden,
+            "pending" => HideState::Pending,
+            "hidden" => HideState::Hidden,
+            _ => panic!("Bad hide state {}", state),
+        }
     }
+}
 
-    if cheater_type.is_some() {
-        for ((id, char_id), offset) in player_offsets {
-            conn.execute(
-                "UPDATE player_ratings 
-            SET value = value + ?
-            WHERE id= ? AND char_id = ?",
-                params![offset, id, char_id],
-            )
-            .unwrap();
-        }
+/// Uses up a pending hide code after it was found in the player's comment. Returns the state
+/// the request started from, or None if the request was replaced, expired or already used in
+/// the meantime.
+pub fn claim_hide_code(conn: &mut Connection, id: i64, code: &str, now: i64) -> Option<HideState> {
+    let tx = conn.transaction().unwrap();
 
-        conn.execute(
-            "INSERT INTO cheater_status
-            VALUES(?, ?, ?)",
-            params![cheater_id, cheater_type, notes.unwrap_or("")],
+    let previous_state: Option<String> = tx
+        .query_row(
+            "SELECT previous_state FROM hide_requests
+            WHERE id = ? AND state = 'pending' AND code = ? AND code_expiry > ?",
+            params![id, code, now],
+            |r| r.get(0),
         )
+        .optional()
         .unwrap();
-    }
-}
-
-pub async fn update_fraud_once() {
-    let mut conn = Connection::open(DB_NAME).unwrap();
 
-    if let Err(e) = calc_fraud_index(&mut conn) {
-        error!("calc_fraud_index failed: {}", e);
+    if previous_state.is_some() {
+        tx.execute(
+            "UPDATE hide_requests SET code = NULL, code_expiry = NULL WHERE id = ?",
+            params![id],
+        )
+        .unwrap();
     }
-}
-
-pub async fn update_decay_once() {
-    let mut conn = Connection::open(DB_NAME).unwrap();
 
-    update_decay(&mut conn, Utc::now().timestamp()).unwrap();
+    tx.commit().unwrap();
+    previous_state.map(|s| HideState::from_db(&s))
 }
 
-pub fn get_average_rating(conn: &Transaction, id: i64) -> f64 {
+/// Players who never used the hide flow have no row in `hide_requests`, for them it only
+/// depends on `hidden_status`.
+pub fn get_hide_state(conn: &Connection, id: i64) -> HideState {
     conn.query_row(
-        "select avg(value) from player_ratings where id = ?",
+        "SELECT state FROM hide_requests WHERE id = ?",
         params![id],
-        |r| r.get::<_, Option<f64>>(0),
+        |r| r.get::<_, String>(0),
     )
+    .optional()
     .unwrap()
-    .unwrap_or_default()
+    .map(|state| HideState::from_db(&state))
+    .unwrap_or_else(|| {
+        let hidden: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM hidden_status WHERE id = ?)",
+                params![id],
+                |r| r.get(0),
+            )
+            .unwrap();
+        if hidden {
+            HideState::Hidden
+        } else {
+            HideState::Unhidden
+        }
+    })
 }
 
-pub async fn pull() {
-    let mut conn = Connection::open(DB_NAME).unwrap();
-
-    grab_games(&mut conn, 100).await.unwrap();
+pub fn log_hide_transition(
+    conn: &Connection,
+    id: i64,
+    from: HideState,
+    to: HideState,
+    source: &str,
+) {
+    conn.execute(
+        "INSERT INTO hide_log VALUES(?, ?, ?, ?, ?)",
+        params![
+            Utc::now().timestamp(),
+            id,
+            from.as_str(),
+            to.as_str(),
+            source
+        ],
+    )
+    .unwrap();
 }
 
-async fn grab_games(conn: &mut Connection, _pages: usize) -> Result<()> {
-    let then = Utc::now();
-    info!("Grabbing replays");
-    let replays = ggst_api::get_replays().await;
+/// Hides or unhides a player, dropping any pending code. `source` ends up in `hide_log`.
+/// Returns false if there was nothing to change.
+pub fn set_hidden(
+    conn: &mut Connection,
+    hidden_id: i64,
+    hidden: bool,
+    source: &str,
+    notes: &str,
+) -> bool {
+    let tx = conn.transaction().unwrap();
 
-    let replays = match replays {
-        Ok(replays) => replays,
-        Err(e) => {
-            error!("Error fetching replays: {e}");
-            return Ok(());
-        }
+    let from = get_hide_state(&tx, hidden_id);
+    let to = if hidden {
+        HideState::Hidden
+    } else {
+        HideState::Unhidden
     };
 
-    let old_count: i64 = conn.query_row("SELECT COUNT(*) FROM games", [], |r| r.get(0))?;
+    let was_hidden: bool = tx
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM hidden_status WHERE id = ?)",
+            params![hidden_id],
+            |r| r.get(0),
+        )
+        .unwrap();
 
-    let tx = conn.transaction()?;
+    if hidden {
+        tx.execute(
+            "INSERT INTO hidden_status(id, hidden_status, notes)
+                VALUES(?, 'hidden', ?)
+                ON CONFLICT(id) DO UPDATE SET notes = excluded.notes",
+            params![hidden_id, notes],
+        )
+        .unwrap();
+    } else {
+        tx.execute("DELETE FROM hidden_status WHERE id = ?", params![hidden_id])
+            .unwrap();
+    }
 
-    let mut new_games = Vec::new();
+    tx.execute(
+        "UPDATE hide_requests SET state = ?, code = NULL, code_expiry = NULL WHERE id = ?",
+        params![to.as_str(), hidden_id],
+    )
+    .unwrap();
 
-    let num_replays = replays.len();
-    for r in replays {
-        new_games.extend(add_game(&tx, r));
+    if from != to {
+        log_hide_transition(&tx, hidden_id, from, to, source);
     }
-    tx.commit()?;
 
-    let count: i64 = conn.query_row("SELECT COUNT(*) FROM games", [], |r| r.get(0))?;
+    tx.commit().unwrap();
 
-    let elapsed = (Utc::now() - then).num_milliseconds();
+    was_hidden != hidden
+}
+
+pub fn add_season(name: &str, start_date: &str, soft_reset: Option<&str>) -> Result<()> {
+    let start_timestamp = date_to_timestamp(start_date)?;
+    let soft_reset = soft_reset.map(|s| s.parse::<f64>()).transpose()?;
+
+    let mut conn = Connection::open(DB_NAME)?;
+    let season_id = create_season(&mut conn, name, start_timestamp, soft_reset)?;
 
     info!(
-        "Grabbed {} games -  new games: {} ({} total) - {}ms",
-        num_replays,
-        count - old_count,
-        count,
-        elapsed,
+        "Added season {} ({}) starting {}",
+        name, season_id, start_date
     );
 
-    assert_eq!(count - old_count, new_games.len() as i64);
+    Ok(())
+}
 
-    update_ratings(conn, Some(new_games));
+/// Starts a season at `start_timestamp`, ending the ongoing one there, and rebuilds both from
+/// the games already rated. Seasons can't overlap, so it has to start after every other season.
+pub fn create_season(
+    conn: &mut Connection,
+    name: &str,
+    start_timestamp: i64,
+    soft_reset: Option<f64>,
+) -> Result<i64> {
+    let tx = conn.transaction()?;
 
-    if count - old_count == num_replays as i64 {
-        if num_replays > 0 {
-            error!("Only new replays! We're probably missing some, try increasing the page count.");
-        } else {
-            error!("No replays! Maybe servers are down?");
+    let overlapping: Option<String> = tx
+        .query_row(
+            "SELECT name FROM seasons
+            WHERE start_timestamp >= ?1 OR end_timestamp > ?1
+            LIMIT 1",
+            params![start_timestamp],
+            |r| r.get(0),
+        )
+        .optional()?;
+    if let Some(overlapping) = overlapping {
+        anyhow::bail!("Season {} overlaps with the new season", overlapping);
+    }
+
+    //The previous season ends where the new one starts
+    let previous_id: Option<i64> = tx
+        .query_row(
+            "SELECT season_id FROM seasons WHERE end_timestamp IS NULL",
+            [],
+            |r| r.get(0),
+        )
+        .optional()?;
+    tx.execute(
+        "UPDATE seasons SET end_timestamp = ? WHERE end_timestamp IS NULL",
+        params![start_timestamp],
+    )?;
+    tx.execute(
+        "INSERT INTO seasons(name, start_timestamp, end_timestamp, soft_reset)
+        VALUES(?, ?, NULL, ?)",
+        params![name, start_timestamp, soft_reset],
+    )?;
+    let season_id = tx.last_insert_rowid();
+    tx.commit()?;
+
+    //Games from after the new start date move from the previous season into the new one
+    if let Some(previous_id) = previous_id {
+        rebuild_season(conn, previous_id)?;
+    }
+    rebuild_season(conn, season_id)?;
+
+    Ok(season_id)
+}
+
+pub fn rebuild_season_once(season_id: &str) -> Result<()> {
+    let mut conn = Connection::open(DB_NAME)?;
+    rebuild_season(&mut conn, season_id.parse()?)
+}
+
+/// Recalculates a season's ratings from the games that have already been rated.
+pub fn rebuild_season(conn: &mut Connection, season_id: i64) -> Result<()> {
+    info!("Rebuilding season {}", season_id);
+    let then = Utc::now();
+
+    let tx = conn.transaction()?;
+    let season = tx.query_row(
+        "SELECT season_id, name, start_timestamp, end_timestamp, soft_reset
+        FROM seasons WHERE season_id = ?",
+        params![season_id],
+        |r| Ok(Season::from_row(r)),
+    )?;
+
+    tx.execute(
+        "DELETE FROM season_player_ratings WHERE season_id = ?",
+        params![season_id],
+    )?;
+    tx.execute(
+        "DELETE FROM season_matchups WHERE season_id = ?",
+        params![season_id],
+    )?;
+
+    let mut season_players = FxHashMap::default();
+    {
+        let mut stmt = tx.prepare(
+            "SELECT
+                timestamp,
+                id_a, char_a, value_a, deviation_a,
+                id_b, char_b, value_b, deviation_b,
+                winner
+            FROM games NATURAL JOIN game_ratings
+            WHERE valid AND timestamp >= ? AND timestamp < ?
+            ORDER BY timestamp ASC",
+        )?;
+        let mut rows = stmt.query(params![
+            season.start_timestamp,
+            season.end_timestamp.unwrap_or(i64::MAX)
+        ])?;
+
+        while let Some(row) = rows.next()? {
+            let timestamp: i64 = row.get(0)?;
+            let a = (row.get::<_, i64>(1)?, row.get::<_, i64>(2)?);
+            let rating_a = Rating::new(row.get(3)?, row.get(4)?);
+            let b = (row.get::<_, i64>(5)?, row.get::<_, i64>(6)?);
+            let rating_b = Rating::new(row.get(7)?, row.get(8)?);
+
+            let ((winner, winner_rating), (loser, loser_rating)) = match row.get(9)? {
+                1 => ((a, rating_a), (b, rating_b)),
+                2 => ((b, rating_b), (a, rating_a)),
+                _ => panic!("Bad winner"),
+            };
+
+            update_season_ratings(
+                &tx,
+                &season,
+                &mut season_players,
+                timestamp,
+                winner,
+                loser,
+                winner_rating,
+                loser_rating,
+            );
+            update_season_matchup(&tx, season.season_id, winner.1, loser.1);
         }
-    } else if count - old_count > num_replays as i64 / 2 {
-        warn!("Over half the grabbed replays are new, consider increasing page count.");
     }
 
+    let player_count = season_players.len();
+    save_season_players(&tx, season_players);
+    tx.commit()?;
+
+    info!(
+        "Rebuilt season {} with {} players - {}ms",
+        season.name,
+        player_count,
+        (Utc::now() - then).num_milliseconds()
+    );
+
     Ok(())
 }
 
-fn add_game(conn: &Transaction, game: responses::Replay) -> Option<Game> {
-    //2023-01-30 01:52:15"
-    let responses::Replay {
+/// Marks a player as a cheater and re-rates everyone who played them as if those games never
+/// happened. Without a cheater type the estimated offsets are only printed. Marking an already marked
+/// player only updates the status.
+pub async fn mark_cheater(
+    cheater_id: Option<&str>,
+    cheater_type: Option<&str>,
+    notes: Option<&str>,
+    moderator: Option<&str>,
+) {
+    let cheater_id = i64::from_str_radix(cheater_id.unwrap(), 16).unwrap();
+    let moderator = get_moderator(moderator);
+
+    let mut conn = Connection::open(DB_NAME).unwrap();
+
+    if let Some(cheater_type) = cheater_type {
+        if set_cheater(
+            &mut conn,
+            cheater_id,
+            cheater_type,
+            notes.unwrap_or(""),
+            &moderator,
+        ) {
+            let count = rerate_cheater_opponents(&mut conn, cheater_id);
+            println!(
+                "Marked {:X} as {}, re-rated {} characters",
+                cheater_id, cheater_type, count
+            );
+        } else {
+            println!(
+                "{:X} is already marked, opponents are not re-rated again",
+                cheater_id
+            );
+        }
+    } else {
+        for (key, value) in &get_cheater_offsets(&conn, cheater_id) {
+            println!("{:?}: {:.1}", key, value);
+        }
+    }
+}
+
+/// Applies a cheater status and returns whether the player's opponents have to be re-rated,
+/// which isn't the case if they were already marked and only the status and notes changed.
+pub fn set_cheater(
+    conn: &mut Connection,
+    cheater_id: i64,
+    cheater_type: &str,
+    notes: &str,
+    moderator: &str,
+) -> bool {
+    let tx = conn.transaction().unwrap();
+
+    let existing_status: Option<String> = tx
+        .query_row(
+            "SELECT cheater_status FROM cheater_status WHERE id = ?",
+            params![cheater_id],
+            |r| r.get(0),
+        )
+        .optional()
+        .unwrap();
+
+    if let Some(existing_status) = existing_status {
+        if cheater_type != existing_status {
+            tx.execute(
+                "UPDATE cheater_status SET cheater_status = ?, notes = ? WHERE id = ?",
+                params![cheater_type, notes, cheater_id],
+            )
+            .unwrap();
+            log_cheater_action(
+                &tx,
+                cheater_id,
+                "update",
+                Some(cheater_type),
+                moderator,
+                Some(notes),
+            );
+            tx.commit().unwrap();
+        }
+        return false;
+    }
+
+    tx.execute(
+        "INSERT INTO cheater_status
+        VALUES(?, ?, ?)",
+        params![cheater_id, cheater_type, notes],
+    )
+    .unwrap();
+
+    log_cheater_action(
+        &tx,
+        cheater_id,
+        "mark",
+        Some(cheater_type),
+        moderator,
+        Some(notes),
+    );
+
+    tx.commit().unwrap();
+    true
+}
+
+/// Removes the cheater status and re-rates the cheater and their opponents with their games
+/// counting again. Since the same games are replayed this gives everyone back the ratings they
+/// had before the marking, instead of undoing a stored per-opponent offset.
+pub fn unmark_cheater(cheater_id: &str, notes: Option<&str>, moderator: Option<&str>) {
+    let cheater_id = i64::from_str_radix(cheater_id, 16).unwrap();
+    let moderator = get_moderator(moderator);
+
+    let mut conn = Connection::open(DB_NAME).unwrap();
+
+    if remove_cheater(&mut conn, cheater_id, notes.unwrap_or(""), &moderator) {
+        let count = rerate_cheater_opponents(&mut conn, cheater_id);
+        println!("Unmarked {:X}, re-rated {} characters", cheater_id, count);
+    } else {
+        println!("{:X} is not marked as a cheater", cheater_id);
+    }
+}
+
+/// Removes a cheater status and returns whether the player's opponents have to be re-rated,
+/// which isn't the case if they weren't marked.
+pub fn remove_cheater(
+    conn: &mut Connection,
+    cheater_id: i64,
+    notes: &str,
+    moderator: &str,
+) -> bool {
+    let tx = conn.transaction().unwrap();
+
+    let removed = tx
+        .execute(
+            "DELETE FROM cheater_status WHERE id = ?",
+            params![cheater_id],
+        )
+        .unwrap();
+
+    if removed == 0 {
+        return false;
+    }
+
+    log_cheater_action(&tx, cheater_id, "unmark", None, moderator, Some(notes));
+
+    tx.commit().unwrap();
+    true
+}
+
+/// Re-rates a player and their opponents after their cheater status was applied or removed,
+/// and returns how many characters were re-rated. How much each of them moved is kept in
+/// `cheater_offsets` under the marking or unmarking that caused it.
+pub fn rerate_cheater_opponents(conn: &mut Connection, cheater_id: i64) -> usize {
+    let tx = conn.transaction().unwrap();
+    let (changes, since) = rerate_opponents(&tx, cheater_id);
+
+    let log_id: Option<i64> = tx
+        .query_row(
+            "SELECT log_id FROM cheater_log
+            WHERE id = ? AND action IN ('mark', 'unmark')
+            ORDER BY log_id DESC
+            LIMIT 1",
+            params![cheater_id],
+            |r| r.get(0),
+        )
+        .optional()
+        .unwrap();
+    if let Some(log_id) = log_id {
+        let mut stmt = tx
+            .prepare("REPLACE INTO cheater_offsets VALUES(?, ?, ?, ?)")
+            .unwrap();
+        for ((id, char_id), offset) in &changes {
+            stmt.execute(params![log_id, id, char_id, offset]).unwrap();
+        }
+    }
+
+    tx.commit().unwrap();
+
+    if let Some(since) = since {
+        rebuild_seasons_since(conn, since);
+    }
+
+    changes.len()
+}
+
+/// Leaves re-rating a player's opponents to the rater, for status changes made from the admin
+/// pages which shouldn't wait for it.
+pub fn queue_rerate(conn: &Connection, cheater_id: i64) {
+    conn.execute(
+        "INSERT OR IGNORE INTO pending_rerates VALUES(?, ?)",
+        params![cheater_id, Utc::now().timestamp()],
+    )
+    .unwrap();
+}
+
+/// Re-rates the opponents of everyone in `pending_rerates`, oldest request first.
+pub fn rerate_queued(conn: &mut Connection) -> Result<()> {
+    let ids = {
+        let mut stmt = conn.prepare("SELECT id FROM pending_rerates ORDER BY requested ASC")?;
+        let rows = stmt.query_map([], |r| r.get::<_, i64>(0))?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()?
+    };
+
+    for id in ids {
+        let count = rerate_cheater_opponents(conn, id);
+        conn.execute("DELETE FROM pending_rerates WHERE id = ?", params![id])?;
+        info!("Re-rated {} characters for {:X}", count, id);
+    }
+    Ok(())
+}
+
+/// Estimates how much rating each opponent lost to (or took from) a player, by undoing the
+/// rating change of every rated game against them.
+pub fn get_cheater_offsets(conn: &Connection, cheater_id: i64) -> FxHashMap<(i64, i64), f64> {
+    struct Game {
+        id_a: i64,
+        char_a: i64,
+        value_a: f64,
+        deviation_a: f64,
+        id_b: i64,
+        char_b: i64,
+        value_b: f64,
+        deviation_b: f64,
+        winner: i64,
+    }
+
+    let games = {
+        let mut stmt = conn.prepare(
+            "SELECT id_a, char_a, value_a, deviation_a, id_b, char_b, value_b, deviation_b, winner
+            FROM game_ratings
+            NATURAL JOIN games
+            WHERE (id_a = ? OR id_b = ?) AND valid").unwrap();
+
+        let mut games = Vec::new();
+        let mut rows = stmt.query(params![cheater_id, cheater_id]).unwrap();
+
+        while let Some(row) = rows.next().unwrap() {
+            games.push(Game {
+                id_a: row.get(0).unwrap(),
+                char_a: row.get(1).unwrap(),
+                value_a: row.get(2).unwrap(),
+                deviation_a: row.get(3).unwrap(),
+                id_b: row.get(4).unwrap(),
+                char_b: row.get(5).unwrap(),
+                value_b: row.get(6).unwrap(),
+                deviation_b: row.get(7).unwrap(),
+                winner: row.get(8).unwrap(),
+            });
+        }
+
+        games
+    };
+
+    let mut player_offsets = FxHashMap::<(i64, i64), f64>::default();
+
+    for g in games {
+        if g.id_a == cheater_id {
+            let change = Rating::new(g.value_b, g.deviation_b).rating_change(
+                Rating::new(g.value_a, g.deviation_a),
+                if g.winner == 1 { 0.0 } else { 1.0 },
+            );
+
+            *player_offsets.entry((g.id_b, g.char_b)).or_default() -= change;
+        } else {
+            let change = Rating::new(g.value_a, g.deviation_a).rating_change(
+                Rating::new(g.value_b, g.deviation_b),
+                if g.winner == 1 { 1.0 } else { 0.0 },
+            );
+
+            *player_offsets.entry((g.id_a, g.char_a)).or_default() -= change;
+        }
+    }
+
+    player_offsets
+}
+
+/// The first game of each character in the games matching `condition`, which is where their
+/// re-rating has to start.
+fn first_games(
+    tx: &Transaction,
+    condition: &str,
+    params: impl rusqlite::Params,
+) -> FxHashMap<(i64, i64), i64> {
+    let mut first_games = FxHashMap::<(i64, i64), i64>::default();
+    let mut stmt = tx
+        .prepare(&format!(
+            "SELECT timestamp, id_a, char_a, id_b, char_b FROM games WHERE {}",
+            condition
+        ))
+        .unwrap();
+    let mut rows = stmt.query(params).unwrap();
+    while let Some(row) = rows.next().unwrap() {
+        let timestamp: i64 = row.get(0).unwrap();
+        for key in [
+            (row.get(1).unwrap(), row.get(2).unwrap()),
+            (row.get(3).unwrap(), row.get(4).unwrap()),
+        ] {
+            let first = first_games.entry(key).or_insert(timestamp);
+            *first = (*first).min(timestamp);
+        }
+    }
+    first_games
+}
+
+/// Re-rates a player and everyone who played them, each from their own first game against
+/// them. Returns the rating changes and the first affected timestamp.
+fn rerate_opponents(tx: &Transaction, player_id: i64) -> (FxHashMap<(i64, i64), f64>, Option<i64>) {
+    let first_games = first_games(tx, "id_a = ?1 OR id_b = ?1", params![player_id]);
+    let since = first_games.values().min().copied();
+    (rerate_players(tx, &first_games), since)
+}
+
+/// A character's state just before `timestamp`, from the games they played until then. This is
+/// where re-rating them starts, with `rating` being their stored rating going into that game.
+fn rated_player_before(
+    tx: &Transaction,
+    (id, char_id): (i64, i64),
+    timestamp: i64,
+    rating: Rating,
+) -> RatedPlayer {
+    //Each game from the character's side, the same way update_ratings sees them
+    const OWN_GAMES: &str = "
+        SELECT
+            timestamp, valid, winner = 1 AS won,
+            value_a AS value, deviation_a AS deviation,
+            id_b AS opp_id, char_b AS opp_char_id, name_b AS opp_name,
+            value_b AS opp_value, deviation_b AS opp_deviation, game_floor
+        FROM games NATURAL JOIN game_ratings
+        WHERE id_a = ?1 AND char_a = ?2 AND timestamp < ?3
+        UNION ALL
+        SELECT
+            timestamp, valid, winner = 2,
+            value_b, deviation_b,
+            id_a, char_a, name_a,
+            value_a, deviation_a, game_floor
+        FROM games NATURAL JOIN game_ratings
+        WHERE id_b = ?1 AND char_b = ?2 AND timestamp < ?3";
+    //Games with cheaters, hidden players or excluded trading pairs don't count for top ratings
+    const COUNTED: &str = "
+        opp_id NOT IN (SELECT id FROM cheater_status)
+        AND opp_id NOT IN (SELECT id FROM hidden_status)
+        AND ?1 NOT IN (SELECT id FROM cheater_status)
+        AND ?1 NOT IN (SELECT id FROM hidden_status)
+        AND NOT EXISTS (
+            SELECT 1 FROM trading_pairs
+            WHERE status = 'excluded'
+                AND ((winner_id = ?1 AND loser_id = opp_id) OR (winner_id = opp_id AND loser_id = ?1))
+        )";
+
+    let (win_count, loss_count) = tx
+        .query_row(
+            &format!(
+                "SELECT COALESCE(SUM(won), 0), COALESCE(SUM(NOT won), 0)
+                FROM ({}) WHERE valid",
+                OWN_GAMES
+            ),
+            params![id, char_id, timestamp],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .unwrap();
+
+    //Ties go to the latest top rating and the earliest top defeated, like in RatedPlayer
+    let top_rating = tx
+        .query_row(
+            &format!(
+                "SELECT value, deviation, timestamp FROM ({})
+                WHERE deviation < ?4 AND {}
+                ORDER BY value DESC, timestamp DESC
+                LIMIT 1",
+                OWN_GAMES, COUNTED
+            ),
+            params![id, char_id, timestamp, LOW_DEVIATION],
+            |r| {
+                Ok(TopRating {
+                    value: r.get(0)?,
+                    deviation: r.get(1)?,
+                    timestamp: r.get(2)?,
+                })
+            },
+        )
+        .optional()
+        .unwrap();
+    let top_defeated = tx
+        .query_row(
+            &format!(
+                "SELECT opp_id, opp_char_id, opp_name, opp_value, opp_deviation, game_floor, timestamp
+                FROM ({})
+                WHERE won AND opp_deviation < ?4 AND {}
+                ORDER BY opp_value DESC, timestamp ASC
+                LIMIT 1",
+                OWN_GAMES, COUNTED
+            ),
+            params![id, char_id, timestamp, LOW_DEVIATION],
+            |r| {
+                Ok(TopDefeated {
+                    id: r.get(0)?,
+                    char_id: r.get(1)?,
+                    name: r.get(2)?,
+                    value: r.get(3)?,
+                    deviation: r.get(4)?,
+                    floor: r.get(5)?,
+                    timestamp: r.get(6)?,
+                })
+            },
+        )
+        .optional()
+        .unwrap();
+
+    RatedPlayer {
+        id,
+        char_id,
+        win_count,
+        loss_count,
+        rating,
+        last_decay: timestamp,
+        top_rating,
+        top_defeated,
+        character_rank: None,
+    }
+}
+
+/// Rebuilds a character's matchup ratings from the stored ratings of their valid games.
+fn rebuild_player_matchups(tx: &Transaction, (id, char_id): (i64, i64)) {
+    tx.execute(
+        "DELETE FROM player_matchups WHERE id = ? AND char_id = ?",
+        params![id, char_id],
+    )
+    .unwrap();
+
+    let mut stmt = tx
+        .prepare(
+            "SELECT timestamp, value, deviation, opp_char_id, opp_value, opp_deviation, won
+            FROM (
+                SELECT
+                    timestamp, valid, winner = 1 AS won,
+                    value_a AS value, deviation_a AS deviation,
+                    char_b AS opp_char_id, value_b AS opp_value, deviation_b AS opp_deviation
+                FROM games NATURAL JOIN game_ratings
+                WHERE id_a = ?1 AND char_a = ?2
+                UNION ALL
+                SELECT
+                    timestamp, valid, winner = 2,
+                    value_b, deviation_b,
+                    char_a, value_a, deviation_a
+                FROM games NATURAL JOIN game_ratings
+                WHERE id_b = ?1 AND char_b = ?2
+            )
+            WHERE valid
+            ORDER BY timestamp ASC",
+        )
+        .unwrap();
+    let mut rows = stmt.query(params![id, char_id]).unwrap();
+    while let Some(row) = rows.next().unwrap() {
+        update_player_matchup(
+            tx,
+            id,
+            char_id,
+            Rating::new(row.get(1).unwrap(), row.get(2).unwrap()),
+            row.get(3).unwrap(),
+            Rating::new(row.get(4).unwrap(), row.get(5).unwrap()),
+            if row.get(6).unwrap() { 1.0 } else { 0.0 },
+            row.get(0).unwrap(),
+        );
+    }
+}
+
+/// Replays every game from the earliest of the given timestamps on. Each of the given
+/// characters is re-rated from their own timestamp, starting from their stored rating going
+/// into that game. Anyone playing a re-rated character whose rating moved, or a game whose
+/// validity changed, is re-rated from that game on as well, so the change carries over to
+/// their later opponents. Re-rated characters get their player ratings, top rating and top
+/// defeated, player matchups, daily ratings and game ratings rewritten, and games that changed
+/// validity are added to or removed from the matchup tiers they counted for at the time.
+/// Returns how much each re-rated character's rating changed.
+pub fn rerate_players(
+    tx: &Transaction,
+    first_games: &FxHashMap<(i64, i64), i64>,
+) -> FxHashMap<(i64, i64), f64> {
+    let since = match first_games.values().min() {
+        Some(&since) => since,
+        None => return FxHashMap::default(),
+    };
+    let then = Utc::now();
+    info!(
+        "Re-rating {} characters and their opponents since {}",
+        first_games.len(),
+        since
+    );
+
+    let cheaters = get_cheater_ids(tx);
+    let traded = get_excluded_pairs(tx);
+    let hidden = get_hidden_ids(tx);
+    let popularities = get_global_popularities(tx);
+    let matchup_tiers = get_matchup_tiers(tx);
+
+    let mut players = FxHashMap::<(i64, i64), RatedPlayer>::default();
+    let mut old_values = FxHashMap::default();
+    //Game ratings are only written once the games have all been read
+    let mut rewritten = Vec::new();
+    let mut tier_changes = Vec::new();
+
+    let mut stmt = tx
+        .prepare(
+            "SELECT
+                timestamp,
+                id_a, name_a, char_a, value_a, deviation_a, rank_a,
+                id_b, name_b, char_b, value_b, deviation_b, rank_b,
+                winner, game_floor, valid
+            FROM games NATURAL JOIN game_ratings
+            WHERE timestamp >= ?
+            ORDER BY timestamp ASC",
+        )
+        .unwrap();
+    let mut rows = stmt.query(params![since]).unwrap();
+
+    while let Some(row) = rows.next().unwrap() {
+        let timestamp: i64 = row.get(0).unwrap();
+        let key_a: (i64, i64) = (row.get(1).unwrap(), row.get(3).unwrap());
+        let key_b: (i64, i64) = (row.get(7).unwrap(), row.get(9).unwrap());
+        let stored_a = Rating::new(row.get(4).unwrap(), row.get(5).unwrap());
+        let stored_b = Rating::new(row.get(10).unwrap(), row.get(11).unwrap());
+
+        let starts = |key: (i64, i64)| first_games.get(&key).is_some_and(|&t| timestamp >= t);
+        if !players.contains_key(&key_a)
+            && !players.contains_key(&key_b)
+            && !starts(key_a)
+            && !starts(key_b)
+        {
+            continue;
+        }
+
+        let mut rating_of = |key: (i64, i64), stored: Rating| match players.get_mut(&key) {
+            Some(player) => {
+                player.decay(timestamp);
+                player.rating
+            }
+            None => stored,
+        };
+        let rating_a = rating_of(key_a, stored_a);
+        let rating_b = rating_of(key_b, stored_b);
+
+        let name_a: String = row.get(2).unwrap();
+        let name_b: String = row.get(8).unwrap();
+        let (winner, winner_rating, loser, loser_rating, loser_name) = match row.get(13).unwrap() {
+            1 => (key_a, rating_a, key_b, rating_b, name_b),
+            2 => (key_b, rating_b, key_a, rating_a, name_a),
+            _ => panic!("Bad winner"),
+        };
+        let game_floor: i64 = row.get(14).unwrap();
+        let was_valid: bool = row.get(15).unwrap();
+
+        //Games between a pair excluded for win trading are thrown out like games with cheaters
+        let has_cheater = cheaters.contains(&key_a.0)
+            || cheaters.contains(&key_b.0)
+            || traded.contains(&(key_a.0.min(key_b.0), key_a.0.max(key_b.0)));
+        let has_hidden = hidden.contains(&key_a.0) || hidden.contains(&key_b.0);
+        let valid = within_margin(winner_rating, loser_rating) && !has_cheater && !has_hidden;
+
+        //A character joins in at their own first affected game, or as soon as a game of theirs
+        //comes out differently
+        let moved = |rating: Rating, stored: Rating| {
+            (rating.value - stored.value).abs() > RERATE_THRESHOLD
+                || (rating.deviation - stored.deviation).abs() > RERATE_THRESHOLD
+        };
+        let changed = valid != was_valid || moved(rating_a, stored_a) || moved(rating_b, stored_b);
+        for (key, rating) in [(key_a, rating_a), (key_b, rating_b)] {
+            if !players.contains_key(&key) && (changed || starts(key)) {
+                let player = rated_player_before(tx, key, timestamp, rating);
+                let old_value: Option<f64> = tx
+                    .query_row(
+                        "SELECT value FROM player_ratings WHERE id = ? AND char_id = ?",
+                        params![key.0, key.1],
+                        |r| r.get(0),
+                    )
+                    .optional()
+                    .unwrap();
+                old_values.insert(key, old_value.unwrap_or(1500.0));
+                //The rest of the day is replayed, the days after are written again
+                tx.execute(
+                    "DELETE FROM daily_ratings WHERE id = ? AND char_id = ? AND timestamp > ?",
+                    params![key.0, key.1, timestamp - timestamp.rem_euclid(60 * 60 * 24)],
+                )
+                .unwrap();
+                players.insert(key, player);
+            }
+        }
+
+        if !has_cheater && !has_hidden {
+            if let Some(player) = players.get_mut(&winner) {
+                player.update_top_rating(timestamp);
+                player.update_top_defeated(
+                    loser.0,
+                    loser.1,
+                    loser_name,
+                    loser_rating,
+                    game_floor,
+                    timestamp,
+                );
+            }
+            if let Some(player) = players.get_mut(&loser) {
+                player.update_top_rating(timestamp);
+            }
+        }
+
+        if valid {
+            let day_timestamp = timestamp - timestamp.rem_euclid(60 * 60 * 24);
+
+            for (key, own_rating, opp_rating, result) in [
+                (winner, winner_rating, loser_rating, 1.0),
+                (loser, loser_rating, winner_rating, 0.0),
+            ] {
+                if let Some(player) = players.get_mut(&key) {
+                    player.rating = own_rating.update(opp_rating, result);
+                    if result == 1.0 {
+                        player.win_count += 1;
+                    } else {
+                        player.loss_count += 1;
+                    }
+
+                    if player.rating.deviation < LOW_DEVIATION {
+                        tx.execute(
+                            "REPLACE INTO daily_ratings VALUES(?, ?, ?, ?, ?)",
+                            params![
+                                key.0,
+                                key.1,
+                                day_timestamp,
+                                player.rating.value,
+                                player.rating.deviation
+                            ],
+                        )
+                        .unwrap();
+                    }
+                }
+            }
+        }
+
+        if valid != was_valid {
+            //Ranks are stored with each game, older games fall back to the current ones
+            let rank_a: Option<i64> = row.get(6).unwrap();
+            let rank_b: Option<i64> = row.get(12).unwrap();
+            let rank_a = rank_a.unwrap_or_else(|| get_character_rank(tx, key_a));
+            let rank_b = rank_b.unwrap_or_else(|| get_character_rank(tx, key_b));
+            let (winner_rank, loser_rank) = if winner == key_a {
+                (rank_a, rank_b)
+            } else {
+                (rank_b, rank_a)
+            };
+            for tier in &matchup_tiers {
+                if tier.includes(
+                    winner_rank,
+                    winner_rating,
+                    *popularities.get(&winner.1).unwrap_or(&0.0),
+                    game_floor,
+                ) && tier.includes(
+                    loser_rank,
+                    loser_rating,
+                    *popularities.get(&loser.1).unwrap_or(&0.0),
+                    game_floor,
+                ) {
+                    tier_changes.push((tier.tier_id, winner.1, loser.1, valid));
+                }
+            }
+        }
+
+        rewritten.push((timestamp, key_a.0, key_b.0, rating_a, rating_b, valid));
+    }
+    drop(rows);
+    drop(stmt);
+
+    for (timestamp, id_a, id_b, rating_a, rating_b, valid) in rewritten {
+        tx.execute(
+            "UPDATE game_ratings
+            SET value_a = ?, deviation_a = ?, value_b = ?, deviation_b = ?, valid = ?
+            WHERE timestamp = ? AND id_a = ? AND id_b = ?",
+            params![
+                rating_a.value,
+                rating_a.deviation,
+                rating_b.value,
+                rating_b.deviation,
+                valid,
+                timestamp,
+                id_a,
+                id_b
+            ],
+        )
+        .unwrap();
+    }
+
+    for (tier_id, winner_char, loser_char, valid) in tier_changes {
+        if valid {
+            update_global_matchup(tx, tier_id, winner_char, loser_char);
+        } else {
+            remove_global_matchup(tx, tier_id, winner_char, loser_char);
+        }
+    }
+
+    let mut changes = FxHashMap::default();
+    for (key, player) in players {
+        changes.insert(key, player.rating.value - old_values[&key]);
+        save_rated_player(tx, &player);
+        rebuild_player_matchups(tx, key);
+    }
+
+    info!(
+        "Re-rated {} characters - {}ms",
+        changes.len(),
+        (Utc::now() - then).num_milliseconds()
+    );
+
+    changes
+}
+
+/// Seasons are cheap enough to rebuild from game ratings, so this is used after rewriting them.
+fn rebuild_seasons_since(conn: &mut Connection, timestamp: i64) {
+    let seasons = get_seasons(conn);
+    for season in seasons {
+        if season
+            .end_timestamp
+            .map(|end| end > timestamp)
+            .unwrap_or(true)
+        {
+            if let Err(e) = rebuild_season(conn, season.season_id) {
+                error!("rebuild_season failed: {}", e);
+            }
+        }
+    }
+}
+
+fn get_moderator(moderator: Option<&str>) -> String {
+    moderator
+        .map(|m| m.to_owned())
+        .or_else(|| std::env::var("USER").ok())
+        .unwrap_or_default()
+}
+
+fn log_cheater_action(
+    tx: &Transaction,
+    cheater_id: i64,
+    action: &str,
+    cheater_type: Option<&str>,
+    moderator: &str,
+    notes: Option<&str>,
+) {
+    tx.execute(
+        "INSERT INTO cheater_log(timestamp, id, action, cheater_status, moderator, notes)
+        VALUES(?, ?, ?, ?, ?, ?)",
+        params![
+            Utc::now().timestamp(),
+            cheater_id,
+            action,
+            cheater_type,
+            moderator,
+            notes.unwrap_or("")
+        ],
+    )
+    .unwrap();
+}
+
+/// Records who changed a player's VIP or hidden status and when.
+pub fn log_moderation(conn: &Connection, id: i64, action: &str, moderator: &str, notes: &str) {
+    conn.execute(
+        "INSERT INTO moderation_log(timestamp, id, action, moderator, notes)
+        VALUES(?, ?, ?, ?, ?)",
+        params![Utc::now().timestamp(), id, action, moderator, notes],
+    )
+    .unwrap();
+}
+
+pub async fn update_fraud_once() {
+    let mut conn = Connection::open(DB_NAME).unwrap();
+
+    if let Err(e) = calc_fraud_index(&mut conn) {
+        error!("calc_fraud_index failed: {}", e);
+    }
+}
+
+pub async fn update_matchup_estimates_once() {
+    let mut conn = Connection::open(DB_NAME).unwrap();
+
+    if let Err(e) = calc_matchup_estimates(&mut conn, Utc::now().timestamp()) {
+        error!("calc_matchup_estimates failed: {}", e);
+    }
+}
+
+pub async fn detect_suspicious_once() {
+    let mut conn = Connection::open(DB_NAME).unwrap();
+
+    if let Err(e) = detect_suspicious_accounts(&mut conn, Utc::now().timestamp()) {
+        error!("detect_suspicious_accounts failed: {}", e);
+    }
+}
+
+pub fn print_suspicious() {
+    let conn = Connection::open(DB_NAME).unwrap();
+
+    println!("| Id | Name | Reason | Score | Details | Flagged |");
+    println!("|----|------|--------|-------|---------|---------|");
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT suspicious_accounts.id, name, reason, score, details, flagged_at
+            FROM suspicious_accounts LEFT JOIN players ON players.id = suspicious_accounts.id
+            WHERE status = 'open'
+            ORDER BY flagged_at DESC, score DESC",
+        )
+        .unwrap();
+
+    let mut rows = stmt.query([]).unwrap();
+    while let Some(row) = rows.next().unwrap() {
+        let id: i64 = row.get(0).unwrap();
+        let name: Option<String> = row.get(1).unwrap();
+        let reason: String = row.get(2).unwrap();
+        let score: f64 = row.get(3).unwrap();
+        let details: String = row.get(4).unwrap();
+        let flagged_at: i64 = row.get(5).unwrap();
+        println!(
+            "| {:X} | {} | {} | {:.1} | {} | {} |",
+            id,
+            name.unwrap_or_default(),
+            reason,
+            score,
+            details,
+            DateTime::from_timestamp(flagged_at, 0).unwrap().naive_utc()
+        );
+    }
+}
+
+/// Takes flags off the review queue. They are not raised again for the same reason.
+pub fn dismiss_suspicious(id: &str, reason: Option<&str>) {
+    let id = i64::from_str_radix(id, 16).unwrap();
+    let conn = Connection::open(DB_NAME).unwrap();
+
+    let dismissed = conn
+        .execute(
+            "UPDATE suspicious_accounts SET status = 'dismissed'
+            WHERE id = ? AND (? IS NULL OR reason = ?) AND status = 'open'",
+            params![id, reason, reason],
+        )
+        .unwrap();
+
+    println!("Dismissed {} flags for {:X}", dismissed, id);
+}
+
+pub async fn detect_trading_once() {
+    let mut conn = Connection::open(DB_NAME).unwrap();
+
+    if let Err(e) = detect_trading_pairs(&mut conn, Utc::now().timestamp()) {
+        error!("detect_trading_pairs failed: {}", e);
+    }
+}
+
+/// Lists how much every character moved in the re-rates after a player was marked or unmarked.
+pub fn print_cheater_offsets(cheater_id: &str) {
+    let cheater_id = i64::from_str_radix(cheater_id, 16).unwrap();
+    let conn = Connection::open(DB_NAME).unwrap();
+
+    let mut log_stmt = conn
+        .prepare(
+            "SELECT log_id, timestamp, action, moderator FROM cheater_log
+            WHERE id = ? AND action IN ('mark', 'unmark')
+            ORDER BY log_id",
+        )
+        .unwrap();
+    let mut offset_stmt = conn
+        .prepare(
+            "SELECT cheater_offsets.id, name, char_id, offset
+            FROM cheater_offsets
+            LEFT JOIN players ON players.id = cheater_offsets.id
+            WHERE log_id = ?
+            ORDER BY ABS(offset) DESC",
+        )
+        .unwrap();
+
+    let mut rows = log_stmt.query(params![cheater_id]).unwrap();
+    while let Some(row) = rows.next().unwrap() {
+        let log_id: i64 = row.get(0).unwrap();
+        let timestamp: i64 = row.get(1).unwrap();
+        let action: String = row.get(2).unwrap();
+        let moderator: String = row.get(3).unwrap();
+        println!(
+            "{} by {} at {}",
+            action,
+            moderator,
+            DateTime::from_timestamp(timestamp, 0).unwrap().naive_utc()
+        );
+
+        let mut offsets = offset_stmt.query(params![log_id]).unwrap();
+        while let Some(offset) = offsets.next().unwrap() {
+            let id: i64 = offset.get(0).unwrap();
+            let name: Option<String> = offset.get(1).unwrap();
+            let char_id: usize = offset.get(2).unwrap();
+            let offset: f64 = offset.get(3).unwrap();
+            println!(
+                "| {:X} | {} | {} | {:+.1} |",
+                id,
+                name.unwrap_or_default(),
+                website::CHAR_NAMES[char_id].0,
+                offset
+            );
+        }
+    }
+}
+
+pub fn print_trading_pairs() {
+    let conn = Connection::open(DB_NAME).unwrap();
+
+    println!(
+        "| Winner | Loser | Games | Sets | Record | Expected games | Rating transferred | Status |"
+    );
+    println!(
+        "|--------|-------|-------|------|--------|----------------|--------------------|--------|"
+    );
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT winner_id, winner.name, loser_id, loser.name,
+                games, sets, wins, expected_games, rating_transferred, status
+            FROM trading_pairs
+            LEFT JOIN players AS winner ON winner.id = winner_id
+            LEFT JOIN players AS loser ON loser.id = loser_id
+            WHERE status != 'dismissed'
+            ORDER BY rating_transferred DESC",
+        )
+        .unwrap();
+
+    let mut rows = stmt.query([]).unwrap();
+    while let Some(row) = rows.next().unwrap() {
+        let winner_id: i64 = row.get(0).unwrap();
+        let winner_name: Option<String> = row.get(1).unwrap();
+        let loser_id: i64 = row.get(2).unwrap();
+        let loser_name: Option<String> = row.get(3).unwrap();
+        let games: i64 = row.get(4).unwrap();
+        let sets: i64 = row.get(5).unwrap();
+        let wins: i64 = row.get(6).unwrap();
+        let expected_games: f64 = row.get(7).unwrap();
+        let rating_transferred: f64 = row.get(8).unwrap();
+        let status: String = row.get(9).unwrap();
+        println!(
+            "| {} ({:X}) | {} ({:X}) | {} | {} | {}-{} | {:.2} | {:+.0} | {} |",
+            winner_name.unwrap_or_default(),
+            winner_id,
+            loser_name.unwrap_or_default(),
+            loser_id,
+            games,
+            sets,
+            wins,
+            games - wins,
+            expected_games,
+            rating_transferred,
+            status
+        );
+    }
+}
+
+/// Sets a pair to 'open', 'dismissed' or 'excluded'. Excluding a pair stops their games from
+/// being rated and re-rates both players, as does taking the exclusion back.
+pub fn set_trading_pair_status(winner_id: &str, loser_id: &str, status: &str) {
+    let winner_id = i64::from_str_radix(winner_id, 16).unwrap();
+    let loser_id = i64::from_str_radix(loser_id, 16).unwrap();
+    if !["open", "dismissed", "excluded"].contains(&status) {
+        println!(
+            "Unknown status {}, expected open, dismissed or excluded",
+            status
+        );
+        return;
+    }
+
+    let mut conn = Connection::open(DB_NAME).unwrap();
+    match update_trading_pair_status(&mut conn, winner_id, loser_id, status) {
+        Some(old_status) => println!(
+            "{:X} and {:X}: {} -> {}",
+            winner_id, loser_id, old_status, status
+        ),
+        None => println!(
+            "{:X} and {:X} are not in the trading report",
+            winner_id, loser_id
+        ),
+    }
+}
+
+/// Applies a trading pair status and returns the one it replaced, None if the pair isn't in
+/// the report.
+pub fn update_trading_pair_status(
+    conn: &mut Connection,
+    winner_id: i64,
+    loser_id: i64,
+    status: &str,
+) -> Option<String> {
+    let tx = conn.transaction().unwrap();
+
+    let old_status: String = tx
+        .query_row(
+            "SELECT status FROM trading_pairs WHERE winner_id = ? AND loser_id = ?",
+            params![winner_id, loser_id],
+            |r| r.get(0),
+        )
+        .optional()
+        .unwrap()?;
+
+    tx.execute(
+        "UPDATE trading_pairs SET status = ? WHERE winner_id = ? AND loser_id = ?",
+        params![status, winner_id, loser_id],
+    )
+    .unwrap();
+
+    let mut since = None;
+    if (old_status == "excluded") != (status == "excluded") {
+        let first_games = first_games(
+            &tx,
+            "(id_a = ?1 AND id_b = ?2) OR (id_a = ?2 AND id_b = ?1)",
+            params![winner_id, loser_id],
+        );
+        since = first_games.values().min().copied();
+        rerate_players(&tx, &first_games);
+    }
+
+    tx.commit().unwrap();
+
+    if let Some(since) = since {
+        rebuild_seasons_since(conn, since);
+    }
+
+    Some(old_status)
+}
+
+/// Writes everything stored about a player to a JSON file, for players who ask for their data
+/// without going through the website.
+pub fn export_player_once(id: &str, path: Option<&str>) -> Result<()> {
+    let id = i64::from_str_radix(id, 16)?;
+    let path = path
+        .map(|p| p.to_owned())
+        .unwrap_or_else(|| format!("rating-update-{:X}.json", id));
+
+    let conn = Connection::open(DB_NAME)?;
+    std::fs::write(&path, format!("{:#}", api::export_player(&conn, id)))?;
+    println!("Wrote {}", path);
+    Ok(())
+}
+
+pub async fn update_decay_once() {
+    let mut conn = Connection::open(DB_NAME).unwrap();
+
+    update_decay(&mut conn, Utc::now().timestamp()).unwrap();
+}
+
+pub fn get_average_rating(conn: &Transaction, id: i64) -> f64 {
+    conn.query_row(
+        "select avg(value) from player_ratings where id = ?",
+        params![id],
+        |r| r.get::<_, Option<f64>>(0),
+    )
+    .unwrap()
+    .unwrap_or_default()
+}
+
+pub async fn pull() {
+    let mut conn = Connection::open(DB_NAME).unwrap();
+
+    grab_games(&mut conn, 100).await.unwrap();
+}
+
+async fn grab_games(conn: &mut Connection, _pages: usize) -> Result<()> {
+    let then = Utc::now();
+    info!("Grabbing replays");
+    let replays = ggst_api::get_replays().await;
+
+    let replays = match replays {
+        Ok(replays) => replays,
+        Err(e) => {
+            error!("Error fetching replays: {e}");
+            return Ok(());
+        }
+    };
+
+    let old_count: i64 = conn.query_row("SELECT COUNT(*) FROM games", [], |r| r.get(0))?;
+
+    let tx = conn.transaction()?;
+
+    let mut new_games = Vec::new();
+
+    let num_replays = replays.len();
+    for r in replays {
+        new_games.extend(add_game(&tx, r));
+    }
+    tx.commit()?;
+
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM games", [], |r| r.get(0))?;
+
+    let elapsed = (Utc::now() - then).num_milliseconds();
+
+    info!(
+        "Grabbed {} games -  new games: {} ({} total) - {}ms",
+        num_replays,
+        count - old_count,
+        count,
+        elapsed,
+    );
+
+    assert_eq!(count - old_count, new_games.len() as i64);
+
+    update_ratings(conn, Some(new_games));
+
+    if count - old_count == num_replays as i64 {
+        if num_replays > 0 {
+            error!("Only new replays! We're probably missing some, try increasing the page count.");
+        } else {
+            error!("No replays! Maybe servers are down?");
+        }
+    } else if count - old_count > num_replays as i64 / 2 {
+        warn!("Over half the grabbed replays are new, consider increasing page count.");
+    }
+
+    Ok(())
+}
+
+fn add_game(conn: &Transaction, game: responses::Replay) -> Option<Game> {
+    //2023-01-30 01:52:15"
+    let responses::Replay {
         timestamp,
         player1,
         player1_character,
@@ -462,7 +1850,7 @@ fn add_game(conn: &Transaction, game: responses::Replay) -> Option<Game> {
             game_floor
         ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
             params![
-                timestamp.timestamp(),
+                timestamp.and_utc().timestamp(),
                 player1.id,
                 player1.name,
                 player1_character,
@@ -479,7 +1867,7 @@ fn add_game(conn: &Transaction, game: responses::Replay) -> Option<Game> {
 
     if count == 1 {
         Some(Game {
-            timestamp: timestamp.timestamp(),
+            timestamp: timestamp.and_utc().timestamp(),
             id_a: player1.id.parse().unwrap(),
             char_a: player1_character,
             platform_a: player1.platform,
@@ -501,7 +1889,14 @@ fn add_game(conn: &Transaction, game: responses::Replay) -> Option<Game> {
     //sort the list by date
 }
 
-fn update_player(conn: &Transaction, id: i64, name: &str, floor: i64, platform: i64) {
+fn update_player(
+    conn: &Transaction,
+    id: i64,
+    name: &str,
+    floor: i64,
+    platform: i64,
+    timestamp: i64,
+) {
     if let Err(e) = conn.execute(
         "REPLACE INTO players(id, name, floor, platform) VALUES(?, ?, ?, ?)",
         params![id, name, floor, platform],
@@ -509,11 +1904,24 @@ fn update_player(conn: &Transaction, id: i64, name: &str, floor: i64, platform:
         warn!("{}", e);
     }
 
-    if let Err(e) = conn.execute(
-        "INSERT OR IGNORE INTO player_names(id, name) VALUES(?, ?)",
-        params![id, name],
+    match conn.execute(
+        "INSERT OR IGNORE INTO player_names(id, name, first_seen, last_seen) VALUES(?1, ?2, ?3, ?3)",
+        params![id, name, timestamp],
     ) {
-        warn!("{}", e);
+        Ok(0) => {
+            if let Err(e) = conn.execute(
+                "UPDATE player_names SET
+                    first_seen = CASE first_seen WHEN 0 THEN ?3 ELSE MIN(first_seen, ?3) END,
+                    last_seen = MAX(last_seen, ?3)
+                WHERE id = ?1 AND name = ?2",
+                params![id, name, timestamp],
+            ) {
+                warn!("{}", e);
+            }
+        }
+        //A name we haven't seen for this player before
+        Ok(_) => search::index_name(conn, id, name),
+        Err(e) => warn!("{}", e),
     }
 }
 
@@ -669,32 +2077,8 @@ fn update_ratings(conn: &mut Connection, games: Option<Vec<Game>>) -> i64 {
     //Fetch all the players in the games
     let mut players = FxHashMap::default();
     for g in &games {
-        if !players.contains_key(&(g.id_a, g.char_a)) {
-            players.insert(
-                (g.id_a, g.char_a),
-                tx.query_row(
-                    "SELECT 
-                        player_ratings.id, player_ratings.char_id, wins, losses, value, deviation, last_decay,
-                        top_rating_value, top_rating_deviation, top_rating_timestamp,
-                        top_defeated_id, top_defeated_char_id, top_defeated_name,
-                        top_defeated_value, top_defeated_deviation, top_defeated_floor,
-                        top_defeated_timestamp, character_rank
-                    FROM player_ratings LEFT JOIN ranking_character 
-                    ON 
-                        player_ratings.id = ranking_character.id AND 
-                        player_ratings.char_id = ranking_character.char_id
-                    WHERE player_ratings.id = ? AND player_ratings.char_id = ?",
-                    params![g.id_a, g.char_a],
-                    |r| Ok(RatedPlayer::from_row(r)),
-                )
-                .optional()
-                .unwrap()
-                .unwrap_or(RatedPlayer::new(g.id_a, g.char_a, g.timestamp)),
-            );
-        }
-        if !players.contains_key(&(g.id_b, g.char_b)) {
-            players.insert(
-                (g.id_b, g.char_b),
+        for (id, char_id) in [(g.id_a, g.char_a), (g.id_b, g.char_b)] {
+            players.entry((id, char_id)).or_insert_with(|| {
                 tx.query_row(
                     "SELECT 
                         player_ratings.id, player_ratings.char_id, wins, losses, value, deviation, last_decay,
@@ -707,76 +2091,32 @@ fn update_ratings(conn: &mut Connection, games: Option<Vec<Game>>) -> i64 {
                         player_ratings.id = ranking_character.id AND 
                         player_ratings.char_id = ranking_character.char_id
                     WHERE player_ratings.id = ? AND player_ratings.char_id = ?",
-                    params![g.id_b, g.char_b],
+                    params![id, char_id],
                     |r| Ok(RatedPlayer::from_row(r)),
                 )
                 .optional()
                 .unwrap()
-                .unwrap_or(RatedPlayer::new(g.id_b, g.char_b, g.timestamp)),
-            );
+                .unwrap_or_else(|| RatedPlayer::new(id, char_id, g.timestamp))
+            });
         }
     }
 
     info!("Fetched {} players", players.len());
 
     //fetch all our known cheaters
-    let cheaters = {
-        let mut cheaters = FxHashSet::<i64>::default();
-
-        let mut stmt = tx
-            .prepare(
-                "SELECT
-                    id
-                FROM cheater_status",
-            )
-            .unwrap();
-        let mut rows = stmt.query([]).unwrap();
-
-        while let Some(row) = rows.next().unwrap() {
-            cheaters.insert(row.get(0).unwrap());
-        }
-        cheaters
-    };
-
-    let hidden = {
-        let mut hidden = FxHashSet::<i64>::default();
-
-        let mut stmt = tx
-            .prepare(
-                "SELECT
-                    id
-                FROM hidden_status
-                WHERE hidden_status='enabled'
-                ",
-            )
-            .unwrap();
-        let mut rows = stmt.query([]).unwrap();
-
-        while let Some(row) = rows.next().unwrap() {
-            hidden.insert(row.get(0).unwrap());
-        }
-        hidden
-    };
+    let cheaters = get_cheater_ids(&tx);
+    let traded = get_excluded_pairs(&tx);
+    let hidden = get_hidden_ids(&tx);
 
     let mut counter = 0;
 
     //let mut last_timestamp = 0;
 
-    let popularities = {
-        let mut stmt = tx
-            .prepare("SELECT char_id, popularity FROM character_popularity_global")
-            .unwrap();
-
-        let mut rows = stmt.query([]).unwrap();
-
-        let mut popularities = FxHashMap::<i64, f64>::default();
-
-        while let Some(row) = rows.next().unwrap() {
-            popularities.insert(row.get(0).unwrap(), row.get(1).unwrap());
-        }
+    let popularities = get_global_popularities(&tx);
 
-        popularities
-    };
+    let matchup_tiers = get_matchup_tiers(&tx);
+    let seasons = get_seasons(&tx);
+    let mut season_players = FxHashMap::default();
 
     for g in games {
         //This fails and I don't know why
@@ -788,10 +2128,27 @@ fn update_ratings(conn: &mut Connection, games: Option<Vec<Game>>) -> i64 {
             info!("On game {}...", counter);
         }
 
-        update_player(&tx, g.id_a, &g.name_a, g.game_floor, g.platform_a);
-        update_player(&tx, g.id_b, &g.name_b, g.game_floor, g.platform_b);
+        update_player(
+            &tx,
+            g.id_a,
+            &g.name_a,
+            g.game_floor,
+            g.platform_a,
+            g.timestamp,
+        );
+        update_player(
+            &tx,
+            g.id_b,
+            &g.name_b,
+            g.game_floor,
+            g.platform_b,
+            g.timestamp,
+        );
 
-        let has_cheater = cheaters.contains(&g.id_a) || cheaters.contains(&g.id_b);
+        //Games between a pair excluded for win trading are thrown out like games with cheaters
+        let has_cheater = cheaters.contains(&g.id_a)
+            || cheaters.contains(&g.id_b)
+            || traded.contains(&(g.id_a.min(g.id_b), g.id_a.max(g.id_b)));
         let has_hidden = hidden.contains(&g.id_a) || hidden.contains(&g.id_b);
 
         players
@@ -823,16 +2180,7 @@ fn update_ratings(conn: &mut Connection, games: Option<Vec<Game>>) -> i64 {
         let loser_rank = players.get(&loser).unwrap().character_rank.unwrap_or(99999);
         let loser_char = players.get(&loser).unwrap().char_id;
 
-        let expected_outcome = winner_rating.expected(loser_rating);
-
-        const MARGIN: f64 = 0.045;
-        let rsm_deviation = (0.5 * winner_rating.deviation.powf(2.0)
-            + 0.5 * loser_rating.deviation.powf(2.0))
-        .sqrt();
-        let valid = ((expected_outcome > MARGIN && expected_outcome < 1.0 - MARGIN)
-            || rsm_deviation >= 50.0)
-            && !has_cheater
-            && !has_hidden;
+        let valid = within_margin(winner_rating, loser_rating) && !has_cheater && !has_hidden;
 
         if !has_cheater && !has_hidden {
             //Update top rating and top defeated
@@ -860,98 +2208,29 @@ fn update_ratings(conn: &mut Connection, games: Option<Vec<Game>>) -> i64 {
                 .update_top_rating(g.timestamp);
         }
 
-        if valid {
-            //Update ratings
-            players.get_mut(&winner).unwrap().rating = winner_rating.update(loser_rating, 1.0);
-            players.get_mut(&winner).unwrap().win_count += 1;
-
-            players.get_mut(&loser).unwrap().rating = loser_rating.update(winner_rating, 0.0);
-            players.get_mut(&loser).unwrap().loss_count += 1;
-
-            //Update player matchups
-            fn update_player_matchup(
-                tx: &Transaction,
-                player_id: i64,
-                char_id: i64,
-                player_rating: Rating,
-                opp_char_id: i64,
-                opp_rating: Rating,
-                result: f64,
-                game_timestamp: i64,
-            ) {
-                tx.execute(
-                    "INSERT OR IGNORE INTO player_matchups VALUES(?, ?, ?, ?, 350.0, ?, 0, 0)",
-                    params![
-                        player_id,
-                        char_id,
-                        opp_char_id,
-                        player_rating.value,
-                        game_timestamp
-                    ],
-                )
-                .unwrap();
-
-                let (value, deviation, mut last_decay_timestamp, mut wins, mut losses): (
-                    f64,
-                    f64,
-                    i64,
-                    i64,
-                    i64,
-                ) = tx
-                    .query_row(
-                        "SELECT 
-                            rating_value, 
-                            rating_deviation, 
-                            rating_timestamp, 
-                            wins,
-                            losses
-                        FROM player_matchups
-                        WHERE id=? AND char_id=? AND opp_char_id=?",
-                        params![player_id, char_id, opp_char_id],
-                        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?)),
-                    )
-                    .unwrap();
-
-                let mut rating = Rating::new(value, deviation);
-
-                rating = rating.update(opp_rating, result);
-
-                while last_decay_timestamp + RATING_PERIOD < game_timestamp {
-                    rating.decay_deviation(1, DECAY_CONSTANT);
-                    last_decay_timestamp += RATING_PERIOD;
-                }
-
-                if result == 1.0 {
-                    wins += 1;
-                } else {
-                    losses += 1;
-                }
+        if valid {
+            //Update ratings
+            players.get_mut(&winner).unwrap().rating = winner_rating.update(loser_rating, 1.0);
+            players.get_mut(&winner).unwrap().win_count += 1;
 
-                let Rating { value, deviation } = rating;
+            players.get_mut(&loser).unwrap().rating = loser_rating.update(winner_rating, 0.0);
+            players.get_mut(&loser).unwrap().loss_count += 1;
 
-                tx.execute(
-                    "UPDATE player_matchups
-                SET 
-                    rating_value = ?,
-                    rating_deviation = ?,
-                    rating_timestamp = ?,
-                    wins = ?,
-                    losses = ?
-                WHERE id=? AND char_id=? AND opp_char_id=?",
-                    params![
-                        value,
-                        deviation,
-                        last_decay_timestamp,
-                        wins,
-                        losses,
-                        player_id,
-                        char_id,
-                        opp_char_id
-                    ],
-                )
-                .unwrap();
+            if let Some(season) = seasons.iter().find(|s| s.contains(g.timestamp)) {
+                update_season_ratings(
+                    &tx,
+                    season,
+                    &mut season_players,
+                    g.timestamp,
+                    winner,
+                    loser,
+                    winner_rating,
+                    loser_rating,
+                );
+                update_season_matchup(&tx, season.season_id, winner.1, loser.1);
             }
 
+            //Update player matchups
             update_player_matchup(
                 &tx,
                 winner.0,
@@ -973,134 +2252,30 @@ fn update_ratings(conn: &mut Connection, games: Option<Vec<Game>>) -> i64 {
                 g.timestamp,
             );
 
-            fn update_global_matchup(
-                tx: &Transaction,
-                table: &str,
-                winner_char: i64,
-                loser_char: i64,
-            ) {
-                tx.execute(
-                    &format!(
-                        "INSERT OR IGNORE INTO {} VALUES(?, ?, 1500.0, 350.0, 0, 0)",
-                        table
-                    ),
-                    params![winner_char, loser_char,],
-                )
-                .unwrap();
-                tx.execute(
-                    &format!(
-                        "INSERT OR IGNORE INTO {} VALUES(?, ?, 1500.0, 350.0, 0, 0)",
-                        table
-                    ),
-                    params![loser_char, winner_char],
-                )
-                .unwrap();
-
-                let (winner_value, winner_deviation, mut winner_wins): (f64, f64, i64) = tx
-                    .query_row(
-                        &format!(
-                            "SELECT 
-                        rating_value, rating_deviation, wins
-                    FROM {}
-                    WHERE char_id = ? AND opp_char_id = ?",
-                            table
-                        ),
-                        params![winner_char, loser_char],
-                        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
-                    )
-                    .unwrap();
-
-                let (loser_value, loser_deviation, mut loser_losses): (f64, f64, i64) = tx
-                    .query_row(
-                        &format!(
-                            "SELECT 
-                        rating_value, rating_deviation, losses
-                    FROM {}
-                    WHERE char_id = ? AND opp_char_id  = ?",
-                            table
-                        ),
-                        params![loser_char, winner_char],
-                        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
-                    )
-                    .unwrap();
-
-                let new_winner = Rating::new(winner_value, winner_deviation).update_with_min_dev(
-                    Rating::new(loser_value, loser_deviation),
-                    1.0,
-                    5.0,
-                );
-                let new_loser = Rating::new(loser_value, loser_deviation).update_with_min_dev(
-                    Rating::new(winner_value, winner_deviation),
-                    0.0,
-                    5.0,
-                );
-
-                winner_wins += 1;
-                loser_losses += 1;
-
-                tx.execute(
-                    &format!(
-                        "UPDATE {}
-                SET 
-                    rating_value=?,
-                    rating_deviation=?,
-                    wins=?
-                WHERE
-                    char_id = ? AND opp_char_id = ?",
-                        table
-                    ),
-                    params![
-                        new_winner.value,
-                        new_winner.deviation,
-                        winner_wins,
-                        winner_char,
-                        loser_char,
-                    ],
-                )
-                .unwrap();
-
-                tx.execute(
-                    &format!(
-                        "UPDATE {}
-                SET 
-                    rating_value=?,
-                    rating_deviation=?,
-                    losses=?
-                WHERE
-                    char_id = ? AND opp_char_id = ?",
-                        table
-                    ),
-                    params![
-                        new_loser.value,
-                        new_loser.deviation,
-                        loser_losses,
-                        loser_char,
-                        winner_char,
-                    ],
-                )
-                .unwrap();
-            }
-
-            update_global_matchup(&tx, "global_matchups", winner.1, loser.1);
-            if winner_rank <= 100 && loser_rank <= 100 {
-                update_global_matchup(&tx, "top_100_matchups", winner.1, loser.1);
-            }
-            if winner_rank <= 1000 && loser_rank <= 1000 {
-                update_global_matchup(&tx, "top_1000_matchups", winner.1, loser.1);
-            }
-            if winner_rank as f64 <= popularities.get(&winner_char).unwrap_or(&0.0) * 1000.0
-                && loser_rank as f64 <= popularities.get(&loser_char).unwrap_or(&0.0) * 1000.0
-            {
-                update_global_matchup(&tx, "proportional_matchups", winner.1, loser.1);
+            for tier in &matchup_tiers {
+                if tier.includes(
+                    winner_rank,
+                    winner_rating,
+                    *popularities.get(&winner_char).unwrap_or(&0.0),
+                    g.game_floor,
+                ) && tier.includes(
+                    loser_rank,
+                    loser_rating,
+                    *popularities.get(&loser_char).unwrap_or(&0.0),
+                    g.game_floor,
+                ) {
+                    update_global_matchup(&tx, tier.tier_id, winner.1, loser.1);
+                }
             }
 
             //Update daily ratings
             {
-                let day_timestamp = NaiveDateTime::from_timestamp_opt(g.timestamp, 0)
+                let day_timestamp = DateTime::from_timestamp(g.timestamp, 0)
                     .unwrap()
-                    .date()
+                    .date_naive()
                     .and_hms_opt(0, 0, 0)
                     .unwrap()
+                    .and_utc()
                     .timestamp();
 
                 let winner_new_rating = players.get(&winner).unwrap().rating;
@@ -1136,8 +2311,12 @@ fn update_ratings(conn: &mut Connection, games: Option<Vec<Game>>) -> i64 {
             }
         }
 
+        let (rank_a, rank_b) = match g.winner {
+            1 => (winner_rank, loser_rank),
+            _ => (loser_rank, winner_rank),
+        };
         tx.execute(
-            "INSERT INTO game_ratings VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            "INSERT INTO game_ratings VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
             params![
                 g.timestamp,
                 g.id_a,
@@ -1148,22 +2327,547 @@ fn update_ratings(conn: &mut Connection, games: Option<Vec<Game>>) -> i64 {
                 old_rating_b.deviation,
                 g.winner,
                 valid,
+                rank_a,
+                rank_b,
             ],
         )
         .unwrap();
     }
 
     for (_, player) in players.into_iter() {
-        if player.rating.deviation < 0.0 {
-            error!("Negative rating deviation???");
-        }
+        save_rated_player(&tx, &player);
+    }
+
+    save_season_players(&tx, season_players);
+
+    tx.commit().unwrap();
+
+    info!(
+        "Calculated ratings - {}ms",
+        (Utc::now() - then).num_milliseconds()
+    );
+
+    remaining
+}
+
+fn get_cheater_ids(tx: &Transaction) -> FxHashSet<i64> {
+    let mut cheaters = FxHashSet::<i64>::default();
+
+    let mut stmt = tx
+        .prepare(
+            "SELECT
+                id
+            FROM cheater_status",
+        )
+        .unwrap();
+    let mut rows = stmt.query([]).unwrap();
+
+    while let Some(row) = rows.next().unwrap() {
+        cheaters.insert(row.get(0).unwrap());
+    }
+    cheaters
+}
+
+fn get_excluded_pairs(tx: &Transaction) -> FxHashSet<(i64, i64)> {
+    let mut pairs = FxHashSet::default();
+
+    let mut stmt = tx
+        .prepare("SELECT winner_id, loser_id FROM trading_pairs WHERE status = 'excluded'")
+        .unwrap();
+    let mut rows = stmt.query([]).unwrap();
+
+    while let Some(row) = rows.next().unwrap() {
+        let (a, b): (i64, i64) = (row.get(0).unwrap(), row.get(1).unwrap());
+        pairs.insert((a.min(b), a.max(b)));
+    }
+    pairs
+}
+
+/// Players whose games are left out of the ratings. Hidden players used to be stored as
+/// 'enabled', migrate_hide_requests.sql turned those rows into 'hidden' when pending codes
+/// moved to `hide_requests`.
+fn get_hidden_ids(tx: &Transaction) -> FxHashSet<i64> {
+    let mut hidden = FxHashSet::<i64>::default();
+
+    let mut stmt = tx
+        .prepare(
+            "SELECT
+                id
+            FROM hidden_status
+            WHERE hidden_status='hidden'
+            ",
+        )
+        .unwrap();
+    let mut rows = stmt.query([]).unwrap();
+
+    while let Some(row) = rows.next().unwrap() {
+        hidden.insert(row.get(0).unwrap());
+    }
+    hidden
+}
+
+fn get_global_popularities(tx: &Transaction) -> FxHashMap<i64, f64> {
+    let mut stmt = tx
+        .prepare("SELECT char_id, popularity FROM character_popularity_global")
+        .unwrap();
+
+    let mut rows = stmt.query([]).unwrap();
+
+    let mut popularities = FxHashMap::<i64, f64>::default();
+
+    while let Some(row) = rows.next().unwrap() {
+        popularities.insert(row.get(0).unwrap(), row.get(1).unwrap());
+    }
+
+    popularities
+}
+
+fn get_character_rank(tx: &Transaction, (id, char_id): (i64, i64)) -> i64 {
+    tx.query_row(
+        "SELECT character_rank FROM ranking_character WHERE id = ? AND char_id = ?",
+        params![id, char_id],
+        |r| r.get(0),
+    )
+    .optional()
+    .unwrap()
+    .unwrap_or(99999)
+}
+
+/// Games that were too lopsided to say anything about either player are left unrated.
+fn within_margin(winner_rating: Rating, loser_rating: Rating) -> bool {
+    const MARGIN: f64 = 0.045;
+    let expected_outcome = winner_rating.expected(loser_rating);
+    let rsm_deviation =
+        (0.5 * winner_rating.deviation.powf(2.0) + 0.5 * loser_rating.deviation.powf(2.0)).sqrt();
+    (expected_outcome > MARGIN && expected_outcome < 1.0 - MARGIN) || rsm_deviation >= 50.0
+}
+
+fn save_rated_player(tx: &Transaction, player: &RatedPlayer) {
+    if player.rating.deviation < 0.0 {
+        error!("Negative rating deviation???");
+    }
+
+    tx.execute(
+        "REPLACE INTO player_ratings VALUES(
+            ?, ?, ?, ?, ?, ?, ?,
+            ?, ?, ?, 
+            ?, ?, ?, ?, ?, ?, ?)",
+        params![
+            player.id,
+            player.char_id,
+            player.win_count,
+            player.loss_count,
+            player.rating.value,
+            player.rating.deviation,
+            player.last_decay,
+            //
+            player.top_rating.as_ref().map(|r| r.value),
+            player.top_rating.as_ref().map(|r| r.deviation),
+            player.top_rating.as_ref().map(|r| r.timestamp),
+            //
+            player.top_defeated.as_ref().map(|t| t.id),
+            player.top_defeated.as_ref().map(|t| t.char_id),
+            player.top_defeated.as_ref().map(|t| t.name.clone()),
+            player.top_defeated.as_ref().map(|t| t.value),
+            player.top_defeated.as_ref().map(|t| t.deviation),
+            player.top_defeated.as_ref().map(|t| t.floor),
+            player.top_defeated.as_ref().map(|t| t.timestamp),
+        ],
+    )
+    .unwrap();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_player_matchup(
+    tx: &Transaction,
+    player_id: i64,
+    char_id: i64,
+    player_rating: Rating,
+    opp_char_id: i64,
+    opp_rating: Rating,
+    result: f64,
+    game_timestamp: i64,
+) {
+    tx.execute(
+        "INSERT OR IGNORE INTO player_matchups VALUES(?, ?, ?, ?, 350.0, ?, 0, 0)",
+        params![
+            player_id,
+            char_id,
+            opp_char_id,
+            player_rating.value,
+            game_timestamp
+        ],
+    )
+    .unwrap();
+
+    let (value, deviation, mut last_decay_timestamp, mut wins, mut losses): (
+        f64,
+        f64,
+        i64,
+        i64,
+        i64,
+    ) = tx
+        .query_row(
+            "SELECT 
+                rating_value, 
+                rating_deviation, 
+                rating_timestamp, 
+                wins,
+                losses
+            FROM player_matchups
+            WHERE id=? AND char_id=? AND opp_char_id=?",
+            params![player_id, char_id, opp_char_id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?)),
+        )
+        .unwrap();
+
+    let mut rating = Rating::new(value, deviation);
+
+    rating = rating.update(opp_rating, result);
+
+    while last_decay_timestamp + RATING_PERIOD < game_timestamp {
+        rating.decay_deviation(1, DECAY_CONSTANT);
+        last_decay_timestamp += RATING_PERIOD;
+    }
+
+    if result == 1.0 {
+        wins += 1;
+    } else {
+        losses += 1;
+    }
+
+    let Rating { value, deviation } = rating;
+
+    tx.execute(
+        "UPDATE player_matchups
+    SET 
+        rating_value = ?,
+        rating_deviation = ?,
+        rating_timestamp = ?,
+        wins = ?,
+        losses = ?
+    WHERE id=? AND char_id=? AND opp_char_id=?",
+        params![
+            value,
+            deviation,
+            last_decay_timestamp,
+            wins,
+            losses,
+            player_id,
+            char_id,
+            opp_char_id
+        ],
+    )
+    .unwrap();
+}
+
+fn update_global_matchup(tx: &Transaction, tier_id: i64, winner_char: i64, loser_char: i64) {
+    tx.execute(
+        "INSERT OR IGNORE INTO matchups VALUES(?, ?, ?, 1500.0, 350.0, 0, 0)",
+        params![tier_id, winner_char, loser_char],
+    )
+    .unwrap();
+    tx.execute(
+        "INSERT OR IGNORE INTO matchups VALUES(?, ?, ?, 1500.0, 350.0, 0, 0)",
+        params![tier_id, loser_char, winner_char],
+    )
+    .unwrap();
+
+    let (winner_value, winner_deviation, mut winner_wins): (f64, f64, i64) = tx
+        .query_row(
+            "SELECT 
+            rating_value, rating_deviation, wins
+        FROM matchups
+        WHERE tier_id = ? AND char_id = ? AND opp_char_id = ?",
+            params![tier_id, winner_char, loser_char],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .unwrap();
+
+    let (loser_value, loser_deviation, mut loser_losses): (f64, f64, i64) = tx
+        .query_row(
+            "SELECT 
+            rating_value, rating_deviation, losses
+        FROM matchups
+        WHERE tier_id = ? AND char_id = ? AND opp_char_id  = ?",
+            params![tier_id, loser_char, winner_char],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .unwrap();
+
+    let new_winner = Rating::new(winner_value, winner_deviation).update_with_min_dev(
+        Rating::new(loser_value, loser_deviation),
+        1.0,
+        5.0,
+    );
+    let new_loser = Rating::new(loser_value, loser_deviation).update_with_min_dev(
+        Rating::new(winner_value, winner_deviation),
+        0.0,
+        5.0,
+    );
+
+    winner_wins += 1;
+    loser_losses += 1;
+
+    tx.execute(
+        "UPDATE matchups
+    SET 
+        rating_value=?,
+        rating_deviation=?,
+        wins=?
+    WHERE
+        tier_id = ? AND char_id = ? AND opp_char_id = ?",
+        params![
+            new_winner.value,
+            new_winner.deviation,
+            winner_wins,
+            tier_id,
+            winner_char,
+            loser_char,
+        ],
+    )
+    .unwrap();
+
+    tx.execute(
+        "UPDATE matchups
+    SET 
+        rating_value=?,
+        rating_deviation=?,
+        losses=?
+    WHERE
+        tier_id = ? AND char_id = ? AND opp_char_id = ?",
+        params![
+            new_loser.value,
+            new_loser.deviation,
+            loser_losses,
+            tier_id,
+            loser_char,
+            winner_char,
+        ],
+    )
+    .unwrap();
+}
+
+/// Takes a game back out of a matchup tier. Glicko updates can't be undone exactly, so this
+/// moves the ratings back by what the game would be worth now and leaves the deviations be.
+fn remove_global_matchup(tx: &Transaction, tier_id: i64, winner_char: i64, loser_char: i64) {
+    let winner: Option<(f64, f64)> = tx
+        .query_row(
+            "SELECT rating_value, rating_deviation FROM matchups
+            WHERE tier_id = ? AND char_id = ? AND opp_char_id = ?",
+            params![tier_id, winner_char, loser_char],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .optional()
+        .unwrap();
+    let loser: Option<(f64, f64)> = tx
+        .query_row(
+            "SELECT rating_value, rating_deviation FROM matchups
+            WHERE tier_id = ? AND char_id = ? AND opp_char_id = ?",
+            params![tier_id, loser_char, winner_char],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .optional()
+        .unwrap();
+
+    if let (Some(winner), Some(loser)) = (winner, loser) {
+        let winner = Rating::new(winner.0, winner.1);
+        let loser = Rating::new(loser.0, loser.1);
+
+        tx.execute(
+            "UPDATE matchups
+            SET rating_value = ?, wins = max(wins - 1, 0)
+            WHERE tier_id = ? AND char_id = ? AND opp_char_id = ?",
+            params![
+                winner.value - winner.rating_change(loser, 1.0),
+                tier_id,
+                winner_char,
+                loser_char
+            ],
+        )
+        .unwrap();
+        tx.execute(
+            "UPDATE matchups
+            SET rating_value = ?, losses = max(losses - 1, 0)
+            WHERE tier_id = ? AND char_id = ? AND opp_char_id = ?",
+            params![
+                loser.value - loser.rating_change(winner, 0.0),
+                tier_id,
+                loser_char,
+                winner_char
+            ],
+        )
+        .unwrap();
+    }
+}
+
+pub fn get_seasons(conn: &Connection) -> Vec<Season> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT season_id, name, start_timestamp, end_timestamp, soft_reset
+            FROM seasons
+            ORDER BY start_timestamp ASC",
+        )
+        .unwrap();
+
+    let mut rows = stmt.query([]).unwrap();
+    let mut seasons = Vec::new();
+    while let Some(row) = rows.next().unwrap() {
+        seasons.push(Season::from_row(row));
+    }
+    seasons
+}
+
+pub fn get_matchup_tiers(conn: &Connection) -> Vec<MatchupTier> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT tier_id, name, description, max_rank, proportional, min_rating, max_rating, min_floor
+            FROM matchup_tiers
+            ORDER BY tier_id ASC",
+        )
+        .unwrap();
+
+    let mut rows = stmt.query([]).unwrap();
+    let mut tiers = Vec::new();
+    while let Some(row) = rows.next().unwrap() {
+        tiers.push(MatchupTier::from_row(row));
+    }
+    tiers
+}
+
+/// Applies a game to the season ratings. Players entering the season are seeded from their
+/// lifetime rating before the game according to the season's reset policy.
+#[allow(clippy::too_many_arguments)]
+fn update_season_ratings(
+    tx: &Transaction,
+    season: &Season,
+    season_players: &mut FxHashMap<(i64, i64, i64), RatedPlayer>,
+    timestamp: i64,
+    winner: (i64, i64),
+    loser: (i64, i64),
+    winner_lifetime_rating: Rating,
+    loser_lifetime_rating: Rating,
+) {
+    for ((id, char_id), lifetime_rating) in [
+        (winner, winner_lifetime_rating),
+        (loser, loser_lifetime_rating),
+    ] {
+        season_players
+            .entry((season.season_id, id, char_id))
+            .or_insert_with(|| {
+                tx.query_row(
+                    "SELECT id, char_id, wins, losses, value, deviation, last_decay
+                    FROM season_player_ratings
+                    WHERE season_id = ? AND id = ? AND char_id = ?",
+                    params![season.season_id, id, char_id],
+                    |r| Ok(RatedPlayer::from_row(r)),
+                )
+                .optional()
+                .unwrap()
+                .unwrap_or_else(|| RatedPlayer {
+                    rating: season.initial_rating(lifetime_rating),
+                    ..RatedPlayer::new(id, char_id, timestamp)
+                })
+            })
+            .decay(timestamp);
+    }
+
+    let winner_key = (season.season_id, winner.0, winner.1);
+    let loser_key = (season.season_id, loser.0, loser.1);
+    let winner_rating = season_players.get(&winner_key).unwrap().rating;
+    let loser_rating = season_players.get(&loser_key).unwrap().rating;
+
+    let winner = season_players.get_mut(&winner_key).unwrap();
+    winner.rating = winner_rating.update(loser_rating, 1.0);
+    winner.win_count += 1;
+
+    let loser = season_players.get_mut(&loser_key).unwrap();
+    loser.rating = loser_rating.update(winner_rating, 0.0);
+    loser.loss_count += 1;
+}
+
+/// Same as the global matchup tables, except that each season keeps its own ratings which
+/// never decay, so the result reflects only the games played on that patch.
+fn update_season_matchup(tx: &Transaction, season_id: i64, winner_char: i64, loser_char: i64) {
+    tx.execute(
+        "INSERT OR IGNORE INTO season_matchups VALUES(?, ?, ?, 1500.0, 350.0, 0, 0)",
+        params![season_id, winner_char, loser_char],
+    )
+    .unwrap();
+    tx.execute(
+        "INSERT OR IGNORE INTO season_matchups VALUES(?, ?, ?, 1500.0, 350.0, 0, 0)",
+        params![season_id, loser_char, winner_char],
+    )
+    .unwrap();
+
+    let (winner_value, winner_deviation, winner_wins): (f64, f64, i64) = tx
+        .query_row(
+            "SELECT rating_value, rating_deviation, wins
+            FROM season_matchups
+            WHERE season_id = ? AND char_id = ? AND opp_char_id = ?",
+            params![season_id, winner_char, loser_char],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .unwrap();
+
+    let (loser_value, loser_deviation, loser_losses): (f64, f64, i64) = tx
+        .query_row(
+            "SELECT rating_value, rating_deviation, losses
+            FROM season_matchups
+            WHERE season_id = ? AND char_id = ? AND opp_char_id = ?",
+            params![season_id, loser_char, winner_char],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .unwrap();
+
+    let new_winner = Rating::new(winner_value, winner_deviation).update_with_min_dev(
+        Rating::new(loser_value, loser_deviation),
+        1.0,
+        5.0,
+    );
+    let new_loser = Rating::new(loser_value, loser_deviation).update_with_min_dev(
+        Rating::new(winner_value, winner_deviation),
+        0.0,
+        5.0,
+    );
+
+    tx.execute(
+        "UPDATE season_matchups
+        SET rating_value = ?, rating_deviation = ?, wins = ?
+        WHERE season_id = ? AND char_id = ? AND opp_char_id = ?",
+        params![
+            new_winner.value,
+            new_winner.deviation,
+            winner_wins + 1,
+            season_id,
+            winner_char,
+            loser_char,
+        ],
+    )
+    .unwrap();
+
+    tx.execute(
+        "UPDATE season_matchups
+        SET rating_value = ?, rating_deviation = ?, losses = ?
+        WHERE season_id = ? AND char_id = ? AND opp_char_id = ?",
+        params![
+            new_loser.value,
+            new_loser.deviation,
+            loser_losses + 1,
+            season_id,
+            loser_char,
+            winner_char,
+        ],
+    )
+    .unwrap();
+}
 
+fn save_season_players(tx: &Transaction, season_players: FxHashMap<(i64, i64, i64), RatedPlayer>) {
+    for ((season_id, _, _), player) in season_players {
         tx.execute(
-            "REPLACE INTO player_ratings VALUES(
-                ?, ?, ?, ?, ?, ?, ?,
-                ?, ?, ?, 
-                ?, ?, ?, ?, ?, ?, ?)",
+            "REPLACE INTO season_player_ratings VALUES(?, ?, ?, ?, ?, ?, ?, ?)",
             params![
+                season_id,
                 player.id,
                 player.char_id,
                 player.win_count,
@@ -1171,31 +2875,10 @@ fn update_ratings(conn: &mut Connection, games: Option<Vec<Game>>) -> i64 {
                 player.rating.value,
                 player.rating.deviation,
                 player.last_decay,
-                //
-                player.top_rating.as_ref().map(|r| r.value),
-                player.top_rating.as_ref().map(|r| r.deviation),
-                player.top_rating.as_ref().map(|r| r.timestamp),
-                //
-                player.top_defeated.as_ref().map(|t| t.id),
-                player.top_defeated.as_ref().map(|t| t.char_id),
-                player.top_defeated.as_ref().map(|t| t.name.clone()),
-                player.top_defeated.as_ref().map(|t| t.value),
-                player.top_defeated.as_ref().map(|t| t.deviation),
-                player.top_defeated.as_ref().map(|t| t.floor),
-                player.top_defeated.as_ref().map(|t| t.timestamp),
             ],
         )
         .unwrap();
     }
-
-    tx.commit().unwrap();
-
-    info!(
-        "Calculated ratings - {}ms",
-        (Utc::now() - then).num_milliseconds()
-    );
-
-    remaining
 }
 
 pub fn calc_character_popularity(conn: &mut Connection, last_timestamp: i64) -> Result<()> {
@@ -1260,12 +2943,7 @@ pub fn calc_character_popularity(conn: &mut Connection, last_timestamp: i64) ->
     }
 
     for r in 0..POP_RATING_BRACKETS {
-        let rating_min = if r > 0 { (900 + r * 100) as f64 } else { -99.0 };
-        let rating_max = if r < POP_RATING_BRACKETS - 1 {
-            (1000 + (r + 1) * 100) as f64
-        } else {
-            3000.0
-        };
+        let (rating_min, rating_max) = rating_bracket_range(r);
 
         let rating_game_count: f64 = tx.query_row(
             "SELECT
@@ -1429,6 +3107,39 @@ pub fn update_decay(conn: &mut Connection, timestamp: i64) -> Result<()> {
         )?;
     }
 
+    let current_season = get_seasons(&tx).into_iter().find(|s| s.contains(timestamp));
+    if let Some(season) = current_season {
+        let mut season_players = Vec::new();
+        {
+            let mut stmt = tx.prepare(
+                "SELECT id, char_id, wins, losses, value, deviation, last_decay
+                FROM season_player_ratings
+                WHERE season_id = ? AND deviation < 350.0",
+            )?;
+            let mut rows = stmt.query(params![season.season_id])?;
+
+            while let Some(row) = rows.next()? {
+                season_players.push(RatedPlayer::from_row(row));
+            }
+        }
+
+        for player in &mut season_players {
+            player.decay(timestamp);
+            tx.execute(
+                "UPDATE season_player_ratings SET
+                deviation = ?, last_decay = ? WHERE
+                season_id = ? AND id = ? AND char_id = ?",
+                params![
+                    player.rating.deviation,
+                    player.last_decay,
+                    season.season_id,
+                    player.id,
+                    player.char_id,
+                ],
+            )?;
+        }
+    }
+
     tx.commit()?;
     info!(
         "Updated decay - {}ms",
@@ -1468,44 +3179,7 @@ fn decay_matchups(conn: &mut Connection, _timestamp: i64) -> Result<()> {
     //)?;
 
     tx.execute(
-        "UPDATE global_matchups
-        SET rating_deviation = min(
-                 :initial_deviation, 
-                 sqrt(rating_deviation * rating_deviation + :c * :c))
-        WHERE 
-            rating_deviation < :initial_deviation",
-        named_params! {
-            ":initial_deviation": glicko::INITIAL_DEVIATION,
-            ":c": DECAY_CONSTANT,
-        },
-    )?;
-
-    tx.execute(
-        "UPDATE top_1000_matchups
-        SET rating_deviation = min(
-                 :initial_deviation, 
-                 sqrt(rating_deviation * rating_deviation + :c * :c))
-        WHERE 
-            rating_deviation < :initial_deviation",
-        named_params! {
-            ":initial_deviation": glicko::INITIAL_DEVIATION,
-            ":c": DECAY_CONSTANT,
-        },
-    )?;
-    tx.execute(
-        "UPDATE top_100_matchups
-        SET rating_deviation = min(
-                 :initial_deviation, 
-                 sqrt(rating_deviation * rating_deviation + :c * :c))
-        WHERE 
-            rating_deviation < :initial_deviation",
-        named_params! {
-            ":initial_deviation": glicko::INITIAL_DEVIATION,
-            ":c": DECAY_CONSTANT,
-        },
-    )?;
-    tx.execute(
-        "UPDATE proportional_matchups
+        "UPDATE matchups
         SET rating_deviation = min(
                  :initial_deviation, 
                  sqrt(rating_deviation * rating_deviation + :c * :c))
@@ -1691,16 +3365,645 @@ pub fn calc_fraud_index(conn: &mut Connection) -> Result<()> {
             )
             .unwrap();
         }
-    }
+    }
+
+    tx.commit()?;
+
+    info!(
+        "Updated fraud index - {}ms",
+        (Utc::now() - then).num_milliseconds()
+    );
+
+    Ok(())
+}
+
+/// The ratings a popularity or tier list bracket covers, neighbouring brackets overlap by half.
+fn rating_bracket_range(r: usize) -> (f64, f64) {
+    let rating_min = if r > 0 { (900 + r * 100) as f64 } else { -99.0 };
+    let rating_max = if r < POP_RATING_BRACKETS - 1 {
+        (1000 + (r + 1) * 100) as f64
+    } else {
+        3000.0
+    };
+    (rating_min, rating_max)
+}
+
+/// Fits the advantage of each matchup from its (wins, games) bucketed by rating difference,
+/// giving its games, wins, advantage and standard error.
+fn fit_matchups<K: Eq + Hash>(
+    buckets: FxHashMap<(K, i64), (f64, f64)>,
+) -> FxHashMap<K, (i64, i64, f64, f64)> {
+    let mut samples = FxHashMap::<K, Vec<(f64, f64, f64)>>::default();
+    for ((matchup, bucket), (wins, games)) in buckets {
+        samples
+            .entry(matchup)
+            .or_default()
+            .push((bucket as f64 * 10.0, wins, games));
+    }
+
+    samples
+        .into_iter()
+        .map(|(matchup, samples)| {
+            let (advantage, std_error) = glicko::fit_advantage(&samples);
+            let wins: f64 = samples.iter().map(|s| s.1).sum();
+            let games: f64 = samples.iter().map(|s| s.2).sum();
+            (matchup, (games as i64, wins as i64, advantage, std_error))
+        })
+        .collect()
+}
+
+/// Estimates every matchup from the games of the current season (or all games outside of
+/// seasons), controlling for the ratings of the players involved. Rating differences are
+/// bucketed to 10 points so the fit only has to go over a few hundred samples per matchup.
+/// Each rating bracket gets its own estimates too, from the games where the character's player
+/// was rated within it.
+pub fn calc_matchup_estimates(conn: &mut Connection, timestamp: i64) -> Result<()> {
+    let then = Utc::now();
+    info!("Calculating matchup estimates");
+
+    let tx = conn.transaction()?;
+    let since = get_seasons(&tx)
+        .into_iter()
+        .find(|s| s.contains(timestamp))
+        .map(|s| s.start_timestamp)
+        .unwrap_or(0);
+
+    let mut buckets = FxHashMap::<((i64, i64), i64), (f64, f64)>::default();
+    let mut bracket_buckets = FxHashMap::<((usize, i64, i64), i64), (f64, f64)>::default();
+    {
+        let mut stmt = tx.prepare(
+            "SELECT char_a, value_a, deviation_a, char_b, value_b, deviation_b, winner
+            FROM games NATURAL JOIN game_ratings
+            WHERE timestamp >= ? AND valid AND char_a != char_b
+                AND deviation_a < ? AND deviation_b < ?",
+        )?;
+        let mut rows = stmt.query(params![
+            since,
+            MATCHUP_ESTIMATE_MAX_DEVIATION,
+            MATCHUP_ESTIMATE_MAX_DEVIATION
+        ])?;
+
+        while let Some(row) = rows.next()? {
+            let char_a: i64 = row.get(0)?;
+            let rating_a = Rating::new(row.get(1)?, row.get(2)?);
+            let char_b: i64 = row.get(3)?;
+            let rating_b = Rating::new(row.get(4)?, row.get(5)?);
+            let winner: i64 = row.get(6)?;
+
+            let difference =
+                glicko::g((rating_a.deviation.powf(2.0) + rating_b.deviation.powf(2.0)).sqrt())
+                    * (rating_a.value - rating_b.value);
+            let bucket = (difference / 10.0).round().clamp(-80.0, 80.0) as i64;
+
+            for (char_id, rating, opp_char_id, bucket, won) in [
+                (char_a, rating_a, char_b, bucket, winner == 1),
+                (char_b, rating_b, char_a, -bucket, winner == 2),
+            ] {
+                let won = if won { 1.0 } else { 0.0 };
+                let side = buckets
+                    .entry(((char_id, opp_char_id), bucket))
+                    .or_insert((0.0, 0.0));
+                side.0 += won;
+                side.1 += 1.0;
+
+                for r in 0..POP_RATING_BRACKETS {
+                    let (rating_min, rating_max) = rating_bracket_range(r);
+                    if rating.value >= rating_min && rating.value < rating_max {
+                        let side = bracket_buckets
+                            .entry(((r, char_id, opp_char_id), bucket))
+                            .or_insert((0.0, 0.0));
+                        side.0 += won;
+                        side.1 += 1.0;
+                    }
+                }
+            }
+        }
+    }
+
+    tx.execute("DELETE FROM matchup_estimates", [])?;
+    for ((char_id, opp_char_id), (games, wins, advantage, std_error)) in fit_matchups(buckets) {
+        tx.execute(
+            "INSERT INTO matchup_estimates VALUES(?, ?, ?, ?, ?, ?)",
+            params![char_id, opp_char_id, games, wins, advantage, std_error],
+        )?;
+    }
+
+    tx.execute("DELETE FROM matchup_estimates_rating", [])?;
+    for ((r, char_id, opp_char_id), (games, wins, advantage, std_error)) in
+        fit_matchups(bracket_buckets)
+    {
+        tx.execute(
+            "INSERT INTO matchup_estimates_rating VALUES(?, ?, ?, ?, ?, ?, ?)",
+            params![r, char_id, opp_char_id, games, wins, advantage, std_error],
+        )?;
+    }
+
+    tx.commit()?;
+
+    info!(
+        "Updated matchup estimates - {}ms",
+        (Utc::now() - then).num_milliseconds()
+    );
+
+    Ok(())
+}
+
+/// Expected win rate of each character against the field of every rating bracket, using the
+/// bracket's matchup estimates weighted by how popular each opponent is in that bracket.
+/// Matchups with too few games in a bracket fall back to their overall estimate. The fraud
+/// index closest to the bracket is stored next to it.
+pub fn calc_tier_list(conn: &mut Connection) -> Result<()> {
+    let then = Utc::now();
+    info!("Calculating tier list");
+
+    let tx = conn.transaction()?;
+
+    let mut overall_win_chances = [[0.5; CHAR_COUNT]; CHAR_COUNT];
+    {
+        let mut stmt =
+            tx.prepare("SELECT char_id, opp_char_id, advantage FROM matchup_estimates")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let char_id: usize = row.get(0)?;
+            let opp_char_id: usize = row.get(1)?;
+            if char_id < CHAR_COUNT && opp_char_id < CHAR_COUNT {
+                overall_win_chances[char_id][opp_char_id] =
+                    glicko::advantage_to_probability(row.get(2)?);
+            }
+        }
+    }
+
+    tx.execute("DELETE FROM tier_list", [])?;
+
+    for r in 0..POP_RATING_BRACKETS {
+        let mut popularities = [0.0; CHAR_COUNT];
+        {
+            let mut stmt = tx.prepare(
+                "SELECT char_id, popularity FROM character_popularity_rating WHERE rating_bracket = ?",
+            )?;
+            let mut rows = stmt.query(params![r])?;
+            while let Some(row) = rows.next()? {
+                let char_id: usize = row.get(0)?;
+                if char_id < CHAR_COUNT {
+                    popularities[char_id] = row.get(1)?;
+                }
+            }
+        }
+
+        let total_popularity: f64 = popularities.iter().sum();
+        if total_popularity <= 0.0 {
+            continue;
+        }
+
+        let mut win_chances = overall_win_chances;
+        {
+            let mut stmt = tx.prepare(
+                "SELECT char_id, opp_char_id, advantage FROM matchup_estimates_rating
+                WHERE rating_bracket = ? AND game_count >= ?",
+            )?;
+            let mut rows = stmt.query(params![r, BRACKET_ESTIMATE_MIN_GAMES])?;
+            while let Some(row) = rows.next()? {
+                let char_id: usize = row.get(0)?;
+                let opp_char_id: usize = row.get(1)?;
+                if char_id < CHAR_COUNT && opp_char_id < CHAR_COUNT {
+                    win_chances[char_id][opp_char_id] =
+                        glicko::advantage_to_probability(row.get(2)?);
+                }
+            }
+        }
+
+        let fraud_table = if 1000 + r * 100 >= 1800 {
+            "fraud_index_highest_rated"
+        } else if 1000 + r * 100 >= 1500 {
+            "fraud_index_higher_rated"
+        } else {
+            "fraud_index"
+        };
+
+        for (c, chances) in win_chances.iter().enumerate() {
+            let win_rate = popularities
+                .iter()
+                .zip(chances)
+                .map(|(popularity, chance)| popularity * chance)
+                .sum::<f64>()
+                / total_popularity;
+
+            let fraud_offset: Option<f64> = tx
+                .query_row(
+                    &format!("SELECT avg_delta FROM {} WHERE char_id = ?", fraud_table),
+                    params![c],
+                    |r| r.get(0),
+                )
+                .optional()?;
+
+            tx.execute(
+                "INSERT INTO tier_list VALUES(?, ?, ?, ?)",
+                params![r, c, win_rate, fraud_offset],
+            )?;
+        }
+    }
+
+    tx.commit()?;
+
+    info!(
+        "Updated tier list - {}ms",
+        (Utc::now() - then).num_milliseconds()
+    );
+
+    Ok(())
+}
+
+/// Looks through the last week of games for patterns worth a closer look and puts them in the
+/// `suspicious_accounts` review queue. Nothing here affects ratings, flags are only for review.
+pub fn detect_suspicious_accounts(conn: &mut Connection, timestamp: i64) -> Result<()> {
+    let then = Utc::now();
+    info!("Looking for suspicious accounts");
+
+    let since = timestamp - SUSPICIOUS_WINDOW;
+    let tx = conn.transaction()?;
+    let cheaters = get_cheater_ids(&tx);
+
+    let mut flags = Vec::<(i64, &str, f64, String)>::new();
+
+    //Winning a lot of games that were very unlikely to be won
+    {
+        let mut upsets = FxHashMap::<i64, (i64, f64)>::default();
+        let mut stmt = tx.prepare(
+            "SELECT id_a, value_a, deviation_a, id_b, value_b, deviation_b, winner
+            FROM games NATURAL JOIN game_ratings
+            WHERE timestamp > ?",
+        )?;
+        let mut rows = stmt.query(params![since])?;
+        while let Some(row) = rows.next()? {
+            let rating_a = Rating::new(row.get(1)?, row.get(2)?);
+            let rating_b = Rating::new(row.get(4)?, row.get(5)?);
+            let (winner, winner_rating, loser_rating) = match row.get::<_, i64>(6)? {
+                1 => (row.get::<_, i64>(0)?, rating_a, rating_b),
+                _ => (row.get::<_, i64>(3)?, rating_b, rating_a),
+            };
+
+            let expected = winner_rating.expected(loser_rating);
+            if loser_rating.deviation < LOW_DEVIATION && expected < SUSPICIOUS_UPSET_EXPECTED {
+                let upset = upsets.entry(winner).or_default();
+                upset.0 += 1;
+                upset.1 += expected;
+            }
+        }
+
+        for (id, (wins, expected_wins)) in upsets {
+            if wins >= SUSPICIOUS_UPSET_WINS {
+                flags.push((
+                    id,
+                    "upsets",
+                    wins as f64,
+                    format!(
+                        "{} wins against opponents they had under {:.0}% chance against, {:.1} expected",
+                        wins,
+                        SUSPICIOUS_UPSET_EXPECTED * 100.0,
+                        expected_wins
+                    ),
+                ));
+            }
+        }
+    }
+
+    //Characters that shot up right after being picked up, out of the ones played lately so
+    //the same old accounts don't come back every week
+    {
+        let mut stmt = tx.prepare(
+            "SELECT id, char_id, value, wins + losses
+            FROM player_ratings
+            WHERE wins + losses <= ? AND value >= ?
+                AND (id, char_id) IN (
+                    SELECT id_a, char_a FROM games WHERE timestamp > ?
+                    UNION
+                    SELECT id_b, char_b FROM games WHERE timestamp > ?
+                )",
+        )?;
+        let mut rows = stmt.query(params![
+            SUSPICIOUS_FRESH_GAMES,
+            1500.0 + SUSPICIOUS_FRESH_GAIN,
+            since,
+            since
+        ])?;
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let char_id: usize = row.get(1)?;
+            let value: f64 = row.get(2)?;
+            let games: i64 = row.get(3)?;
+            flags.push((
+                id,
+                "fresh_character",
+                value - 1500.0,
+                format!(
+                    "{} at {:.0} after {} games",
+                    website::CHAR_NAMES[char_id].1,
+                    value,
+                    games
+                ),
+            ));
+        }
+    }
+
+    //Playing the same account over and over and (nearly) always winning
+    {
+        let mut stmt = tx.prepare(
+            "SELECT min(id_a, id_b), max(id_a, id_b), COUNT(*),
+                SUM(CASE WHEN (winner = 1) = (id_a < id_b) THEN 1 ELSE 0 END)
+            FROM games
+            WHERE timestamp > ?
+            GROUP BY min(id_a, id_b), max(id_a, id_b)
+            HAVING COUNT(*) >= ?",
+        )?;
+        let mut rows = stmt.query(params![since, SUSPICIOUS_PAIR_GAMES])?;
+        while let Some(row) = rows.next()? {
+            let low_id: i64 = row.get(0)?;
+            let high_id: i64 = row.get(1)?;
+            let games: i64 = row.get(2)?;
+            let low_wins: i64 = row.get(3)?;
+
+            let (winner, loser, wins) = if low_wins * 2 >= games {
+                (low_id, high_id, low_wins)
+            } else {
+                (high_id, low_id, games - low_wins)
+            };
+
+            if wins as f64 >= games as f64 * SUSPICIOUS_PAIR_WIN_RATE {
+                flags.push((
+                    winner,
+                    "repeated_opponent",
+                    games as f64,
+                    format!("Won {} of {} games against {:X}", wins, games, loser),
+                ));
+            }
+        }
+    }
+
+    //Changing names a lot, player_names already has every name with when it was last used
+    {
+        let mut stmt = tx.prepare(
+            "SELECT id, COUNT(*)
+            FROM player_names
+            WHERE last_seen > ?
+            GROUP BY id
+            HAVING COUNT(*) >= ?",
+        )?;
+        let mut rows = stmt.query(params![since, SUSPICIOUS_NAMES])?;
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let names: i64 = row.get(1)?;
+            flags.push((
+                id,
+                "name_changes",
+                names as f64,
+                format!("Played under {} names in the last week", names),
+            ));
+        }
+    }
+
+    let flag_count = flags.len();
+    for (id, reason, score, details) in flags {
+        if cheaters.contains(&id) {
+            continue;
+        }
+
+        tx.execute(
+            "INSERT INTO suspicious_accounts VALUES(?, ?, ?, ?, ?, 'open')
+            ON CONFLICT(id, reason) DO UPDATE SET
+                score = excluded.score,
+                details = excluded.details,
+                flagged_at = excluded.flagged_at
+            WHERE status = 'open'",
+            params![id, reason, score, details, timestamp],
+        )?;
+    }
+
+    tx.commit()?;
+
+    info!(
+        "Found {} suspicious patterns - {}ms",
+        flag_count,
+        (Utc::now() - then).num_milliseconds()
+    );
+
+    Ok(())
+}
+
+/// Finds pairs of players who met far more often in the last month than their game counts
+/// would suggest, with one side winning most of the games. They end up in `trading_pairs` for
+/// review, together with how much rating changed hands.
+pub fn detect_trading_pairs(conn: &mut Connection, timestamp: i64) -> Result<()> {
+    let then = Utc::now();
+    info!("Looking for win trading");
+
+    let since = timestamp - TRADING_WINDOW;
+    let tx = conn.transaction()?;
+
+    let total_games: f64 = tx.query_row(
+        "SELECT COUNT(*) FROM games WHERE timestamp > ?",
+        params![since],
+        |r| r.get(0),
+    )?;
+    if total_games == 0.0 {
+        return Ok(());
+    }
+
+    let mut game_counts = FxHashMap::<i64, f64>::default();
+    {
+        let mut stmt = tx.prepare(
+            "SELECT id, COUNT(*) FROM (
+                SELECT id_a AS id FROM games WHERE timestamp > ?
+                UNION ALL
+                SELECT id_b AS id FROM games WHERE timestamp > ?
+            )
+            GROUP BY id",
+        )?;
+        let mut rows = stmt.query(params![since, since])?;
+        while let Some(row) = rows.next()? {
+            game_counts.insert(row.get(0)?, row.get(1)?);
+        }
+    }
+
+    let mut candidates = Vec::new();
+    {
+        let mut stmt = tx.prepare(
+            "SELECT min(id_a, id_b), max(id_a, id_b), COUNT(*),
+                SUM(CASE WHEN (winner = 1) = (id_a < id_b) THEN 1 ELSE 0 END),
+                MIN(timestamp), MAX(timestamp)
+            FROM games
+            WHERE timestamp > ?
+            GROUP BY min(id_a, id_b), max(id_a, id_b)
+            HAVING COUNT(*) >= ?",
+        )?;
+        let mut rows = stmt.query(params![since, TRADING_MIN_GAMES])?;
+        while let Some(row) = rows.next()? {
+            let low_id: i64 = row.get(0)?;
+            let high_id: i64 = row.get(1)?;
+            let games: i64 = row.get(2)?;
+            let low_wins: i64 = row.get(3)?;
+
+            //If everyone was matched at random, this is how often these two would have met
+            let expected_games = game_counts[&low_id] * game_counts[&high_id] / (2.0 * total_games);
+
+            let (winner, loser, wins) = if low_wins * 2 >= games {
+                (low_id, high_id, low_wins)
+            } else {
+                (high_id, low_id, games - low_wins)
+            };
+
+            if games as f64 >= expected_games * TRADING_MIN_RATIO
+                && wins as f64 >= games as f64 * TRADING_WIN_RATE
+            {
+                candidates.push((
+                    winner,
+                    loser,
+                    games,
+                    wins,
+                    expected_games,
+                    row.get::<_, i64>(4)?,
+                    row.get::<_, i64>(5)?,
+                ));
+            }
+        }
+    }
+
+    let candidate_count = candidates.len();
+    for (winner, loser, games, wins, expected_games, first_timestamp, last_timestamp) in candidates
+    {
+        let (sets, rating_transferred) = api::pair_sets(&tx, winner, loser, since);
+
+        tx.execute(
+            "INSERT INTO trading_pairs VALUES(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 'open')
+            ON CONFLICT(winner_id, loser_id) DO UPDATE SET
+                games = excluded.games,
+                sets = excluded.sets,
+                wins = excluded.wins,
+                expected_games = excluded.expected_games,
+                rating_transferred = excluded.rating_transferred,
+                first_timestamp = min(first_timestamp, excluded.first_timestamp),
+                last_timestamp = excluded.last_timestamp,
+                flagged_at = excluded.flagged_at
+            WHERE status = 'open'",
+            params![
+                winner,
+                loser,
+                games,
+                sets as i64,
+                wins,
+                expected_games,
+                rating_transferred,
+                first_timestamp,
+                last_timestamp,
+                timestamp
+            ],
+        )?;
+    }
+
+    tx.commit()?;
+
+    info!(
+        "Found {} suspected win trading pairs - {}ms",
+        candidate_count,
+        (Utc::now() - then).num_milliseconds()
+    );
+
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+pub struct Season {
+    pub season_id: i64,
+    pub name: String,
+    pub start_timestamp: i64,
+    pub end_timestamp: Option<i64>,
+    pub soft_reset: Option<f64>,
+}
+
+impl Season {
+    pub fn from_row(row: &Row) -> Self {
+        Self {
+            season_id: row.get(0).unwrap(),
+            name: row.get(1).unwrap(),
+            start_timestamp: row.get(2).unwrap(),
+            end_timestamp: row.get(3).unwrap(),
+            soft_reset: row.get(4).unwrap(),
+        }
+    }
+
+    pub fn contains(&self, timestamp: i64) -> bool {
+        timestamp >= self.start_timestamp
+            && self
+                .end_timestamp
+                .map(|end| timestamp < end)
+                .unwrap_or(true)
+    }
+
+    fn initial_rating(&self, lifetime_rating: Rating) -> Rating {
+        match self.soft_reset {
+            Some(factor) => Rating::new(
+                1500.0 + (lifetime_rating.value - 1500.0) * factor,
+                lifetime_rating.deviation.max(SOFT_RESET_DEVIATION),
+            ),
+            None => Rating::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchupTier {
+    pub tier_id: i64,
+    pub name: String,
+    pub description: String,
+    pub max_rank: Option<i64>,
+    pub proportional: bool,
+    pub min_rating: Option<f64>,
+    pub max_rating: Option<f64>,
+    pub min_floor: Option<i64>,
+}
+
+impl MatchupTier {
+    pub fn from_row(row: &Row) -> Self {
+        Self {
+            tier_id: row.get(0).unwrap(),
+            name: row.get(1).unwrap(),
+            description: row.get(2).unwrap(),
+            max_rank: row.get(3).unwrap(),
+            proportional: row.get(4).unwrap(),
+            min_rating: row.get(5).unwrap(),
+            max_rating: row.get(6).unwrap(),
+            min_floor: row.get(7).unwrap(),
+        }
+    }
+
+    /// Whether one side of a game counts towards this tier. Both players have to pass.
+    fn includes(&self, rank: i64, rating: Rating, popularity: f64, game_floor: i64) -> bool {
+        if let Some(max_rank) = self.max_rank {
+            let cutoff = if self.proportional {
+                popularity * max_rank as f64
+            } else {
+                max_rank as f64
+            };
+            if rank as f64 > cutoff {
+                return false;
+            }
+        }
 
-    tx.commit()?;
+        if (self.min_rating.is_some() || self.max_rating.is_some())
+            && (rating.deviation >= LOW_DEVIATION
+                || rating.value < self.min_rating.unwrap_or(f64::MIN)
+                || rating.value >= self.max_rating.unwrap_or(f64::MAX))
+        {
+            return false;
+        }
 
-    info!(
-        "Updated fraud index - {}ms",
-        (Utc::now() - then).num_milliseconds()
-    );
+        if let Some(min_floor) = self.min_floor {
+            if game_floor < min_floor {
+                return false;
+            }
+        }
 
-    Ok(())
+        true
+    }
 }
 
 #[derive(Debug)]
@@ -1904,3 +4207,866 @@ impl RatedPlayer {
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../init.sql")).unwrap();
+        conn
+    }
+
+    /// Adds a game on floor 10 and rates it. Players are named after their ids.
+    pub(crate) fn play(
+        conn: &mut Connection,
+        timestamp: i64,
+        (id_a, char_a): (i64, i64),
+        (id_b, char_b): (i64, i64),
+        winner: i64,
+    ) {
+        conn.execute(
+            "INSERT INTO games VALUES(?, ?, ?, ?, 1, ?, ?, ?, 1, ?, 10)",
+            params![
+                timestamp,
+                id_a,
+                format!("p{}", id_a),
+                char_a,
+                id_b,
+                format!("p{}", id_b),
+                char_b,
+                winner
+            ],
+        )
+        .unwrap();
+        update_ratings(conn, None);
+    }
+
+    pub(crate) fn rating(conn: &Connection, (id, char_id): (i64, i64)) -> Rating {
+        conn.query_row(
+            "SELECT value, deviation FROM player_ratings WHERE id = ? AND char_id = ?",
+            params![id, char_id],
+            |r| Ok(Rating::new(r.get(0)?, r.get(1)?)),
+        )
+        .unwrap()
+    }
+
+    /// The rating a character went into a game with, and whether the game counted.
+    pub(crate) fn game_rating(
+        conn: &Connection,
+        timestamp: i64,
+        (id, _): (i64, i64),
+    ) -> (Rating, bool) {
+        conn.query_row(
+            "SELECT
+                CASE WHEN id_a = ?2 THEN value_a ELSE value_b END,
+                CASE WHEN id_a = ?2 THEN deviation_a ELSE deviation_b END,
+                valid
+            FROM game_ratings WHERE timestamp = ?1 AND (id_a = ?2 OR id_b = ?2)",
+            params![timestamp, id],
+            |r| Ok((Rating::new(r.get(0)?, r.get(1)?), r.get(2)?)),
+        )
+        .unwrap()
+    }
+
+    /// Adds a game with the given ratings going into it, without rating it.
+    pub(crate) fn rated_game(
+        conn: &Connection,
+        timestamp: i64,
+        (id_a, char_a, rating_a): (i64, i64, Rating),
+        (id_b, char_b, rating_b): (i64, i64, Rating),
+        winner: i64,
+    ) {
+        conn.execute(
+            "INSERT INTO games VALUES(?, ?, ?, ?, 1, ?, ?, ?, 1, ?, 10)",
+            params![
+                timestamp,
+                id_a,
+                format!("p{}", id_a),
+                char_a,
+                id_b,
+                format!("p{}", id_b),
+                char_b,
+                winner
+            ],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO game_ratings VALUES(?, ?, ?, ?, ?, ?, ?, ?, 1, NULL, NULL)",
+            params![
+                timestamp,
+                id_a,
+                rating_a.value,
+                rating_a.deviation,
+                id_b,
+                rating_b.value,
+                rating_b.deviation,
+                winner
+            ],
+        )
+        .unwrap();
+    }
+
+    fn assert_close(a: Rating, b: Rating) {
+        assert!(
+            (a.value - b.value).abs() < 1e-6 && (a.deviation - b.deviation).abs() < 1e-6,
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn marking_a_cheater_rerates_from_their_first_game() {
+        let mut conn = test_db();
+        let opponent = (1, 0);
+        let other = (2, 3);
+        let cheater = (3, 5);
+        let third = (4, 7);
+
+        play(&mut conn, 1000, opponent, other, 1);
+        play(&mut conn, 1100, cheater, opponent, 1);
+        play(&mut conn, 1200, opponent, other, 2);
+        play(&mut conn, 1300, other, third, 1);
+
+        let before = game_rating(&conn, 1000, opponent);
+        //What they went into their next games with, games this close together don't decay
+        let opponent_after_first = game_rating(&conn, 1100, opponent).0;
+        let other_after_first = game_rating(&conn, 1200, other).0;
+
+        assert!(set_cheater(&mut conn, cheater.0, "cheater", "", "test"));
+        rerate_cheater_opponents(&mut conn, cheater.0);
+
+        //Nothing before the first game against the cheater changes
+        assert_eq!(game_rating(&conn, 1000, opponent), before);
+        assert!(!game_rating(&conn, 1100, opponent).1);
+
+        //Going into the next game the loss to the cheater is gone
+        assert_close(game_rating(&conn, 1200, opponent).0, opponent_after_first);
+        let opponent_rating = opponent_after_first.update(other_after_first, 0.0);
+        let other_rating = other_after_first.update(opponent_after_first, 1.0);
+        assert_close(rating(&conn, opponent), opponent_rating);
+        assert_close(game_rating(&conn, 1300, other).0, other_rating);
+
+        //Someone who only played the opponent's opponent afterwards is re-rated as well
+        assert_close(
+            rating(&conn, other),
+            other_rating.update(Rating::default(), 1.0),
+        );
+        assert_close(
+            rating(&conn, third),
+            Rating::default().update(other_rating, 0.0),
+        );
+
+        let wins: i64 = conn
+            .query_row(
+                "SELECT wins FROM player_ratings WHERE id = ? AND char_id = ?",
+                params![other.0, other.1],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(wins, 2);
+        let matchups: Vec<(i64, i64, i64)> = conn
+            .prepare("SELECT opp_char_id, wins, losses FROM player_matchups WHERE id = ?")
+            .unwrap()
+            .query_map(params![opponent.0], |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?))
+            })
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(matchups, vec![(other.1, 1, 1)]);
+    }
+
+    #[test]
+    fn unmarking_a_cheater_restores_the_ratings() {
+        let mut conn = test_db();
+        let opponent = (1, 0);
+        let other = (2, 3);
+        let cheater = (3, 5);
+
+        play(&mut conn, 1000, opponent, other, 1);
+        play(&mut conn, 1100, cheater, opponent, 1);
+        play(&mut conn, 1200, opponent, other, 2);
+        play(&mut conn, 1300, cheater, other, 2);
+
+        let before = [
+            rating(&conn, opponent),
+            rating(&conn, other),
+            rating(&conn, cheater),
+        ];
+
+        assert!(set_cheater(&mut conn, cheater.0, "cheater", "", "test"));
+        rerate_cheater_opponents(&mut conn, cheater.0);
+        assert_ne!(rating(&conn, opponent).value, before[0].value);
+
+        assert!(remove_cheater(&mut conn, cheater.0, "", "test"));
+        assert!(!remove_cheater(&mut conn, cheater.0, "", "test"));
+        rerate_cheater_opponents(&mut conn, cheater.0);
+
+        assert_close(rating(&conn, opponent), before[0]);
+        assert_close(rating(&conn, other), before[1]);
+        assert_close(rating(&conn, cheater), before[2]);
+        assert!(game_rating(&conn, 1100, opponent).1);
+    }
+
+    #[test]
+    fn rerate_offsets_are_kept_under_the_log_row() {
+        let mut conn = test_db();
+        let opponent = (1, 0);
+        let other = (2, 3);
+        let cheater = (3, 5);
+
+        play(&mut conn, 1000, opponent, other, 1);
+        play(&mut conn, 1100, cheater, opponent, 1);
+        play(&mut conn, 1200, opponent, other, 2);
+
+        let offsets = |conn: &Connection, log_id: i64| -> Vec<(i64, i64, f64)> {
+            let mut stmt = conn
+                .prepare("SELECT id, char_id, offset FROM cheater_offsets WHERE log_id = ? ORDER BY id, char_id")
+                .unwrap();
+            stmt.query_map(params![log_id], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+                .unwrap()
+                .map(|r| r.unwrap())
+                .collect()
+        };
+        let last_log_id = |conn: &Connection| -> i64 {
+            conn.query_row("SELECT MAX(log_id) FROM cheater_log", [], |r| r.get(0))
+                .unwrap()
+        };
+
+        let before = rating(&conn, opponent).value;
+        assert!(set_cheater(&mut conn, cheater.0, "cheater", "", "test"));
+        let mark_id = last_log_id(&conn);
+        rerate_cheater_opponents(&mut conn, cheater.0);
+        let marked = rating(&conn, opponent).value;
+
+        let mark_offsets = offsets(&conn, mark_id);
+        let (_, _, offset) = mark_offsets
+            .iter()
+            .find(|(id, char_id, _)| (*id, *char_id) == opponent)
+            .unwrap();
+        assert!((offset - (marked - before)).abs() < 1e-6);
+        assert!(*offset != 0.0);
+
+        assert!(remove_cheater(&mut conn, cheater.0, "", "test"));
+        let unmark_id = last_log_id(&conn);
+        rerate_cheater_opponents(&mut conn, cheater.0);
+
+        let unmark_offsets = offsets(&conn, unmark_id);
+        let (_, _, offset) = unmark_offsets
+            .iter()
+            .find(|(id, char_id, _)| (*id, *char_id) == opponent)
+            .unwrap();
+        assert!((offset - (before - marked)).abs() < 1e-6);
+        assert_eq!(offsets(&conn, mark_id), mark_offsets);
+    }
+
+    fn season_rating(conn: &Connection, season_id: i64, (id, char_id): (i64, i64)) -> RatedPlayer {
+        conn.query_row(
+            "SELECT id, char_id, wins, losses, value, deviation, last_decay
+            FROM season_player_ratings
+            WHERE season_id = ? AND id = ? AND char_id = ?",
+            params![season_id, id, char_id],
+            |r| Ok(RatedPlayer::from_row(r)),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn soft_reset_carries_over_part_of_the_rating() {
+        let season = Season {
+            season_id: 1,
+            name: "Season 1".to_owned(),
+            start_timestamp: 0,
+            end_timestamp: None,
+            soft_reset: Some(0.5),
+        };
+        assert_close(
+            season.initial_rating(Rating::new(1700.0, 80.0)),
+            Rating::new(1600.0, SOFT_RESET_DEVIATION),
+        );
+        assert_close(
+            season.initial_rating(Rating::new(1300.0, 300.0)),
+            Rating::new(1400.0, 300.0),
+        );
+
+        let hard_reset = Season {
+            soft_reset: None,
+            ..season
+        };
+        assert_close(
+            hard_reset.initial_rating(Rating::new(1700.0, 80.0)),
+            Rating::default(),
+        );
+    }
+
+    #[test]
+    fn a_new_season_takes_over_the_games_after_its_start() {
+        let mut conn = test_db();
+        let winner = (1, 0);
+        let loser = (2, 1);
+
+        let first = create_season(&mut conn, "Season 1", 0, None).unwrap();
+        play(&mut conn, 1000, winner, loser, 1);
+        play(&mut conn, 2000, winner, loser, 1);
+        let going_in = game_rating(&conn, 2000, winner).0;
+        let loser_going_in = game_rating(&conn, 2000, loser).0;
+
+        //Started after the games were played, the first season has to give up the second one
+        let second = create_season(&mut conn, "Season 2", 1500, Some(0.5)).unwrap();
+
+        let first_season = season_rating(&conn, first, winner);
+        assert_eq!((first_season.win_count, first_season.loss_count), (1, 0));
+        assert_close(
+            first_season.rating,
+            Rating::default().update(Rating::default(), 1.0),
+        );
+
+        let second_season = season_rating(&conn, second, winner);
+        assert_eq!((second_season.win_count, second_season.loss_count), (1, 0));
+        let season = &get_seasons(&conn)[1];
+        assert_eq!(season.end_timestamp, None);
+        assert_eq!(get_seasons(&conn)[0].end_timestamp, Some(1500));
+        assert_close(
+            second_season.rating,
+            season
+                .initial_rating(going_in)
+                .update(season.initial_rating(loser_going_in), 1.0),
+        );
+
+        //Seasons can't start inside or before another one
+        assert!(create_season(&mut conn, "Season 0", 1200, None).is_err());
+        assert!(create_season(&mut conn, "Season 2b", 1500, None).is_err());
+        assert_eq!(get_seasons(&conn).len(), 2);
+    }
+
+    #[test]
+    fn hide_codes_can_only_be_used_once_while_pending() {
+        let mut conn = test_db();
+        let pending = |conn: &Connection, code: &str, expiry: i64| {
+            conn.execute(
+                "INSERT OR REPLACE INTO hide_requests
+                VALUES(1, 'pending', 'unhidden', ?, ?, 0, NULL)",
+                params![code, expiry],
+            )
+            .unwrap();
+        };
+
+        pending(&conn, "abc", 200);
+        assert_eq!(
+            claim_hide_code(&mut conn, 1, "abc", 100),
+            Some(HideState::Unhidden)
+        );
+        assert_eq!(claim_hide_code(&mut conn, 1, "abc", 100), None);
+
+        //A new code replaced the one that was found
+        pending(&conn, "def", 200);
+        assert_eq!(claim_hide_code(&mut conn, 1, "abc", 100), None);
+
+        //The code ran out while the comment was fetched
+        assert_eq!(claim_hide_code(&mut conn, 1, "def", 200), None);
+    }
+
+    #[test]
+    fn season_matchups_only_count_their_own_games() {
+        let mut conn = test_db();
+        let sol = (1, 0);
+        let ky = (2, 1);
+
+        let first = create_season(&mut conn, "Season 1", 0, None).unwrap();
+        play(&mut conn, 1000, sol, ky, 1);
+        play(&mut conn, 1100, sol, ky, 1);
+        let second = create_season(&mut conn, "Season 2", 1500, None).unwrap();
+        play(&mut conn, 2000, sol, ky, 2);
+
+        let record = |season_id: i64| -> (i64, i64) {
+            conn.query_row(
+                "SELECT wins, losses FROM season_matchups
+                WHERE season_id = ? AND char_id = ? AND opp_char_id = ?",
+                params![season_id, sol.1, ky.1],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap()
+        };
+        assert_eq!(record(first), (2, 0));
+        assert_eq!(record(second), (0, 1));
+    }
+
+    #[test]
+    fn tier_criteria() {
+        let tier = MatchupTier {
+            tier_id: 5,
+            name: "Test".to_owned(),
+            description: String::new(),
+            max_rank: Some(100),
+            proportional: false,
+            min_rating: None,
+            max_rating: None,
+            min_floor: None,
+        };
+        let settled = Rating::new(1600.0, 50.0);
+        assert!(tier.includes(100, settled, 0.1, 1));
+        assert!(!tier.includes(101, settled, 0.1, 1));
+
+        let proportional = MatchupTier {
+            proportional: true,
+            ..tier.clone()
+        };
+        assert!(proportional.includes(10, settled, 0.1, 1));
+        assert!(!proportional.includes(11, settled, 0.1, 1));
+
+        let rating_range = MatchupTier {
+            max_rank: None,
+            min_rating: Some(1600.0),
+            max_rating: Some(1700.0),
+            ..tier.clone()
+        };
+        assert!(rating_range.includes(99999, settled, 0.1, 1));
+        assert!(!rating_range.includes(99999, Rating::new(1700.0, 50.0), 0.1, 1));
+        assert!(!rating_range.includes(99999, Rating::new(1650.0, LOW_DEVIATION), 0.1, 1));
+
+        let floor = MatchupTier {
+            max_rank: None,
+            min_floor: Some(99),
+            ..tier
+        };
+        assert!(floor.includes(99999, Rating::default(), 0.1, 99));
+        assert!(!floor.includes(99999, Rating::default(), 0.1, 10));
+    }
+
+    #[test]
+    fn games_count_towards_the_tiers_they_pass() {
+        let mut conn = test_db();
+        conn.execute_batch(
+            "INSERT INTO matchup_tiers(tier_id, name, description, min_floor)
+                VALUES(5, 'Floor 10', '', 10);
+            INSERT INTO matchup_tiers(tier_id, name, description, min_floor)
+                VALUES(6, 'Celestial', '', 99);",
+        )
+        .unwrap();
+        play(&mut conn, 1000, (1, 0), (2, 1), 1);
+
+        let tiers: Vec<i64> = conn
+            .prepare(
+                "SELECT tier_id FROM matchups
+                WHERE char_id = 0 AND opp_char_id = 1 AND wins = 1
+                ORDER BY tier_id",
+            )
+            .unwrap()
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        //New players aren't ranked, so only the tiers without a rank cutoff get the game
+        assert_eq!(tiers, vec![1, 5]);
+    }
+
+    #[test]
+    fn matchup_estimates_control_for_rating() {
+        let mut conn = test_db();
+        let settled = Rating::new(1500.0, 50.0);
+        for (timestamp, winner) in [(1000, 1), (1001, 1), (1002, 1), (1003, 2)] {
+            rated_game(&conn, timestamp, (1, 0, settled), (2, 1, settled), winner);
+        }
+        //Unsettled ratings and mirror matches are left out
+        rated_game(
+            &conn,
+            1004,
+            (1, 0, settled),
+            (3, 1, Rating::new(1500.0, 200.0)),
+            2,
+        );
+        rated_game(&conn, 1005, (1, 0, settled), (4, 0, settled), 1);
+
+        calc_matchup_estimates(&mut conn, 2000).unwrap();
+
+        let estimate = |char_id: i64, opp_char_id: i64| -> (i64, i64, f64) {
+            conn.query_row(
+                "SELECT game_count, wins, advantage FROM matchup_estimates
+                WHERE char_id = ? AND opp_char_id = ?",
+                params![char_id, opp_char_id],
+                |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+            )
+            .unwrap()
+        };
+        //Winning three out of four at equal ratings is worth 400 * log10(3) rating
+        let (games, wins, advantage) = estimate(0, 1);
+        assert_eq!((games, wins), (4, 3));
+        assert!((advantage - 400.0 * 3f64.log10()).abs() < 1e-3);
+        let (games, wins, advantage) = estimate(1, 0);
+        assert_eq!((games, wins), (4, 1));
+        assert!((advantage + 400.0 * 3f64.log10()).abs() < 1e-3);
+
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM matchup_estimates", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 2);
+
+        //1500 is in the brackets from 1400 and from 1500
+        let brackets: Vec<(i64, i64, i64)> = conn
+            .prepare(
+                "SELECT rating_bracket, game_count, wins FROM matchup_estimates_rating
+                WHERE char_id = 0 AND opp_char_id = 1
+                ORDER BY rating_bracket",
+            )
+            .unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(brackets, vec![(5, 4, 3), (6, 4, 3)]);
+    }
+
+    #[test]
+    fn tier_list_weighs_matchups_by_popularity() {
+        let mut conn = test_db();
+        conn.execute_batch(
+            "INSERT INTO matchup_estimates VALUES(0, 1, 100, 60, 100.0, 10.0);
+            INSERT INTO matchup_estimates VALUES(1, 0, 100, 40, -100.0, 10.0);
+            INSERT INTO character_popularity_rating VALUES(0, 5, 0.25);
+            INSERT INTO character_popularity_rating VALUES(1, 5, 0.75);
+            INSERT INTO character_popularity_rating VALUES(0, 6, 0.25);
+            INSERT INTO character_popularity_rating VALUES(1, 6, 0.75);
+            INSERT INTO matchup_estimates_rating VALUES(6, 0, 1, 100, 70, 200.0, 10.0);
+            INSERT INTO matchup_estimates_rating VALUES(6, 1, 0, 99, 30, -200.0, 10.0);
+            INSERT INTO fraud_index_higher_rated VALUES(0, 10, 12.0);",
+        )
+        .unwrap();
+
+        calc_tier_list(&mut conn).unwrap();
+
+        let tier = |char_id: i64| -> (f64, Option<f64>) {
+            conn.query_row(
+                "SELECT win_rate, fraud_offset FROM tier_list
+                WHERE rating_bracket = 5 AND char_id = ?",
+                params![char_id],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap()
+        };
+        let p = glicko::advantage_to_probability(100.0);
+        //A quarter of the field is the mirror match
+        let (win_rate, fraud_offset) = tier(0);
+        assert!((win_rate - (0.25 * 0.5 + 0.75 * p)).abs() < 1e-9);
+        assert_eq!(fraud_offset, Some(12.0));
+        let (win_rate, fraud_offset) = tier(1);
+        assert!((win_rate - (0.25 * (1.0 - p) + 0.75 * 0.5)).abs() < 1e-9);
+        assert_eq!(fraud_offset, None);
+        //Characters without estimates are even against everyone
+        assert!((tier(2).0 - 0.5).abs() < 1e-9);
+
+        //Brackets nobody plays in are left out
+        let brackets: i64 = conn
+            .query_row(
+                "SELECT COUNT(DISTINCT rating_bracket) FROM tier_list",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(brackets, 2);
+
+        //A bracket's own estimate is used once it has enough games
+        let bracket_tier = |char_id: i64| -> f64 {
+            conn.query_row(
+                "SELECT win_rate FROM tier_list WHERE rating_bracket = 6 AND char_id = ?",
+                params![char_id],
+                |r| r.get(0),
+            )
+            .unwrap()
+        };
+        let q = glicko::advantage_to_probability(200.0);
+        assert!((bracket_tier(0) - (0.25 * 0.5 + 0.75 * q)).abs() < 1e-9);
+        assert!((bracket_tier(1) - (0.25 * (1.0 - p) + 0.75 * 0.5)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn suspicious_names_and_characters_only_count_recent_activity() {
+        let mut conn = test_db();
+        let now = 10_000_000;
+        let long_ago = now - SUSPICIOUS_WINDOW - 1000;
+
+        //Lots of names, but all except the current one were dropped long ago
+        for i in 0..SUSPICIOUS_NAMES {
+            let last_seen = if i == 0 { now - 1000 } else { long_ago };
+            conn.execute(
+                "INSERT INTO player_names VALUES(1, ?, ?, ?)",
+                params![format!("old {}", i), long_ago - 100, last_seen],
+            )
+            .unwrap();
+        }
+        //Every one of them used this week, one of them first seen long ago
+        for i in 0..SUSPICIOUS_NAMES {
+            let first_seen = if i == 0 { long_ago } else { now - 1000 };
+            conn.execute(
+                "INSERT INTO player_names VALUES(2, ?, ?, ?)",
+                params![format!("new {}", i), first_seen, now - 1000 + i],
+            )
+            .unwrap();
+        }
+        //Fresh characters far above where they started, only one of them played this week
+        for (id, timestamp) in [(3, now - 1000), (4, long_ago)] {
+            conn.execute(
+                "INSERT INTO player_ratings(id, char_id, wins, losses, value, deviation, last_decay)
+                VALUES(?, 3, 20, 0, 2200.0, 60.0, 0)",
+                params![id],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO games VALUES(?, 5, 'p5', 0, 1, ?, 'fresh', 3, 1, 2, 10)",
+                params![timestamp, id],
+            )
+            .unwrap();
+        }
+
+        detect_suspicious_accounts(&mut conn, now).unwrap();
+
+        let flags: Vec<(i64, String)> = conn
+            .prepare("SELECT id, reason FROM suspicious_accounts ORDER BY id")
+            .unwrap()
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(
+            flags,
+            vec![
+                (2, "name_changes".to_owned()),
+                (3, "fresh_character".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn suspicious_patterns_are_flagged_for_review() {
+        let mut conn = test_db();
+        let now = 10_000_000;
+        let settled = Rating::new(1500.0, 50.0);
+
+        //Farming one account
+        for i in 0..SUSPICIOUS_PAIR_GAMES {
+            rated_game(&conn, now - 1000 + i, (1, 0, settled), (2, 0, settled), 1);
+        }
+        //Beating much better players
+        for i in 0..SUSPICIOUS_UPSET_WINS {
+            rated_game(
+                &conn,
+                now - 2000 + i,
+                (3, 0, Rating::new(1200.0, 50.0)),
+                (4, 0, Rating::new(1700.0, 50.0)),
+                1,
+            );
+        }
+        //Name churn
+        for i in 0..SUSPICIOUS_NAMES {
+            conn.execute(
+                "INSERT INTO player_names VALUES(5, ?, ?, ?)",
+                params![format!("name {}", i), now - 3000 + i, now - 3000 + i],
+            )
+            .unwrap();
+        }
+        //A fresh character far above where it started, once for a known cheater
+        for id in [7, 8] {
+            conn.execute(
+                "INSERT INTO player_ratings(id, char_id, wins, losses, value, deviation, last_decay)
+                VALUES(?, 3, 20, 0, 2200.0, 60.0, 0)",
+                params![id],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO games VALUES(?, ?, 'fresh', 3, 1, 6, 'p6', 0, 1, 1, 10)",
+                params![now - 4000 + id, id],
+            )
+            .unwrap();
+        }
+        conn.execute("INSERT INTO cheater_status VALUES(8, 'cheater', '')", [])
+            .unwrap();
+        //Too long ago to count
+        for i in 0..SUSPICIOUS_PAIR_GAMES {
+            rated_game(
+                &conn,
+                now - SUSPICIOUS_WINDOW - 1000 + i,
+                (9, 0, settled),
+                (10, 0, settled),
+                1,
+            );
+        }
+
+        detect_suspicious_accounts(&mut conn, now).unwrap();
+
+        let flags = |conn: &Connection| -> Vec<(i64, String, String)> {
+            conn.prepare("SELECT id, reason, status FROM suspicious_accounts ORDER BY id")
+                .unwrap()
+                .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+                .unwrap()
+                .map(|r| r.unwrap())
+                .collect()
+        };
+        let open = |id: i64, reason: &str| (id, reason.to_owned(), "open".to_owned());
+        assert_eq!(
+            flags(&conn),
+            vec![
+                open(1, "repeated_opponent"),
+                open(3, "upsets"),
+                open(5, "name_changes"),
+                open(7, "fresh_character"),
+            ]
+        );
+
+        //Reviewed flags stay the way the moderator left them
+        conn.execute(
+            "UPDATE suspicious_accounts SET status = 'dismissed' WHERE id = 1",
+            [],
+        )
+        .unwrap();
+        detect_suspicious_accounts(&mut conn, now).unwrap();
+        assert_eq!(flags(&conn)[0].2, "dismissed");
+    }
+
+    #[test]
+    fn pairs_meeting_far_too_often_are_reported() {
+        let mut conn = test_db();
+        let now = 10_000_000;
+        let settled = Rating::new(1500.0, 50.0);
+
+        //Everyone else meets once
+        for i in 0..1500 {
+            rated_game(
+                &conn,
+                now - 100_000 + i,
+                (100 + i, 0, settled),
+                (10_000 + i, 0, settled),
+                1,
+            );
+        }
+        for i in 0..TRADING_MIN_GAMES {
+            let winner = if i < 3 { 1 } else { 2 };
+            rated_game(
+                &conn,
+                now - 1000 + i,
+                (1, 0, settled),
+                (2, 0, settled),
+                winner,
+            );
+        }
+        //Lopsided, but they didn't meet often enough
+        for i in 0..TRADING_MIN_GAMES - 1 {
+            rated_game(&conn, now - 2000 + i, (3, 0, settled), (4, 0, settled), 1);
+        }
+
+        detect_trading_pairs(&mut conn, now).unwrap();
+
+        let pairs: Vec<(i64, i64, i64, i64, String)> = conn
+            .prepare("SELECT winner_id, loser_id, games, wins, status FROM trading_pairs")
+            .unwrap()
+            .query_map([], |r| {
+                Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?))
+            })
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![(
+                2,
+                1,
+                TRADING_MIN_GAMES,
+                TRADING_MIN_GAMES - 3,
+                "open".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn excluding_a_trading_pair_rerates_them() {
+        let mut conn = test_db();
+        let booster = (1, 0);
+        let boosted = (2, 1);
+        let other = (3, 2);
+
+        play(&mut conn, 1000, boosted, other, 1);
+        play(&mut conn, 1100, booster, boosted, 2);
+        play(&mut conn, 1200, booster, boosted, 2);
+        play(&mut conn, 1300, boosted, other, 2);
+        let before = [rating(&conn, boosted), rating(&conn, other)];
+
+        conn.execute(
+            "INSERT INTO trading_pairs VALUES(2, 1, 2, 1, 2, 0.1, 0.0, 1100, 1200, 0, 'open')",
+            [],
+        )
+        .unwrap();
+        assert_eq!(
+            update_trading_pair_status(&mut conn, 3, 1, "excluded"),
+            None
+        );
+        assert_eq!(
+            update_trading_pair_status(&mut conn, 2, 1, "excluded").as_deref(),
+            Some("open")
+        );
+
+        //Without the boosting only the two games against the other player count
+        let after_first = Rating::default().update(Rating::default(), 1.0);
+        let other_after_first = Rating::default().update(Rating::default(), 0.0);
+        assert_close(
+            rating(&conn, boosted),
+            after_first.update(other_after_first, 0.0),
+        );
+        assert_close(
+            rating(&conn, other),
+            other_after_first.update(after_first, 1.0),
+        );
+        assert!(!game_rating(&conn, 1100, booster).1);
+
+        assert_eq!(
+            update_trading_pair_status(&mut conn, 2, 1, "dismissed").as_deref(),
+            Some("excluded")
+        );
+        assert_close(rating(&conn, boosted), before[0]);
+        assert_close(rating(&conn, other), before[1]);
+    }
+
+    #[test]
+    fn vip_badges_follow_running_supporter_periods() {
+        let mut conn = test_db();
+        conn.execute_batch(
+            "INSERT INTO supporters VALUES(1, 'VIP', 100, 200, NULL, NULL, '');
+            INSERT INTO supporters VALUES(2, 'VIP', 100, NULL, 'Gold', NULL, '');
+            INSERT INTO supporters VALUES(2, 'VIP', 120, NULL, 'Platinum', '#fff', '');
+            INSERT INTO supporters VALUES(3, 'VIP', 300, NULL, NULL, NULL, '');",
+        )
+        .unwrap();
+
+        let badges = |conn: &Connection| -> Vec<(i64, String)> {
+            conn.prepare("SELECT id, vip_status FROM vip_status ORDER BY id")
+                .unwrap()
+                .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))
+                .unwrap()
+                .map(|r| r.unwrap())
+                .collect()
+        };
+
+        assert_eq!(update_vip_status(&mut conn, 150).unwrap(), 2);
+        //The latest period decides the badge
+        assert_eq!(
+            badges(&conn),
+            vec![(1, "VIP".to_owned()), (2, "Platinum".to_owned())]
+        );
+        update_vip_status(&mut conn, 200).unwrap();
+        assert_eq!(badges(&conn), vec![(2, "Platinum".to_owned())]);
+        update_vip_status(&mut conn, 300).unwrap();
+        assert_eq!(badges(&conn).len(), 2);
+
+        let supporter = Supporter {
+            tier: "VIP".to_owned(),
+            expiry_timestamp: None,
+            badge_text: None,
+            badge_colour: None,
+        };
+        add_supporter(&mut conn, 4, &supporter, "").unwrap();
+        assert!(badges(&conn).contains(&(4, "VIP".to_owned())));
+        assert!(end_supporter(&mut conn, 4).unwrap());
+        assert!(!end_supporter(&mut conn, 4).unwrap());
+        assert!(!badges(&conn).iter().any(|(id, _)| *id == 4));
+    }
+}
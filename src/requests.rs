@@ -74,7 +74,7 @@ pub fn generate_player_stats_request(player_id: String) -> Request<PlayerStatsRe
             platform: 3, //PC
         },
         body: PlayerStatsRequest {
-            player_id: player_id,
+            player_id,
             int1: 7,
             int2: -1,
             int3: 1,
@@ -0,0 +1,508 @@
+//! Loose player name search over the `player_search` FTS5 index.
+//!
+//! Every name a player has used goes into the index folded down to lowercase letters and
+//! digits, with accents, full-width letters and leetspeak undone, so "Ｓｏｌ", "Söl" and "5ol"
+//! all find "Sol". The index is trigram based, which gives substring matches like the old
+//! `LIKE '%name%'` search and lets names sharing most of their trigrams with the search count
+//! as typos.
+//!
+//! Suggestions for the search box come from `player_autocomplete` instead, which holds the
+//! same names split into folded words with an FTS5 prefix index, so it only finds names with a
+//! word starting with what's been typed so far.
+use crate::{api, rater, website};
+use anyhow::Result;
+use fxhash::{FxHashMap, FxHashSet};
+use lazy_static::lazy_static;
+use rocket::serde::Serialize;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Most players a search returns.
+pub const MAX_MATCHES: usize = 100;
+/// How many index rows are looked at before ranking.
+const CANDIDATES: usize = 500;
+/// Names that aren't a substring of the search need about half its trigrams to be shown.
+const MIN_RELEVANCE: f64 = 0.4;
+/// How much a player's game count counts next to how well their name matches, an exact match
+/// always beats a prefix match and so on.
+const ACTIVITY_WEIGHT: f64 = 0.1;
+
+/// Most suggestions the search box gets.
+pub const MAX_SUGGESTIONS: usize = 10;
+/// Shorter searches match too many names to be useful as suggestions.
+const MIN_SUGGESTION_SEARCH: usize = 2;
+const SUGGESTION_CACHE_TIME: Duration = Duration::from_secs(60);
+const SUGGESTION_CACHE_SIZE: usize = 1000;
+
+/// When the suggestions were made, each with the id of the player it's for.
+type CachedSuggestions = (Instant, Vec<(i64, Suggestion)>);
+
+lazy_static! {
+    /// Suggestions by autocomplete query, people typing the same names around the same time
+    /// ask for the same prefixes over and over.
+    static ref SUGGESTION_CACHE: Mutex<FxHashMap<String, CachedSuggestions>> =
+        Mutex::new(FxHashMap::default());
+}
+
+fn fold_char(c: char) -> Option<&'static str> {
+    Some(match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' | '@' | '4' => "a",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'ď' | 'đ' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' | '3' => "e",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'ĥ' | 'ħ' => "h",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' | '1' => "i",
+        'ĵ' => "j",
+        'ķ' => "k",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' | '0' => "o",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'ś' | 'ŝ' | 'ş' | 'š' | '$' | '5' => "s",
+        'ţ' | 'ť' | 'ŧ' | '7' => "t",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'ŵ' => "w",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        'ß' => "ss",
+        'æ' => "ae",
+        'œ' => "oe",
+        'þ' => "th",
+        _ => return None,
+    })
+}
+
+/// Folds a name down to what the index and searches compare.
+pub fn fold_name(name: &str) -> String {
+    let mut folded = String::with_capacity(name.len());
+    for c in name.chars() {
+        //Full-width forms of ASCII, common in Japanese names
+        let c = match c {
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap(),
+            _ => c,
+        };
+        for c in c.to_lowercase() {
+            match fold_char(c) {
+                Some(s) => folded.push_str(s),
+                None if c.is_alphanumeric() => folded.push(c),
+                None => {}
+            }
+        }
+    }
+    folded
+}
+
+/// The folded words of a name, with the whole name folded on the end so searches without the
+/// spaces find it too.
+fn fold_words(name: &str) -> Vec<String> {
+    let mut words: Vec<String> = name
+        .split(|c: char| !c.is_alphanumeric() && fold_char(c).is_none())
+        .map(fold_name)
+        .filter(|w| !w.is_empty())
+        .collect();
+    if words.len() > 1 {
+        words.push(words.concat());
+    }
+    words
+}
+
+fn trigrams(folded: &str) -> FxHashSet<String> {
+    let chars: Vec<char> = folded.chars().collect();
+    chars.windows(3).map(|w| w.iter().collect()).collect()
+}
+
+/// Any of the search's trigrams, the index ranks names with more (and rarer) ones first.
+fn match_query(folded: &str) -> String {
+    trigrams(folded)
+        .iter()
+        .map(|t| format!("\"{}\"", t))
+        .collect::<Vec<_>>()
+        .join(" OR ")
+}
+
+/// 3 for the same name, 2 for a prefix, 1 for a substring and below that the share of the
+/// search's trigrams the name has.
+fn relevance(search: &str, name: &str) -> f64 {
+    if name == search {
+        3.0
+    } else if name.starts_with(search) {
+        2.0
+    } else if name.contains(search) {
+        1.0
+    } else {
+        let search_trigrams = trigrams(search);
+        let name_trigrams = trigrams(name);
+        0.9 * search_trigrams.intersection(&name_trigrams).count() as f64
+            / search_trigrams.len().max(1) as f64
+    }
+}
+
+pub fn index_name(conn: &Connection, id: i64, name: &str) {
+    conn.execute(
+        "INSERT INTO player_search(folded, id, name) VALUES(?, ?, ?)",
+        params![fold_name(name), id, name],
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO player_autocomplete(words, id, name) VALUES(?, ?, ?)",
+        params![fold_words(name).join(" "), id, name],
+    )
+    .unwrap();
+}
+
+/// Refills the indices from `player_names`, for databases from before they existed.
+pub fn rebuild_index() -> Result<()> {
+    let mut conn = Connection::open(rater::DB_NAME)?;
+    let tx = conn.transaction()?;
+
+    tx.execute("DELETE FROM player_search", [])?;
+    tx.execute("DELETE FROM player_autocomplete", [])?;
+    let names = {
+        let mut stmt = tx.prepare("SELECT id, name FROM player_names")?;
+        let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()?
+    };
+    for (id, name) in &names {
+        index_name(&tx, *id, name);
+    }
+
+    tx.commit()?;
+    info!("Indexed {} names", names.len());
+    Ok(())
+}
+
+/// Players matching `search` along with the name that matched, best matches first. A search
+/// that's a player's hex id finds that player first.
+pub fn find_players(conn: &Connection, search: &str, include_hidden: bool) -> Vec<(i64, String)> {
+    let mut found = Vec::new();
+
+    if let Ok(id) = i64::from_str_radix(search.trim(), 16) {
+        let player: Option<String> = conn
+            .query_row(
+                "SELECT name FROM players
+                WHERE id = ?1 AND (?2 OR id NOT IN (SELECT id FROM hidden_status))",
+                params![id, include_hidden],
+                |r| r.get(0),
+            )
+            .ok();
+        if let Some(name) = player {
+            found.push((id, name));
+        }
+    }
+
+    let folded = fold_name(search);
+    if folded.is_empty() {
+        return found;
+    }
+
+    //The trigram index can't help with searches shorter than a trigram
+    let use_index = folded.chars().count() >= 3;
+    let mut stmt = if use_index {
+        conn.prepare_cached(
+            "SELECT player_search.id, player_search.name, folded, players.name = player_search.name
+            FROM player_search
+            JOIN players ON players.id = player_search.id
+            WHERE player_search MATCH ?1
+                AND (?2 OR player_search.id NOT IN (SELECT id FROM hidden_status))
+            ORDER BY rank
+            LIMIT ?3",
+        )
+    } else {
+        conn.prepare_cached(
+            "SELECT player_search.id, player_search.name, folded, players.name = player_search.name
+            FROM player_search
+            JOIN players ON players.id = player_search.id
+            WHERE folded LIKE ?1
+                AND (?2 OR player_search.id NOT IN (SELECT id FROM hidden_status))
+            LIMIT ?3",
+        )
+    }
+    .unwrap();
+    let query = if use_index {
+        match_query(&folded)
+    } else {
+        format!("%{}%", folded)
+    };
+    let mut rows = stmt
+        .query(params![query, include_hidden, CANDIDATES])
+        .unwrap();
+
+    let mut games_stmt = conn
+        .prepare_cached("SELECT SUM(wins + losses) FROM player_ratings WHERE id = ?")
+        .unwrap();
+    //Each player's best matching name, preferring their current one
+    let mut best: FxHashMap<i64, (f64, bool, String)> = FxHashMap::default();
+    while let Some(row) = rows.next().unwrap() {
+        let id: i64 = row.get(0).unwrap();
+        let name: String = row.get(1).unwrap();
+        let score = relevance(&folded, &row.get::<_, String>(2).unwrap());
+        let current: bool = row.get(3).unwrap();
+        if score < MIN_RELEVANCE {
+            continue;
+        }
+
+        let better = match best.get(&id) {
+            Some((s, c, _)) => (score, current) > (*s, *c),
+            None => true,
+        };
+        if better {
+            best.insert(id, (score, current, name));
+        }
+    }
+
+    let mut ranked: Vec<(f64, i64, String)> = best
+        .into_iter()
+        .map(|(id, (relevance, _, name))| {
+            let games: Option<i64> = games_stmt.query_row(params![id], |r| r.get(0)).unwrap();
+            let activity = ACTIVITY_WEIGHT * (1.0 + games.unwrap_or(0) as f64).ln();
+            (relevance + activity, id, name)
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+
+    for (_, id, name) in ranked {
+        if found.len() >= MAX_MATCHES {
+            break;
+        }
+        if !found.iter().any(|(f, _)| *f == id) {
+            found.push((id, name));
+        }
+    }
+    found
+}
+
+#[derive(Serialize, Clone)]
+pub struct Suggestion {
+    id: String,
+    name: String,
+    former_name: Option<String>,
+    platform: &'static str,
+    character: &'static str,
+    character_short: &'static str,
+    rating_value: i64,
+    rating_deviation: i64,
+    game_count: i64,
+}
+
+/// Every word of the search has to start a word of the name.
+fn prefix_query(search: &str) -> Option<String> {
+    let words = fold_words(search);
+    if words.concat().chars().count() < MIN_SUGGESTION_SEARCH {
+        return None;
+    }
+    //The whole search folded on the end is only there for indexing names
+    let words = if words.len() > 1 {
+        &words[..words.len() - 1]
+    } else {
+        &words[..]
+    };
+    Some(
+        words
+            .iter()
+            .map(|w| format!("words:\"{}\"*", w))
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+/// The most active players with a name starting with `search`, out of the best `CANDIDATES`
+/// index matches, along with the character they play the most. Hidden players are never
+/// suggested.
+pub fn suggest_players(conn: &Connection, search: &str) -> Vec<Suggestion> {
+    let query = match prefix_query(search) {
+        Some(query) => query,
+        None => return Vec::new(),
+    };
+
+    let cached = SUGGESTION_CACHE
+        .lock()
+        .unwrap()
+        .get(&query)
+        .filter(|(time, _)| time.elapsed() < SUGGESTION_CACHE_TIME)
+        .map(|(_, suggestions)| suggestions.clone());
+    //Players hidden since the suggestions were cached are left out on the way out
+    if let Some(suggestions) = cached {
+        return suggestions
+            .into_iter()
+            .filter(|(id, _)| !api::is_hidden(conn, *id))
+            .map(|(_, suggestion)| suggestion)
+            .collect();
+    }
+
+    //Only the best index matches are looked at, so short prefixes shared by thousands of names
+    //don't need the game counts of all of them
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT matches.id, matches.name, matches.name = players.name
+            FROM player_autocomplete AS matches
+            JOIN players ON players.id = matches.id
+            WHERE player_autocomplete MATCH ?1
+                AND matches.id NOT IN (SELECT id FROM hidden_status)
+            ORDER BY rank
+            LIMIT ?2",
+        )
+        .unwrap();
+    let mut rows = stmt.query(params![query, CANDIDATES]).unwrap();
+    //Each player's matching name, preferring their current one
+    let mut names: FxHashMap<i64, (bool, String)> = FxHashMap::default();
+    while let Some(row) = rows.next().unwrap() {
+        let id: i64 = row.get(0).unwrap();
+        let current: bool = row.get(2).unwrap();
+        if names.get(&id).map(|(c, _)| !c).unwrap_or(true) {
+            names.insert(id, (current, row.get(1).unwrap()));
+        }
+    }
+
+    let mut games_stmt = conn
+        .prepare_cached("SELECT SUM(wins + losses) FROM player_ratings WHERE id = ?")
+        .unwrap();
+    let mut matches: Vec<(i64, i64, String)> = names
+        .into_iter()
+        .filter_map(|(id, (_, name))| {
+            let games: Option<i64> = games_stmt.query_row(params![id], |r| r.get(0)).unwrap();
+            games.filter(|g| *g > 0).map(|g| (g, id, name))
+        })
+        .collect();
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    matches.truncate(MAX_SUGGESTIONS);
+
+    let mut player_stmt = conn
+        .prepare_cached(
+            "SELECT players.name, platform, char_id, value, deviation, wins + losses AS game_count
+            FROM players
+            JOIN player_ratings ON player_ratings.id = players.id
+            WHERE players.id = ?
+            ORDER BY game_count DESC
+            LIMIT 1",
+        )
+        .unwrap();
+    let mut suggestions = Vec::new();
+    for (_, id, matched_name) in matches {
+        let suggestion = player_stmt
+            .query_row(params![id], |row| {
+                let name: String = row.get(0)?;
+                let char_id: usize = row.get(2)?;
+                Ok(Suggestion {
+                    id: format!("{:X}", id),
+                    former_name: if matched_name != name {
+                        Some(matched_name.clone())
+                    } else {
+                        None
+                    },
+                    name,
+                    platform: api::to_platform_string(row.get(1)?),
+                    character: website::CHAR_NAMES[char_id].1,
+                    character_short: website::CHAR_NAMES[char_id].0,
+                    rating_value: row.get::<_, f64>(3)?.round() as i64,
+                    rating_deviation: (row.get::<_, f64>(4)? * 2.0).round() as i64,
+                    game_count: row.get(5)?,
+                })
+            })
+            .optional()
+            .unwrap();
+        suggestions.extend(suggestion.map(|s| (id, s)));
+    }
+
+    let mut cache = SUGGESTION_CACHE.lock().unwrap();
+    if cache.len() >= SUGGESTION_CACHE_SIZE {
+        cache.clear();
+    }
+    cache.insert(query, (Instant::now(), suggestions.clone()));
+    suggestions.into_iter().map(|(_, s)| s).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folding() {
+        assert_eq!(fold_name("Sol Badguy"), "solbadguy");
+        assert_eq!(fold_name("Ｓｏｌ　Ｂａｄｇｕｙ"), "solbadguy");
+        assert_eq!(fold_name("Sölbädgüy"), "solbadguy");
+        assert_eq!(fold_name("50L_B4DGUY!"), "solbadguy");
+        assert_eq!(fold_name("ソル=バッドガイ"), "ソルバッドガイ");
+    }
+
+    #[test]
+    fn words() {
+        assert_eq!(fold_words("Sol"), vec!["sol"]);
+        assert_eq!(
+            fold_words("Sol_B4dguy!!"),
+            vec!["sol", "badguy", "solbadguy"]
+        );
+        assert_eq!(
+            fold_words("Ｓｏｌ　Ｂａｄｇｕｙ"),
+            vec!["sol", "badguy", "solbadguy"]
+        );
+        assert_eq!(prefix_query("s"), None);
+        assert_eq!(prefix_query("so"), Some("words:\"so\"*".to_owned()));
+        assert_eq!(
+            prefix_query("sol bad"),
+            Some("words:\"sol\"* words:\"bad\"*".to_owned())
+        );
+    }
+
+    #[test]
+    fn relevance_order() {
+        let search = fold_name("badguy");
+        let exact = relevance(&search, &fold_name("Badguy"));
+        let prefix = relevance(&search, &fold_name("Badguy Sol"));
+        let substring = relevance(&search, &fold_name("Sol Badguy"));
+        let typo = relevance(&search, &fold_name("Sol Badgu"));
+        assert!(exact > prefix && prefix > substring && substring > typo);
+        assert!(typo >= MIN_RELEVANCE);
+        assert!(relevance(&search, &fold_name("Ky Kiske")) < MIN_RELEVANCE);
+    }
+
+    #[test]
+    fn suggestions_are_the_most_active_players() {
+        use crate::rater::tests::{play, test_db};
+
+        let mut conn = test_db();
+        for id in 1..=12 {
+            for game in 0..id {
+                play(&mut conn, id * 100 + game, (id, 0), (100, 1), 1);
+            }
+            play(&mut conn, id * 100 + 50, (id, 1), (100, 1), 1);
+            let name = format!("Zato {}", id);
+            conn.execute(
+                "UPDATE players SET name = ? WHERE id = ?",
+                params![name, id],
+            )
+            .unwrap();
+            index_name(&conn, id, &name);
+        }
+        index_name(&conn, 3, "Zed");
+        conn.execute("INSERT INTO hidden_status VALUES(12, 'hidden', '')", [])
+            .unwrap();
+
+        let suggestions = suggest_players(&conn, "zato");
+        let names: Vec<&str> = suggestions.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            (2..=11)
+                .rev()
+                .map(|id| format!("Zato {}", id))
+                .collect::<Vec<_>>()
+        );
+        assert!(suggestions
+            .iter()
+            .all(|s| s.character_short == "SO" && s.former_name.is_none()));
+
+        let former = suggest_players(&conn, "zed");
+        assert_eq!(former.len(), 1);
+        assert_eq!(former[0].former_name.as_deref(), Some("Zed"));
+
+        //Hiding takes effect before the cached suggestions run out
+        conn.execute("INSERT INTO hidden_status VALUES(11, 'hidden', '')", [])
+            .unwrap();
+        let suggestions = suggest_players(&conn, "zato");
+        assert_eq!(suggestions.len(), 9);
+        assert!(suggestions.iter().all(|s| s.name != "Zato 11"));
+    }
+}
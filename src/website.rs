@@ -1,7 +1,9 @@
-use crate::api;
+use crate::{api, api_v1};
+use lazy_static::lazy_static;
 use rocket::{
+    form::Form,
     fs::NamedFile,
-    http::{hyper::header::CACHE_CONTROL, Header},
+    http::{hyper::header::CACHE_CONTROL, Cookie, CookieJar, Header, Status},
     response::{self, Redirect, Responder},
     serde::Serialize,
     Request,
@@ -9,7 +11,25 @@ use rocket::{
 use rocket_dyn_templates::Template;
 use rocket_sync_db_pools::database;
 use rusqlite::Connection;
-use std::path::{Path, PathBuf};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+lazy_static! {
+    /// The admin pages are disabled unless ADMIN_KEY is set.
+    static ref ADMIN_KEY: Option<String> = std::env::var("ADMIN_KEY").ok().filter(|k| !k.is_empty());
+    /// When each address last failed to log in.
+    static ref FAILED_ADMIN_LOGINS: Mutex<HashMap<IpAddr, i64>> = Mutex::new(HashMap::new());
+}
+
+/// Private (encrypted) cookie that marks a logged in session, it never holds the key itself.
+const ADMIN_COOKIE: &str = "admin_session";
+const ADMIN_LOGIN_COOLDOWN: i64 = 60;
+const ADMIN_SESSION_LENGTH: i64 = 12 * 60 * 60;
 
 pub const CHAR_NAMES: &[(&str, &str)] = &[
     ("SO", "Sol"),
@@ -53,24 +73,37 @@ pub async fn run() {
                 top_all,
                 top_char,
                 matchups,
+                tier_list,
                 character_popularity,
                 player_distr_forward,
                 player_distribution,
                 player,
                 player_char,
                 player_char_history,
+                head_to_head,
+                compare,
                 search,
                 about,
                 stats,
                 supporters,
                 rating_calculator,
+                seeding,
                 recent,
+                admin,
+                admin_login,
+                admin_logout,
+                admin_player,
+                admin_cheater_preview,
+                admin_status,
                 api::stats,
                 api::player_rating,
                 api::player_rating_all,
                 api::player_rating_accuracy,
                 api::top_all,
                 api::top_char,
+                api::seasons,
+                api::matchup_estimates,
+                api::tier_list,
                 api::search,
                 api::search_exact,
                 api::outcomes,
@@ -84,10 +117,14 @@ pub async fn run() {
                 api::weekly_games,
                 api::daily_character_games,
                 api::start_hide_player,
-                api::poll_hide_player
+                api::poll_hide_player,
+                api::start_player_export,
+                api::download_player_export
             ],
         )
+        .mount("/api/v1", api_v1::routes())
         .register("/", catchers![catch_404, catch_500, catch_503])
+        .register("/api/v1", catchers![api_v1::catch_default])
         .ignite()
         .await
         .unwrap()
@@ -101,12 +138,12 @@ pub struct RatingsDbConn(Connection);
 
 #[get("/")]
 async fn index() -> Redirect {
-    Redirect::to(uri!(top_all))
+    Redirect::to(uri!(top_all(_)))
 }
 
 #[get("/about")]
 async fn about(conn: RatingsDbConn) -> Cached<Template> {
-    api::add_hit(&conn, format!("about")).await;
+    api::add_hit(&conn, "about".to_string()).await;
 
     #[derive(Serialize)]
     struct Context {
@@ -121,7 +158,7 @@ async fn about(conn: RatingsDbConn) -> Cached<Template> {
 
 #[get("/rating_calculator")]
 async fn rating_calculator(conn: RatingsDbConn) -> Cached<Template> {
-    api::add_hit(&conn, format!("rating_calculator")).await;
+    api::add_hit(&conn, "rating_calculator".to_string()).await;
 
     #[derive(Serialize)]
     struct Context {
@@ -134,9 +171,43 @@ async fn rating_calculator(conn: RatingsDbConn) -> Cached<Template> {
     Cached::new(Template::render("rating_calculator", &context), 999)
 }
 
+#[get("/seeding?<entrants>")]
+async fn seeding(conn: RatingsDbConn, entrants: Option<String>) -> Template {
+    api::add_hit(&conn, "seeding".to_owned()).await;
+
+    #[derive(Serialize)]
+    struct Context {
+        entrants: String,
+        seeded: Vec<api::SeededEntrant>,
+        too_many: bool,
+        max_entrants: usize,
+        all_characters: &'static [(&'static str, &'static str)],
+    }
+
+    let entrants = entrants.unwrap_or_default();
+    let parsed: Vec<api::Entrant> = entrants.lines().filter_map(api::Entrant::parse).collect();
+    let too_many = parsed.len() > api::MAX_ENTRANTS;
+    let seeded = if too_many {
+        Vec::new()
+    } else {
+        api::seed_entrants(&conn, parsed).await
+    };
+
+    Template::render(
+        "seeding",
+        &Context {
+            entrants,
+            seeded,
+            too_many,
+            max_entrants: api::MAX_ENTRANTS,
+            all_characters: CHAR_NAMES,
+        },
+    )
+}
+
 #[get("/stats")]
 async fn stats(conn: RatingsDbConn) -> Cached<Template> {
-    api::add_hit(&conn, format!("stats")).await;
+    api::add_hit(&conn, "stats".to_string()).await;
 
     #[derive(Serialize)]
     struct Context {
@@ -153,7 +224,7 @@ async fn stats(conn: RatingsDbConn) -> Cached<Template> {
 
 #[get("/supporters")]
 async fn supporters(conn: RatingsDbConn) -> Cached<Template> {
-    api::add_hit(&conn, format!("supporters")).await;
+    api::add_hit(&conn, "supporters".to_string()).await;
     #[derive(Serialize)]
     struct Context {
         players: Vec<api::VipPlayer>,
@@ -172,27 +243,42 @@ async fn supporters(conn: RatingsDbConn) -> Cached<Template> {
     )
 }
 
-#[get("/top/all")]
-async fn top_all(conn: RatingsDbConn) -> Cached<Template> {
-    api::add_hit(&conn, format!("top/all")).await;
+#[get("/top/all?<season>")]
+async fn top_all(conn: RatingsDbConn, season: Option<i64>) -> Cached<Template> {
+    api::add_hit(&conn, "top/all".to_string()).await;
 
     #[derive(Serialize)]
     struct Context {
         players: Vec<api::RankingPlayer>,
+        seasons: Vec<api::SeasonInfo>,
+        season: Option<i64>,
+        season_name: Option<String>,
         all_characters: &'static [(&'static str, &'static str)],
     }
 
-    let players = api::top_all_inner(&conn).await;
+    let seasons = api::get_seasons(&conn).await;
+    let season_name = season_name(&seasons, season);
+    let players = match season {
+        Some(season) => api::top_season_inner(&conn, season, None).await,
+        None => api::top_all_inner(&conn).await,
+    };
     let context = Context {
         players,
+        seasons,
+        season,
+        season_name,
         all_characters: CHAR_NAMES,
     };
 
     Cached::new(Template::render("top_100", &context), 999)
 }
 
-#[get("/top/<character_short>")]
-async fn top_char(conn: RatingsDbConn, character_short: &str) -> Option<Cached<Template>> {
+#[get("/top/<character_short>?<season>")]
+async fn top_char(
+    conn: RatingsDbConn,
+    character_short: &str,
+    season: Option<i64>,
+) -> Option<Cached<Template>> {
     api::add_hit(&conn, format!("top/{}", character_short)).await;
 
     #[derive(Serialize)]
@@ -200,17 +286,28 @@ async fn top_char(conn: RatingsDbConn, character_short: &str) -> Option<Cached<T
         players: Vec<api::RankingPlayer>,
         character: &'static str,
         character_short: &'static str,
+        seasons: Vec<api::SeasonInfo>,
+        season: Option<i64>,
+        season_name: Option<String>,
         all_characters: &'static [(&'static str, &'static str)],
     }
 
     if let Some(char_code) = CHAR_NAMES.iter().position(|(c, _)| *c == character_short) {
         let (character_short, character) = CHAR_NAMES[char_code];
 
-        let players = api::top_char_inner(&conn, char_code as i64).await;
+        let seasons = api::get_seasons(&conn).await;
+        let season_name = season_name(&seasons, season);
+        let players = match season {
+            Some(season) => api::top_season_inner(&conn, season, Some(char_code as i64)).await,
+            None => api::top_char_inner(&conn, char_code as i64).await,
+        };
         let context = Context {
             players,
             character,
             character_short,
+            seasons,
+            season,
+            season_name,
             all_characters: CHAR_NAMES,
         };
 
@@ -220,42 +317,96 @@ async fn top_char(conn: RatingsDbConn, character_short: &str) -> Option<Cached<T
     }
 }
 
-#[get("/matchups")]
-async fn matchups(conn: RatingsDbConn) -> Cached<Template> {
-    api::add_hit(&conn, format!("matchups")).await;
+fn season_name(seasons: &[api::SeasonInfo], season: Option<i64>) -> Option<String> {
+    season.and_then(|id| {
+        seasons
+            .iter()
+            .find(|s| s.season_id == id)
+            .map(|s| s.name.clone())
+    })
+}
+
+#[get("/matchups?<window>&<compare>")]
+async fn matchups(
+    conn: RatingsDbConn,
+    window: Option<i64>,
+    compare: Option<i64>,
+) -> Cached<Template> {
+    api::add_hit(&conn, "matchups".to_string()).await;
 
     #[derive(Serialize)]
     struct Context {
         character_shortnames: Vec<&'static str>,
-        matchups_global: Vec<api::CharacterMatchups>,
-        matchups_top_1000: Vec<api::CharacterMatchups>,
-        matchups_proportional: Vec<api::CharacterMatchups>,
-        matchups_top_100: Vec<api::CharacterMatchups>,
+        estimates: Vec<api::CharacterMatchupEstimates>,
+        tiers: Vec<api::TierMatchups>,
+        seasons: Vec<api::SeasonInfo>,
+        window: Option<i64>,
+        window_name: Option<String>,
+        matchups_window: Option<Vec<api::CharacterMatchups>>,
+        compare: Option<i64>,
+        compare_name: Option<String>,
+        matchups_compare: Option<Vec<api::CharacterMatchups>>,
+        matchups_changes: Option<Vec<api::CharacterMatchupChanges>>,
         all_characters: &'static [(&'static str, &'static str)],
     }
 
-    let (matchups_global, matchups_top_1000, matchups_proportional, matchups_top_100) = tokio::join!(
-        api::get_matchups(&conn, "global_matchups"),
-        api::get_matchups(&conn, "top_1000_matchups"),
-        api::get_matchups(&conn, "proportional_matchups"),
-        api::get_matchups(&conn, "top_100_matchups"),
-    );
+    let (estimates, tiers) =
+        tokio::join!(api::get_matchup_estimates(&conn), api::get_matchups(&conn));
+    let seasons = api::get_seasons(&conn).await;
+    //A comparison needs a window to compare against, which is the ongoing season unless picked
+    let window = window.or_else(|| compare.and(api::default_season(&seasons)));
+    let matchups_window = match window {
+        Some(window) => Some(api::get_season_matchups(&conn, window).await),
+        None => None,
+    };
+    let matchups_compare = match compare {
+        Some(compare) => Some(api::get_season_matchups(&conn, compare).await),
+        None => None,
+    };
+    let matchups_changes = match (&matchups_window, &matchups_compare) {
+        (Some(window), Some(compare)) => Some(api::compare_matchups(compare, window)),
+        _ => None,
+    };
 
     let context = Context {
         character_shortnames: CHAR_NAMES.iter().map(|c| c.0).collect(),
-        matchups_global,
-        matchups_top_1000,
-        matchups_proportional,
-        matchups_top_100,
+        estimates,
+        tiers,
+        window_name: season_name(&seasons, window),
+        compare_name: season_name(&seasons, compare),
+        seasons,
+        window,
+        matchups_window,
+        compare,
+        matchups_compare,
+        matchups_changes,
         all_characters: CHAR_NAMES,
     };
 
     Cached::new(Template::render("matchups", &context), 999)
 }
 
+#[get("/tier_list")]
+async fn tier_list(conn: RatingsDbConn) -> Cached<Template> {
+    api::add_hit(&conn, "tier_list".to_string()).await;
+
+    #[derive(Serialize)]
+    struct Context {
+        brackets: Vec<api::TierListBracket>,
+        all_characters: &'static [(&'static str, &'static str)],
+    }
+
+    let context = Context {
+        brackets: api::get_tier_list(&conn).await,
+        all_characters: CHAR_NAMES,
+    };
+
+    Cached::new(Template::render("tier_list", &context), 999)
+}
+
 #[get("/character_popularity")]
 async fn character_popularity(conn: RatingsDbConn) -> Cached<Template> {
-    api::add_hit(&conn, format!("character_popularity")).await;
+    api::add_hit(&conn, "character_popularity".to_string()).await;
 
     #[derive(Serialize)]
     struct Context {
@@ -319,7 +470,7 @@ async fn player_distr_forward() -> Redirect {
 
 #[get("/player_distribution")]
 async fn player_distribution(conn: RatingsDbConn) -> Cached<Template> {
-    api::add_hit(&conn, format!("player_distribution")).await;
+    api::add_hit(&conn, "player_distribution".to_string()).await;
 
     #[derive(Serialize)]
     struct Context {
@@ -376,16 +527,9 @@ async fn player_char_history(
         let offset = offset.unwrap_or(0);
         let group_games = group_games.unwrap_or(true);
 
-        if let Some(history) =
-            api::get_player_char_history(&conn, id, char_id, game_count, offset, group_games).await
-        {
-            Some(Cached::new(
-                Template::render("player_char_history", &history),
-                60,
-            ))
-        } else {
-            None
-        }
+        api::get_player_char_history(&conn, id, char_id, game_count, offset, group_games)
+            .await
+            .map(|history| Cached::new(Template::render("player_char_history", &history), 60))
     } else {
         None
     }
@@ -434,6 +578,90 @@ async fn player_char(
     }
 }
 
+#[get("/h2h/<player_a>/<player_b>")]
+async fn head_to_head(
+    conn: RatingsDbConn,
+    player_a: &str,
+    player_b: &str,
+) -> Option<Cached<Template>> {
+    api::add_hit(&conn, format!("h2h/{}/{}", player_a, player_b)).await;
+
+    let id_a = i64::from_str_radix(player_a, 16).ok()?;
+    let id_b = i64::from_str_radix(player_b, 16).ok()?;
+    if id_a == id_b {
+        return None;
+    }
+
+    #[derive(Serialize)]
+    struct Context {
+        title: String,
+        h2h: api::HeadToHead,
+    }
+
+    let h2h = conn
+        .run(move |c| {
+            if api::is_hidden(c, id_a) || api::is_hidden(c, id_b) {
+                None
+            } else {
+                api::get_head_to_head(c, id_a, id_b)
+            }
+        })
+        .await?;
+
+    let context = Context {
+        title: format!("{} vs {}", h2h.player_a.name, h2h.player_b.name),
+        h2h,
+    };
+    Some(Cached::new(Template::render("head_to_head", &context), 999))
+}
+
+#[get("/compare?<ids>")]
+async fn compare(conn: RatingsDbConn, ids: &str) -> Option<Cached<Template>> {
+    api::add_hit(&conn, format!("compare/{}", ids)).await;
+
+    let mut player_ids = Vec::new();
+    for id in ids.split(',') {
+        let id = i64::from_str_radix(id.trim(), 16).ok()?;
+        if !player_ids.contains(&id) {
+            player_ids.push(id);
+        }
+    }
+    if !(2..=api::MAX_COMPARED_PLAYERS).contains(&player_ids.len()) {
+        return None;
+    }
+
+    #[derive(Serialize)]
+    struct Context {
+        title: String,
+        ids: String,
+        comparison: api::Comparison,
+        chart: Option<api::RatingChart>,
+    }
+
+    let comparison = conn
+        .run(move |c| {
+            if player_ids.iter().any(|&id| api::is_hidden(c, id)) {
+                None
+            } else {
+                api::get_comparison(c, &player_ids)
+            }
+        })
+        .await?;
+
+    let context = Context {
+        title: comparison
+            .players
+            .iter()
+            .map(|p| p.player.name.as_str())
+            .collect::<Vec<_>>()
+            .join(" vs "),
+        ids: ids.to_owned(),
+        chart: api::rating_chart(&comparison),
+        comparison,
+    };
+    Some(Cached::new(Template::render("compare", &context), 999))
+}
+
 #[get("/?<name>")]
 async fn search(conn: RatingsDbConn, name: String) -> Template {
     api::add_hit(&conn, format!("search/{}", name)).await;
@@ -444,7 +672,7 @@ async fn search(conn: RatingsDbConn, name: String) -> Template {
         all_characters: &'static [(&'static str, &'static str)],
     }
 
-    let players = api::search_inner(&conn, name.clone(), false).await;
+    let players = api::search_inner(&conn, name.clone(), false, false).await;
 
     Template::render(
         "search_results",
@@ -468,6 +696,212 @@ async fn recent(conn: RatingsDbConn) -> Template {
     Template::render("recent games", &Context { sets })
 }
 
+fn is_admin(cookies: &CookieJar<'_>) -> bool {
+    match (ADMIN_KEY.as_ref(), cookies.get_private(ADMIN_COOKIE)) {
+        (Some(key), Some(cookie)) => {
+            valid_admin_session(key, cookie.value(), chrono::Utc::now().timestamp())
+        }
+        _ => false,
+    }
+}
+
+/// What the session cookie holds: when it runs out and a hash of the key it was made with, so
+/// changing ADMIN_KEY logs everyone out.
+fn admin_session(key: &str, expiry: i64) -> String {
+    format!("{}:{}", expiry, hex::encode(Sha256::digest(key.as_bytes())))
+}
+
+fn valid_admin_session(key: &str, session: &str, now: i64) -> bool {
+    match session
+        .split_once(':')
+        .and_then(|(expiry, _)| expiry.parse::<i64>().ok())
+    {
+        Some(expiry) => {
+            expiry > now
+                && constant_time_eq(session.as_bytes(), admin_session(key, expiry).as_bytes())
+        }
+        None => false,
+    }
+}
+
+/// Compares every byte so the time taken doesn't tell how much of the key was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[get("/admin?<name>")]
+async fn admin(conn: RatingsDbConn, cookies: &CookieJar<'_>, name: Option<String>) -> Template {
+    #[derive(Serialize)]
+    struct LoginContext {
+        enabled: bool,
+        all_characters: &'static [(&'static str, &'static str)],
+    }
+
+    #[derive(Serialize)]
+    struct Context {
+        search_string: Option<String>,
+        players: Vec<api::SearchResultPlayer>,
+        all_characters: &'static [(&'static str, &'static str)],
+    }
+
+    if !is_admin(cookies) {
+        return Template::render(
+            "admin_login",
+            &LoginContext {
+                enabled: ADMIN_KEY.is_some(),
+                all_characters: CHAR_NAMES,
+            },
+        );
+    }
+
+    let players = match name.clone() {
+        Some(name) => api::search_inner(&conn, name, false, true).await,
+        None => Vec::new(),
+    };
+
+    Template::render(
+        "admin",
+        &Context {
+            search_string: name,
+            players,
+            all_characters: CHAR_NAMES,
+        },
+    )
+}
+
+#[derive(FromForm)]
+struct AdminLogin {
+    key: String,
+}
+
+/// After a failed attempt an address has to wait before it can try again, which makes guessing
+/// the key impractical.
+#[post("/admin/login", data = "<login>")]
+async fn admin_login(
+    cookies: &CookieJar<'_>,
+    address: Option<IpAddr>,
+    login: Form<AdminLogin>,
+) -> Result<Redirect, Status> {
+    let key = match ADMIN_KEY.as_ref() {
+        Some(key) => key,
+        None => return Err(Status::NotFound),
+    };
+
+    //Failures are counted per address, without one there would be nothing to hold back
+    let address = address.ok_or(Status::BadRequest)?;
+
+    let now = chrono::Utc::now().timestamp();
+    let mut failed_logins = FAILED_ADMIN_LOGINS.lock().unwrap();
+    failed_logins.retain(|_, last_failure| now - *last_failure < ADMIN_LOGIN_COOLDOWN);
+    if failed_logins.contains_key(&address) {
+        return Err(Status::TooManyRequests);
+    }
+
+    if constant_time_eq(key.as_bytes(), login.key.as_bytes()) {
+        cookies.add_private(Cookie::new(
+            ADMIN_COOKIE,
+            admin_session(key, now + ADMIN_SESSION_LENGTH),
+        ));
+    } else {
+        warn!("Failed admin login from {}", address);
+        failed_logins.insert(address, now);
+    }
+    Ok(Redirect::to(uri!(admin(name = _))))
+}
+
+#[post("/admin/logout")]
+async fn admin_logout(cookies: &CookieJar<'_>) -> Redirect {
+    cookies.remove_private(Cookie::new(ADMIN_COOKIE, ""));
+    Redirect::to(uri!(admin(name = _)))
+}
+
+#[get("/admin/player/<player_id>?<message>")]
+async fn admin_player(
+    conn: RatingsDbConn,
+    cookies: &CookieJar<'_>,
+    player_id: &str,
+    message: Option<String>,
+) -> Result<Option<Template>, Redirect> {
+    if !is_admin(cookies) {
+        return Err(Redirect::to(uri!(admin(name = _))));
+    }
+
+    #[derive(Serialize)]
+    struct Context {
+        player: api::ModerationPlayer,
+        message: Option<String>,
+        all_characters: &'static [(&'static str, &'static str)],
+    }
+
+    if let Ok(id) = i64::from_str_radix(player_id, 16) {
+        Ok(api::get_moderation_player(&conn, id).await.map(|player| {
+            Template::render(
+                "admin_player",
+                &Context {
+                    player,
+                    message,
+                    all_characters: CHAR_NAMES,
+                },
+            )
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+#[get("/admin/player/<player_id>/cheater_preview")]
+async fn admin_cheater_preview(
+    conn: RatingsDbConn,
+    cookies: &CookieJar<'_>,
+    player_id: &str,
+) -> Result<Option<Template>, Redirect> {
+    if !is_admin(cookies) {
+        return Err(Redirect::to(uri!(admin(name = _))));
+    }
+
+    #[derive(Serialize)]
+    struct Context {
+        player: api::ModerationPlayer,
+        offsets: Vec<api::CheaterOffset>,
+        all_characters: &'static [(&'static str, &'static str)],
+    }
+
+    if let Ok(id) = i64::from_str_radix(player_id, 16) {
+        if let Some(player) = api::get_moderation_player(&conn, id).await {
+            let offsets = api::get_cheater_offset_preview(&conn, id).await;
+            return Ok(Some(Template::render(
+                "admin_cheater_preview",
+                &Context {
+                    player,
+                    offsets,
+                    all_characters: CHAR_NAMES,
+                },
+            )));
+        }
+    }
+    Ok(None)
+}
+
+#[post("/admin/player/<player_id>/status", data = "<form>")]
+async fn admin_status(
+    conn: RatingsDbConn,
+    cookies: &CookieJar<'_>,
+    player_id: &str,
+    form: Form<api::ModerationForm>,
+) -> Option<Redirect> {
+    if !is_admin(cookies) {
+        return Some(Redirect::to(uri!(admin(name = _))));
+    }
+
+    let id = i64::from_str_radix(player_id, 16).ok()?;
+    let message = api::set_moderation_status(&conn, id, form.into_inner()).await;
+
+    Some(Redirect::to(uri!(admin_player(
+        player_id = player_id,
+        message = Some(message),
+    ))))
+}
+
 #[catch(404)]
 async fn catch_404() -> NamedFile {
     NamedFile::open(Path::new("static/404.html")).await.unwrap()
@@ -517,3 +951,26 @@ impl<'r, 'o: 'r, R: Responder<'r, 'o>> Responder<'r, 'o> for Cached<R> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admin_key_comparison() {
+        assert!(constant_time_eq(b"hunter2", b"hunter2"));
+        assert!(!constant_time_eq(b"hunter2", b"hunter3"));
+        assert!(!constant_time_eq(b"hunter2", b"hunter"));
+        assert!(!constant_time_eq(b"", b"hunter2"));
+    }
+
+    #[test]
+    fn admin_sessions() {
+        let session = admin_session("hunter2", 1000);
+        assert!(valid_admin_session("hunter2", &session, 999));
+        assert!(!valid_admin_session("hunter2", &session, 1000));
+        assert!(!valid_admin_session("hunter3", &session, 999));
+        assert!(!valid_admin_session("hunter2", "1", 999));
+        assert!(!valid_admin_session("hunter2", "", 999));
+    }
+}
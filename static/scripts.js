@@ -22,8 +22,44 @@ document.addEventListener('DOMContentLoaded', () => {
       }
     });
   }
+
+  const $search = document.getElementById('name');
+  const $suggestions = document.getElementById('searchSuggestions');
+  if ($search && $suggestions) {
+    let timeout = null;
+    $search.addEventListener('input', () => {
+      clearTimeout(timeout);
+      timeout = setTimeout(() => load_suggestions($search, $suggestions), 150);
+    });
+    //Wait a moment so clicking a suggestion still follows its link
+    $search.addEventListener('blur', () => {
+      setTimeout(() => $suggestions.classList.remove('is-active'), 200);
+    });
+  }
 });
 
+const load_suggestions = ($search, $suggestions) => {
+    const search = $search.value;
+    fetch('/api/v1/autocomplete?q=' + encodeURIComponent(search))
+        .then(response => response.json())
+        .then(players => {
+            //Typing went on while this was loading
+            if ($search.value != search) {
+                return;
+            }
+            const $list = document.getElementById('searchSuggestionList');
+            $list.replaceChildren(...players.map(p => {
+                const $item = document.createElement('a');
+                $item.className = 'dropdown-item';
+                $item.href = '/player/' + p.id + '/' + p.character_short;
+                $item.textContent = p.name + (p.former_name ? ' (' + p.former_name + ')' : '')
+                    + ' – ' + p.character_short + ' ' + p.rating_value + ' ±' + p.rating_deviation;
+                return $item;
+            }));
+            $suggestions.classList.toggle('is-active', players.length > 0);
+        });
+}
+
 
 var page_index = 0;
 var char_id = "SO";